@@ -0,0 +1,35 @@
+// gpio_raw_toggle.rs - Measures how fast a pin can be toggled through RawOutput.
+//
+// The pin is toggled as fast as possible for a fixed number of cycles, after which
+// the elapsed time is used to calculate the toggle rate. To verify the actual output
+// frequency, connect a logic analyzer or oscilloscope to the pin.
+
+use std::error::Error;
+use std::time::Instant;
+
+use rppal::gpio::Gpio;
+
+// Gpio uses BCM pin numbering. BCM GPIO 23 is tied to physical pin 16.
+const GPIO_OUTPUT: u8 = 23;
+const CYCLES: u32 = 10_000_000;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let pin = Gpio::new()?.get(GPIO_OUTPUT)?.into_output_low();
+    let raw = pin.raw_output()?;
+
+    let start = Instant::now();
+    for _ in 0..CYCLES {
+        raw.set_high();
+        raw.set_low();
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{} cycles in {:?} ({:.2} MHz)",
+        CYCLES,
+        elapsed,
+        f64::from(CYCLES) / elapsed.as_secs_f64() / 1_000_000.0
+    );
+
+    Ok(())
+}
@@ -4,6 +4,33 @@
 //! accessing the registers through either `/dev/gpiomem` or `/dev/mem`. GPIO interrupts
 //! are configured using the `gpiochip` character device.
 //!
+//! Enabling the `cdev-only` feature replaces the memory-mapped register access with the
+//! `gpiochip` character device, so `/dev/gpiomem` and `/dev/mem` are never opened. Reading
+//! and changing a pin's logic level, mode and built-in pull-up/pull-down resistors all go
+//! through `ioctl` calls, which is considerably slower. Only the [`Input`] and [`Output`]
+//! modes are supported, and changing a pin to any of the alternate function modes is
+//! ignored. Pins configured as outputs stay requested from the kernel until they're
+//! changed back to inputs. A pin's level can only be read if it's configured as an input
+//! or output through RPPAL, or in use by an interrupt trigger, [`LineGroup`] or
+//! [`EventStream`], since requesting a line that's in use by a peripheral could reset it
+//! to an input. Other pins keep reading the level that was last read successfully, which
+//! defaults to [`Low`]. Every pin is read with a separate `ioctl` call, so methods that
+//! read multiple pins at once, like [`PinMask::read`], [`InputBus::read`] and
+//! [`Gpio::read_pins_u64`], don't capture their levels at the same point in time.
+//! [`GpioBuilder::gpiomem`] has no effect, and
+//! [`Gpio::install_cleanup_handler`], [`OutputPin::set_clock`] and [`DmaWave`] return an
+//! [`Error::Io`] error.
+//!
+//! Some container runtimes and security policies allow the registers to be mapped, but
+//! silently drop any writes. When enabled through [`GpioBuilder::verify_register_writes`],
+//! RPPAL briefly changes a register value and reads it back, and falls back to the same
+//! `gpiochip` based access the `cdev-only` feature uses if the change was dropped.
+//! [`Gpio::register_backend`] returns the interface that's currently in use. After falling
+//! back, [`Gpio::install_cleanup_handler`] returns an [`Error::Io`] error, and the drive
+//! modes set through [`OutputPin::set_open_drain_cdev`] become available. The check is
+//! only performed on the Raspberry Pi 4, 5 and related models, since earlier SoCs don't
+//! have a suitable register.
+//!
 //! ## Pins
 //!
 //! GPIO pins are retrieved from a [`Gpio`] instance by their BCM GPIO number by calling
@@ -22,7 +49,8 @@
 //! Use [`InputPin::set_reset_on_drop(false)`], [`OutputPin::set_reset_on_drop(false)`]
 //! or [`IoPin::set_reset_on_drop(false)`], respectively, to disable this behavior.
 //! Note that `drop` methods aren't called when a process is abnormally terminated (for
-//! instance when a `SIGINT` signal isn't caught).
+//! instance when a `SIGINT` signal isn't caught). [`Gpio::install_cleanup_handler`] can be
+//! used to reset pins when a `SIGINT` or `SIGTERM` signal is received.
 //!
 //! ## Interrupts
 //!
@@ -51,6 +79,26 @@
 //! function call overhead, typical jitter is expected to be up to 10 µs on debug builds, and up to
 //! 2 µs on release builds.
 //!
+//! Because of these limitations, frequencies above 10 kHz (periods shorter than 100 µs) are
+//! rejected with `Err(`[`Error::FrequencyTooHigh`]`)`.
+//!
+//! [`OutputPin::set_timer_pwm`] offers an alternative that relies on kernel timers instead of
+//! busy-waiting. All timer-based PWM signals share a single thread, which uses less CPU time
+//! when driving several pins, at the cost of some additional jitter.
+//!
+//! ## Single-core models
+//!
+//! On single-core models like the Raspberry Pi Zero and Raspberry Pi 1, a thread that
+//! busy-waits keeps every other thread from running. Synchronous and asynchronous interrupt
+//! triggers block in `epoll_wait` until an event arrives, so they don't use any CPU time
+//! while waiting, which makes them preferable to repeatedly reading a pin's level in a
+//! loop. Software-based PWM threads only sleep on single-core models, without busy-waiting,
+//! which increases jitter. [`OutputPin::set_timer_pwm`] or the hardware [`Pwm`] peripheral
+//! are better suited for PWM signals on these models. Methods that wait for a pin to
+//! reach a certain level, like [`Gpio::strobe_and_wait`], block on an interrupt trigger
+//! rather than polling the level. Methods with timing-critical delays, like
+//! [`Gpio::pulse_sequence`], sleep instead of busy-waiting.
+//!
 //! ## Examples
 //!
 //! Basic example:
@@ -94,11 +142,31 @@
 //! can be found at [raspberrypi/linux#1225] and [raspberrypi/linux#2289].
 //!
 //! [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+//! [`Error::FrequencyTooHigh`]: enum.Error.html#variant.FrequencyTooHigh
 //! [`PermissionDenied`]: enum.Error.html#variant.PermissionDenied
 //! [raspberrypi/linux#1225]: https://github.com/raspberrypi/linux/issues/1225
 //! [raspberrypi/linux#2289]: https://github.com/raspberrypi/linux/issues/2289
 //! [`Gpio`]: struct.Gpio.html
 //! [`Gpio::get`]: struct.Gpio.html#method.get
+//! [`Gpio::new`]: struct.Gpio.html#method.new
+//! [`Gpio::read_pins_u64`]: struct.Gpio.html#method.read_pins_u64
+//! [`LineGroup`]: struct.LineGroup.html
+//! [`EventStream`]: struct.EventStream.html
+//! [`PinMask::read`]: struct.PinMask.html#method.read
+//! [`InputBus::read`]: struct.InputBus.html#method.read
+//! [`Gpio::strobe_and_wait`]: struct.Gpio.html#method.strobe_and_wait
+//! [`Gpio::pulse_sequence`]: struct.Gpio.html#method.pulse_sequence
+//! [`GpioBuilder::gpiomem`]: struct.GpioBuilder.html#method.gpiomem
+//! [`Input`]: enum.Mode.html#variant.Input
+//! [`Output`]: enum.Mode.html#variant.Output
+//! [`Low`]: enum.Level.html#variant.Low
+//! [`OutputPin::set_open_drain_cdev`]: struct.OutputPin.html#method.set_open_drain_cdev
+//! [`GpioBuilder::verify_register_writes`]: struct.GpioBuilder.html#method.verify_register_writes
+//! [`Gpio::register_backend`]: struct.Gpio.html#method.register_backend
+//! [`OutputPin::set_clock`]: struct.OutputPin.html#method.set_clock
+//! [`DmaWave`]: struct.DmaWave.html
+//! [`Error::Io`]: enum.Error.html#variant.Io
+//! [`Gpio::install_cleanup_handler`]: struct.Gpio.html#method.install_cleanup_handler
 //! [`Gpio::poll_interrupts`]: struct.Gpio.html#method.poll_interrupts
 //! [`Pin`]: struct.Pin.html
 //! [`InputPin`]: struct.InputPin.html
@@ -108,23 +176,35 @@
 //! [`InputPin::set_async_interrupt`]: struct.InputPin.html#method.set_async_interrupt
 //! [`OutputPin`]: struct.OutputPin.html
 //! [`OutputPin::set_reset_on_drop(false)`]: struct.OutputPin.html#method.set_reset_on_drop
+//! [`OutputPin::set_timer_pwm`]: struct.OutputPin.html#method.set_timer_pwm
 //! [`IoPin`]: struct.IoPin.html
 //! [`IoPin::set_reset_on_drop(false)`]: struct.IoPin.html#method.set_reset_on_drop
 //! [`Pwm`]: ../pwm/struct.Pwm.html
 #![allow(clippy::missing_transmute_annotations)]
 
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
 use std::io;
 use std::mem::MaybeUninit;
 use std::ops::Not;
 use std::os::unix::io::AsRawFd;
+use std::panic;
+use std::path::{Path, PathBuf};
 use std::result;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex, Once, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+mod bus;
+mod button;
+mod chip;
+mod cleanup;
+mod clock;
+mod config;
+mod dma;
 mod epoll;
+mod event_stream;
 mod gpiomem;
 #[cfg(any(
     feature = "embedded-hal-0",
@@ -136,13 +216,31 @@ mod hal;
 mod hal_unproven;
 mod interrupt;
 mod ioctl;
+mod lines;
+mod mask;
+pub mod mem;
+mod permissions;
 mod pin;
+pub mod pins;
 mod soft_pwm;
+mod timer_pwm;
+mod touch;
 
 use crate::system;
 use crate::system::DeviceInfo;
 
-pub use self::pin::{InputPin, IoPin, OutputPin, Pin};
+pub use self::bus::{InputBus, OutputBus};
+pub use self::button::{Button, ButtonEvent};
+pub use self::chip::{list_chips, ChipInfo};
+pub use self::clock::ClockSource;
+pub use self::config::{ConfigGuard, PinState};
+pub use self::dma::DmaWave;
+pub use self::event_stream::{EventStream, PinEvent};
+pub use self::lines::LineGroup;
+pub use self::mask::PinMask;
+pub use self::permissions::PermissionProblem;
+pub use self::pin::{InputPin, IoPin, OutputHandle, OutputPin, Pin, RawOutput};
+pub use self::touch::TouchSensor;
 
 /// Errors that can occur when accessing the GPIO peripheral.
 #[derive(Debug)]
@@ -186,6 +284,38 @@ pub enum Error {
     Io(io::Error),
     /// Thread panicked.
     ThreadPanic,
+    /// The requested frequency is too high for software-based PWM.
+    ///
+    /// Software-based PWM is limited to a maximum frequency of 10 kHz (a minimum
+    /// period of 100 µs). More information can be found [here].
+    ///
+    /// [here]: index.html#software-based-pwm
+    FrequencyTooHigh(f64),
+    /// Pin is not exposed on the GPIO header.
+    ///
+    /// The original Raspberry Pi A and B models feature a 26-pin header, which only
+    /// exposes a subset of the BCM GPIO pins. Besides the header generation, the
+    /// available pins also depend on the board revision. Interrupts can't be configured
+    /// for pins that aren't routed to the GPIO header.
+    PinNotOnHeader(u8),
+    /// Pin is claimed by the kernel.
+    ///
+    /// The kernel reports the pin as in use, either because a device tree overlay assigned
+    /// it to a peripheral or kernel driver, or because another process requested it through
+    /// the `gpiochip` character device. Changing the pin's mode or output state through
+    /// the GPIO registers would interfere with its current owner. Disable the
+    /// corresponding overlay or driver, or disable [`GpioBuilder::check_kernel_claims`]
+    /// to skip this check.
+    ///
+    /// [`GpioBuilder::check_kernel_claims`]: struct.GpioBuilder.html#method.check_kernel_claims
+    PinClaimed(u8),
+    /// Pin wasn't reset to its original mode.
+    ///
+    /// After resetting the pin, its mode didn't match the mode it had before it was
+    /// configured. This can happen when a kernel driver or another process changed the
+    /// pin in the meantime, or when the original mode can't be selected, for instance
+    /// an alternate function mode with the `cdev-only` feature enabled.
+    ResetFailed(u8),
 }
 
 impl fmt::Display for Error {
@@ -197,6 +327,20 @@ impl fmt::Display for Error {
             Error::PermissionDenied(ref path) => write!(f, "Permission denied: {}", path),
             Error::Io(ref err) => write!(f, "I/O error: {}", err),
             Error::ThreadPanic => write!(f, "Thread panicked"),
+            Error::FrequencyTooHigh(frequency) => {
+                write!(
+                    f,
+                    "Frequency too high for software-based PWM: {} Hz",
+                    frequency
+                )
+            }
+            Error::PinNotOnHeader(pin) => {
+                write!(f, "Pin {} is not exposed on the GPIO header", pin)
+            }
+            Error::PinClaimed(pin) => write!(f, "Pin {} is claimed by the kernel", pin),
+            Error::ResetFailed(pin) => {
+                write!(f, "Pin {} wasn't reset to its original mode", pin)
+            }
         }
     }
 }
@@ -220,6 +364,7 @@ pub type Result<T> = result::Result<T, Error>;
 
 /// Pin modes.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Mode {
     Input,
@@ -257,6 +402,7 @@ impl fmt::Display for Mode {
 
 /// Pin logic levels.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Level {
     Low = 0,
@@ -305,6 +451,7 @@ impl Not for Level {
 
 /// Built-in pull-up/pull-down resistor states.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Bias {
     Off = 0b00,
     PullDown = 0b01,
@@ -322,6 +469,13 @@ impl fmt::Display for Bias {
 }
 
 /// Interrupt trigger conditions.
+///
+/// `Trigger::Disabled` turns off interrupts when passed to [`InputPin::set_interrupt`] or
+/// [`InputPin::set_async_interrupt`], which allows interrupts to be configured from data
+/// without special-casing the disabled state.
+///
+/// [`InputPin::set_interrupt`]: struct.InputPin.html#method.set_interrupt
+/// [`InputPin::set_async_interrupt`]: struct.InputPin.html#method.set_async_interrupt
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Trigger {
     Disabled = 0,
@@ -341,12 +495,125 @@ impl fmt::Display for Trigger {
     }
 }
 
+/// Clock used for interrupt trigger event timestamps.
+///
+/// `EventClock` is used to configure the clock source for [`Event::timestamp`] through
+/// [`InputPin::set_async_interrupt_with_clock`].
+///
+/// [`Event::timestamp`]: struct.Event.html#structfield.timestamp
+/// [`InputPin::set_async_interrupt_with_clock`]: struct.InputPin.html#method.set_async_interrupt_with_clock
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EventClock {
+    /// Monotonic clock (`CLOCK_MONOTONIC`), which measures the elapsed time since the
+    /// system was booted. Isn't affected by changes to the system time, which makes it
+    /// suitable for measuring intervals between events.
+    Monotonic,
+    /// Wall-clock time (`CLOCK_REALTIME`), which measures the elapsed time since the Unix
+    /// epoch. Useful for correlating events with logs across multiple systems, but can
+    /// jump when the system time is adjusted.
+    Realtime,
+}
+
+impl fmt::Display for EventClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EventClock::Monotonic => write!(f, "Monotonic"),
+            EventClock::Realtime => write!(f, "Realtime"),
+        }
+    }
+}
+
+/// Kernel interface used to detect interrupt trigger events.
+///
+/// The active backend is returned by [`Gpio::interrupt_backend`].
+///
+/// [`Gpio::interrupt_backend`]: struct.Gpio.html#method.interrupt_backend
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+pub enum InterruptBackend {
+    /// Version 2 of the `gpiochip` character device uAPI, introduced in Linux 5.10.
+    ///
+    /// Edges are detected by the kernel's GPIO driver, and timestamped in the interrupt
+    /// handler. Events are read from a line request file descriptor.
+    CdevV2,
+}
+
+impl fmt::Display for InterruptBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            InterruptBackend::CdevV2 => write!(f, "gpiochip uAPI v2"),
+        }
+    }
+}
+
+/// Interface used to change pin modes and levels.
+///
+/// The active backend is returned by [`Gpio::register_backend`].
+///
+/// [`Gpio::register_backend`]: struct.Gpio.html#method.register_backend
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+pub enum RegisterBackend {
+    /// The GPIO registers are memory-mapped through `/dev/gpiomem`, `/dev/gpiomem0` or
+    /// `/dev/mem`, and accessed directly.
+    MemoryMapped,
+    /// Pins are configured through line requests on the `gpiochip` character device.
+    ///
+    /// This backend is used when the `cdev-only` feature is enabled, or when
+    /// [`GpioBuilder::verify_register_writes`] is enabled and writes to the memory-mapped
+    /// registers are found to be ignored.
+    ///
+    /// [`GpioBuilder::verify_register_writes`]: struct.GpioBuilder.html#method.verify_register_writes
+    Cdev,
+}
+
+impl fmt::Display for RegisterBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RegisterBackend::MemoryMapped => write!(f, "Memory-mapped registers"),
+            RegisterBackend::Cdev => write!(f, "gpiochip character device"),
+        }
+    }
+}
+
+/// Return value for asynchronous interrupt callbacks configured through
+/// [`InputPin::set_async_interrupt_until`].
+///
+/// [`InputPin::set_async_interrupt_until`]: struct.InputPin.html#method.set_async_interrupt_until
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CallbackAction {
+    /// Keep the interrupt trigger configured, and wait for the next event.
+    Continue,
+    /// Stop monitoring the pin, and release the interrupt trigger.
+    Stop,
+}
+
+impl fmt::Display for CallbackAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CallbackAction::Continue => write!(f, "Continue"),
+            CallbackAction::Stop => write!(f, "Stop"),
+        }
+    }
+}
+
 /// Interrupt trigger event.
 #[derive(Debug, Copy, Clone)]
 pub struct Event {
     /// Best estimate of time of event occurrence, measured in elapsed time since the system was booted.
+    ///
+    /// If the interrupt trigger was configured with [`EventClock::Realtime`], the timestamp
+    /// is measured in elapsed time since the Unix epoch instead.
+    ///
+    /// [`EventClock::Realtime`]: enum.EventClock.html#variant.Realtime
     pub timestamp: Duration,
     /// Sequence number for this event in the sequence of interrupt trigger events for this pin.
+    ///
+    /// The sequence number is assigned by the kernel, starting at 1 for the first event
+    /// after the interrupt trigger is configured, and increases by 1 for every edge the
+    /// kernel detects. A gap between the sequence numbers of two consecutive events
+    /// indicates events were dropped, for instance because the kernel's event buffer
+    /// overflowed. Reconfiguring the interrupt trigger restarts the sequence.
     pub seqno: u32,
     /// Interrupt trigger. This will contain either [Trigger::RisingEdge] or [Trigger::FallingEdge].
     pub trigger: Trigger,
@@ -366,20 +633,39 @@ impl Default for Event {
 // a cloned Arc.
 pub(crate) struct GpioState {
     gpio_mem: Box<dyn gpiomem::GpioRegisters>,
+    register_backend: RegisterBackend,
     cdev: std::fs::File,
+    // Line requests the gpiochip backend can read levels through
+    line_registry: Arc<ioctl::LineRegistry>,
     sync_interrupts: Mutex<interrupt::EventLoop>,
+    async_workers: interrupt::AsyncWorkers,
+    // Shared thread for timer-based software PWM, running while any channel exists
+    timer_pwm: Mutex<Weak<timer_pwm::TimerLoop>>,
     pins_taken: [AtomicBool; u8::MAX as usize],
+    // Mode + 1 each pin should be reset to by the cleanup handler, or 0 if not applicable
+    reset_modes: [AtomicU8; u8::MAX as usize],
     gpio_lines: u8,
+    header_pins: u64,
+    // Pins and levels applied by our panic hook
+    safe_state: Mutex<Vec<(u8, Level)>>,
+    panic_hook: Once,
 }
 
 impl fmt::Debug for GpioState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("EventLoop")
             .field("gpio_mem", &self.gpio_mem)
+            .field("register_backend", &self.register_backend)
             .field("cdev", &self.cdev)
             .field("sync_interrupts", &self.sync_interrupts)
+            .field("async_workers", &self.async_workers)
+            .field("timer_pwm", &self.timer_pwm)
             .field("pins_taken", &format_args!("{{ .. }}"))
+            .field("reset_modes", &format_args!("{{ .. }}"))
             .field("gpio_lines", &self.gpio_lines)
+            .field("header_pins", &self.header_pins)
+            .field("safe_state", &self.safe_state)
+            .field("panic_hook", &self.panic_hook)
             .finish()
     }
 }
@@ -388,11 +674,54 @@ impl fmt::Debug for GpioState {
 #[derive(Clone, Debug)]
 pub struct Gpio {
     inner: Arc<GpioState>,
+    reset_on_drop: bool,
+    check_kernel_claims: bool,
+    aliases: HashMap<String, u8>,
 }
 
 impl Gpio {
     /// Constructs a new `Gpio`.
+    ///
+    /// All `Gpio` instances, and any pins retrieved through them, share the same memory
+    /// mapping of the GPIO registers and the same `gpiochip` file descriptor. Calling
+    /// `new` while another `Gpio` instance or pin exists doesn't remap `/dev/gpiomem`.
+    /// The mapping is only released after the last instance goes out of scope. To avoid
+    /// repeatedly remapping the registers in code that frequently creates and drops
+    /// `Gpio` instances, keep a single instance alive, or clone it as needed.
+    ///
+    /// Use [`GpioBuilder`] if you need to select a specific `gpiochip` or memory
+    /// device.
+    ///
+    /// [`GpioBuilder`]: struct.GpioBuilder.html
     pub fn new() -> Result<Gpio> {
+        GpioBuilder::new().build()
+    }
+
+    /// Checks whether the current process has the permissions required to access the GPIO
+    /// peripheral, without constructing a `Gpio` instance.
+    ///
+    /// `check_permissions` verifies read and write access to `/dev/gpiomem` (or
+    /// `/dev/gpiomem0` on the Raspberry Pi 5) and the `gpiochip` character device
+    /// [`Gpio::new`] detects based on its label. `/dev/gpiomem` isn't checked when the
+    /// `cdev-only` feature is enabled. If access is denied, the returned
+    /// [`PermissionProblem`] describes what's missing, which can be used to show an
+    /// actionable message at startup, rather than failing while configuring pins.
+    ///
+    /// A successful check doesn't guarantee [`Gpio::new`] succeeds, since the devices may
+    /// still be unavailable for other reasons. Access to `/dev/mem`, which is used as a
+    /// fallback when `/dev/gpiomem` isn't available, isn't checked. Use
+    /// [`GpioBuilder::check_permissions`] to check the devices selected through a
+    /// [`GpioBuilder`].
+    ///
+    /// [`PermissionProblem`]: enum.PermissionProblem.html
+    /// [`Gpio::new`]: #method.new
+    /// [`GpioBuilder`]: struct.GpioBuilder.html
+    /// [`GpioBuilder::check_permissions`]: struct.GpioBuilder.html#method.check_permissions
+    pub fn check_permissions() -> result::Result<(), PermissionProblem> {
+        GpioBuilder::new().check_permissions()
+    }
+
+    fn with_options(options: &GpioBuilder) -> Result<Gpio> {
         // Replace this when std::sync::SyncLazy is stabilized. https://github.com/rust-lang/rust/issues/74465
 
         // Shared state between Gpio and Pin instances. GpioState is dropped after
@@ -417,36 +746,94 @@ impl Gpio {
         if let Some(ref state) = weak_state.upgrade() {
             Ok(Gpio {
                 inner: state.clone(),
+                reset_on_drop: options.reset_on_drop,
+                check_kernel_claims: options.check_kernel_claims,
+                aliases: HashMap::new(),
             })
         } else {
             let device_info = DeviceInfo::new().map_err(|_| Error::UnknownModel)?;
 
-            let gpio_mem: Box<dyn gpiomem::GpioRegisters> = match device_info.gpio_interface() {
-                system::GpioInterface::Bcm => Box::new(gpiomem::bcm::GpioMem::open()?),
-                system::GpioInterface::Rp1 => Box::new(gpiomem::rp1::GpioMem::open()?),
+            #[cfg(not(feature = "cdev-only"))]
+            let gpio_mem: Box<dyn gpiomem::GpioRegisters> = {
+                let gpiomem_path = options.gpiomem.as_deref();
+                match device_info.gpio_interface() {
+                    system::GpioInterface::Bcm => {
+                        Box::new(gpiomem::bcm::GpioMem::open(gpiomem_path)?)
+                    }
+                    system::GpioInterface::Rp1 => {
+                        Box::new(gpiomem::rp1::GpioMem::open(gpiomem_path)?)
+                    }
+                }
+            };
+
+            let cdev = match options.gpiochip {
+                Some(ref path) => ioctl::open_gpiochip(path)?,
+                None => ioctl::find_gpiochip()?,
             };
+            let line_registry = Arc::new(ioctl::LineRegistry::default());
 
-            let cdev = ioctl::find_gpiochip()?;
+            // Some containers and security policies allow the memory mapping, but silently
+            // drop any writes. Fall back to the gpiochip for mode and level changes.
+            #[cfg(not(feature = "cdev-only"))]
+            let (gpio_mem, register_backend): (Box<dyn gpiomem::GpioRegisters>, _) =
+                if !options.verify_register_writes || gpio_mem.verify_writable() {
+                    (gpio_mem, RegisterBackend::MemoryMapped)
+                } else {
+                    (
+                        Box::new(gpiomem::cdev::GpioMem::open(
+                            cdev.try_clone()?,
+                            line_registry.clone(),
+                        )),
+                        RegisterBackend::Cdev,
+                    )
+                };
+
+            // All register access goes through a separate handle to the same gpiochip
+            #[cfg(feature = "cdev-only")]
+            let (gpio_mem, register_backend): (Box<dyn gpiomem::GpioRegisters>, _) = (
+                Box::new(gpiomem::cdev::GpioMem::open(
+                    cdev.try_clone()?,
+                    line_registry.clone(),
+                )),
+                RegisterBackend::Cdev,
+            );
             let sync_interrupts = Mutex::new(interrupt::EventLoop::new(
                 cdev.as_raw_fd(),
+                line_registry.clone(),
                 u8::MAX as usize,
             )?);
+            let async_workers = interrupt::AsyncWorkers::new(options.async_interrupt_threads)?;
             let pins_taken = init_array!(AtomicBool::new(false), u8::MAX as usize);
+            let reset_modes = init_array!(AtomicU8::new(0), u8::MAX as usize);
             let gpio_lines = device_info.gpio_lines();
+            let header_pins = device_info.header_pins();
 
             let gpio_state = Arc::new(GpioState {
                 gpio_mem,
+                register_backend,
                 cdev,
+                line_registry,
                 sync_interrupts,
+                async_workers,
+                timer_pwm: Mutex::new(Weak::new()),
                 pins_taken,
+                reset_modes,
                 gpio_lines,
+                header_pins,
+                safe_state: Mutex::new(Vec::new()),
+                panic_hook: Once::new(),
             });
 
             // Store a weak reference to our state. This gets dropped when
             // all Gpio and Pin instances go out of scope.
             *weak_state = Arc::downgrade(&gpio_state);
 
-            Ok(Gpio { inner: gpio_state })
+            Ok(Gpio {
+                inner: gpio_state,
+                reset_on_drop: options.reset_on_drop,
+                check_kernel_claims: options.check_kernel_claims,
+                aliases: HashMap::new(),
+            })
         }
     }
 
@@ -457,11 +844,23 @@ impl Gpio {
     /// After a [`Pin`] (or a derived [`InputPin`], [`OutputPin`] or [`IoPin`]) goes out
     /// of scope, it can be retrieved again through another `get` call.
     ///
+    /// `get` doesn't change the pin's mode, so it can be used to reserve all required pins
+    /// up front, and detect conflicts early, before deciding how each pin is configured.
+    /// The mode is only changed once the [`Pin`] is converted through one of its `into_`
+    /// methods.
+    ///
+    /// If [`GpioBuilder::check_kernel_claims`] is enabled, and the kernel reports the pin
+    /// as in use, for instance because a device tree overlay assigned it to a peripheral,
+    /// `get` returns `Err(`[`Error::PinClaimed`]`)`. Line requests held by rppal itself
+    /// don't count as a claim.
+    ///
     /// [`Pin`]: struct.Pin.html
     /// [`InputPin`]: struct.InputPin.html
     /// [`OutputPin`]: struct.OutputPin.html
     /// [`IoPin`]: struct.IoPin.html
     /// [`Error::PinUsed`]: enum.Error.html#variant.PinUsed
+    /// [`Error::PinClaimed`]: enum.Error.html#variant.PinClaimed
+    /// [`GpioBuilder::check_kernel_claims`]: struct.GpioBuilder.html#method.check_kernel_claims
     pub fn get(&self, pin: u8) -> Result<Pin> {
         if pin >= self.inner.gpio_lines {
             return Err(Error::PinNotAvailable(pin));
@@ -473,11 +872,366 @@ impl Gpio {
             .is_err()
         {
             // Pin is taken
-            Err(Error::PinUsed(pin))
-        } else {
-            // Return an owned Pin
-            Ok(Pin::new(pin, self.inner.clone()))
+            return Err(Error::PinUsed(pin));
+        }
+
+        if self.check_kernel_claims && self.is_claimed(pin) {
+            self.inner.pins_taken[pin as usize].store(false, Ordering::SeqCst);
+
+            return Err(Error::PinClaimed(pin));
+        }
+
+        // Return an owned Pin
+        Ok(Pin::new(pin, self.inner.clone(), self.reset_on_drop))
+    }
+
+    // Returns true if the kernel reports the pin as in use by anything other than rppal's
+    // own line requests. Lines that can't be queried are treated as unclaimed, so pins
+    // remain usable with gpiochip devices that don't expose all of them.
+    fn is_claimed(&self, pin: u8) -> bool {
+        if self.inner.line_registry.contains(pin) || self.inner.gpio_mem.holds_line(pin) {
+            return false;
+        }
+
+        ioctl::LineInfo::new(self.inner.cdev.as_raw_fd(), u32::from(pin))
+            .map_or(false, |line_info| line_info.flags().used())
+    }
+
+    /// Registers a logical name for the specified BCM GPIO number.
+    ///
+    /// Aliases make it possible to define the wiring in a single place, and refer to pins
+    /// by name with [`get_by_name`] in the rest of the application. Registering an
+    /// existing name again replaces the previous pin number. Aliases are stored in this
+    /// `Gpio` instance, and copied when it's cloned.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` if the pin doesn't exist.
+    ///
+    /// [`get_by_name`]: #method.get_by_name
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn alias(&mut self, name: &str, pin: u8) -> Result<()> {
+        if pin >= self.inner.gpio_lines {
+            return Err(Error::PinNotAvailable(pin));
+        }
+
+        self.aliases.insert(name.to_owned(), pin);
+
+        Ok(())
+    }
+
+    /// Returns a [`Pin`] for the BCM GPIO number registered under `name` through [`alias`].
+    ///
+    /// `get_by_name` behaves the same as [`get`], and returns the same errors. Returns an
+    /// `io::ErrorKind::NotFound` error if no pin was registered under `name`.
+    ///
+    /// [`Pin`]: struct.Pin.html
+    /// [`alias`]: #method.alias
+    /// [`get`]: #method.get
+    pub fn get_by_name(&self, name: &str) -> Result<Pin> {
+        match self.aliases.get(name) {
+            Some(&pin) => self.get(pin),
+            None => Err(Error::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No pin registered as {}", name),
+            ))),
+        }
+    }
+
+    /// Waits until all previous writes to the GPIO registers have completed.
+    ///
+    /// The GPIO registers are accessed through a memory-mapped peripheral bus, which may
+    /// buffer writes. `sync` adds a memory barrier, and reads back one of the registers,
+    /// which guarantees any previous changes to a pin's output state, mode or bias have
+    /// reached the peripheral before `sync` returns. This can be used in timing-critical
+    /// bit-banging code to make sure each step happens before the next one starts.
+    ///
+    /// On most Raspberry Pi models, writes are already completed in order, in which case
+    /// `sync` only adds a small delay.
+    #[inline]
+    pub fn sync(&self) {
+        self.inner.gpio_mem.flush();
+    }
+
+    /// Applies a sequence of output states to multiple pins, holding each step for the
+    /// specified duration before moving on to the next one.
+    ///
+    /// Each step sets the pin's output state to the specified [`Level`], and then waits
+    /// for the step's duration. Steps are applied in order, which makes `pulse_sequence`
+    /// suitable for strobing multiplexed displays, charlieplexed LEDs and similar circuits.
+    /// Since every step borrows its pin mutably, each pin can only appear in a single step.
+    ///
+    /// Each step's deadline is calculated relative to the start of the sequence, so timing
+    /// errors don't accumulate, and the output states are changed directly through the
+    /// GPIO registers, which keeps the overhead between steps low. Timing accuracy has the
+    /// same limits as [`OutputPin::play_pattern`]. `pulse_sequence` blocks the calling
+    /// thread until the entire sequence has been applied.
+    ///
+    /// Returns an [`Error::Io`] error with [`io::ErrorKind::InvalidInput`] without changing
+    /// any output states if a software-based PWM signal or clock is active on one of the
+    /// pins, since it would interfere with the sequence.
+    ///
+    /// [`Level`]: enum.Level.html
+    /// [`OutputPin::play_pattern`]: struct.OutputPin.html#method.play_pattern
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn pulse_sequence(&self, steps: &[(&mut OutputPin, Level, Duration)]) -> Result<()> {
+        if steps.iter().any(|(pin, _, _)| pin.has_signal()) {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Pins in a pulse sequence can't have an active PWM signal or clock",
+            )));
+        }
+
+        let start_ns = soft_pwm::get_time_ns();
+        let mut deadline_ns = 0i64;
+        for &(ref pin, level, duration) in steps {
+            match level {
+                Level::Low => self.inner.gpio_mem.set_low(pin.pin()),
+                Level::High => self.inner.gpio_mem.set_high(pin.pin()),
+            }
+
+            deadline_ns = deadline_ns.saturating_add(duration.as_nanos() as i64);
+            soft_pwm::wait_until(start_ns, deadline_ns);
+        }
+
+        Ok(())
+    }
+
+    /// Changes the modes of a group of pins, and returns a [`ConfigGuard`] that restores
+    /// their original modes when it goes out of scope.
+    ///
+    /// The modes are applied in the order they're specified. If a pin is listed multiple
+    /// times, the last mode is applied, and the mode the pin had before the call is
+    /// restored.
+    ///
+    /// The pins don't need to be retrieved through [`get`]. Changes made by
+    /// `apply_config_reversible` aren't tracked by any [`Pin`], [`InputPin`], [`OutputPin`]
+    /// or [`IoPin`] instances for the same pins, which may restore their own original modes
+    /// when they go out of scope. Avoid using `apply_config_reversible` on pins
+    /// that are in use elsewhere.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` without changing any modes if the GPIO
+    /// peripheral doesn't expose any of the specified pins.
+    ///
+    /// [`ConfigGuard`]: struct.ConfigGuard.html
+    /// [`get`]: #method.get
+    /// [`Pin`]: struct.Pin.html
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`IoPin`]: struct.IoPin.html
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn apply_config_reversible(&self, config: &[(u8, Mode)]) -> Result<ConfigGuard> {
+        ConfigGuard::new(self.inner.clone(), config)
+    }
+
+    /// Consumes an [`InputPin`], [`OutputPin`] or [`IoPin`], changes its mode, and returns
+    /// the pin as an [`IoPin`], together with a [`ConfigGuard`] that restores the mode the
+    /// pin had at the time of the call.
+    ///
+    /// The pin is always returned as an [`IoPin`], regardless of the type that was passed
+    /// in, since an [`IoPin`] is the only pin type that can represent every [`Mode`].
+    ///
+    /// `reconfigure` is intended for runtime pin role changes, for instance when a pin that
+    /// was used as an output temporarily switches to an alternate function. The pin stays
+    /// owned throughout, so it can't be retrieved by another caller in the meantime. The
+    /// mode at the time of the call is restored when the [`ConfigGuard`] is dropped, or
+    /// when [`ConfigGuard::restore`] is called, even if the [`IoPin`] is still in use.
+    ///
+    /// The [`IoPin`] keeps the `reset_on_drop` setting of the original pin, and resets the
+    /// pin to the mode it had before the original pin was constructed when it goes out of
+    /// scope. The conversion stops any interrupt triggers, software-based PWM signals and
+    /// general-purpose clocks.
+    ///
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`IoPin`]: struct.IoPin.html
+    /// [`ConfigGuard`]: struct.ConfigGuard.html
+    /// [`ConfigGuard::restore`]: struct.ConfigGuard.html#method.restore
+    /// [`Mode`]: enum.Mode.html
+    pub fn reconfigure<P: Into<IoPin>>(&self, pin: P, mode: Mode) -> Result<(IoPin, ConfigGuard)> {
+        let mut io_pin = pin.into();
+        let pin = io_pin.pin();
+
+        // The IoPin applies the new mode, so it's only written once
+        let config_guard = ConfigGuard::record(
+            self.inner.clone(),
+            vec![(pin, self.inner.gpio_mem.mode(pin))],
+        );
+
+        io_pin.set_mode(mode);
+
+        Ok((io_pin, config_guard))
+    }
+
+    /// Returns a snapshot of the mode and logic level of every pin exposed by the GPIO
+    /// peripheral, sorted by BCM GPIO number.
+    ///
+    /// The snapshot can be stored, compared against other configurations, and reapplied
+    /// later through [`import_state`]. The built-in pull-up/pull-down resistor configuration
+    /// is read back from the GPIO registers on the Raspberry Pi 4, 5 and related models. On
+    /// earlier models, the registers are write-only, and [`PinState::bias`] is set to `None`.
+    ///
+    /// [`import_state`]: #method.import_state
+    /// [`PinState::bias`]: struct.PinState.html#structfield.bias
+    pub fn export_state(&self) -> Vec<PinState> {
+        config::export_state(&self.inner)
+    }
+
+    /// Applies the modes, output states and pull-up/pull-down resistor configurations
+    /// stored in `states`.
+    ///
+    /// For pins configured as outputs, the output state is set before the mode is changed,
+    /// which prevents glitches. The level of pins that aren't configured as outputs is
+    /// ignored, as well as the resistor configuration of pins where [`PinState::bias`] is
+    /// set to `None`.
+    ///
+    /// The pins don't need to be retrieved through [`get`]. Changes made by `import_state`
+    /// aren't tracked by any [`Pin`], [`InputPin`], [`OutputPin`] or [`IoPin`] instances for
+    /// the same pins, which may restore their own original modes when they go out of scope.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` without changing any pins if the GPIO
+    /// peripheral doesn't expose any of the specified pins.
+    ///
+    /// [`PinState::bias`]: struct.PinState.html#structfield.bias
+    /// [`get`]: #method.get
+    /// [`Pin`]: struct.Pin.html
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`IoPin`]: struct.IoPin.html
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn import_state(&self, states: &[PinState]) -> Result<()> {
+        config::import_state(&self.inner, states)
+    }
+
+    /// Requests the specified pins through a single `gpiochip` character device line
+    /// request, and returns a [`LineGroup`] that reads or changes their logic levels with
+    /// a single `ioctl` call.
+    ///
+    /// Duplicate pins are ignored. The pins don't need to be retrieved through [`get`].
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` if any of the pins isn't available on
+    /// this model. If a pin is already requested by another process, or used for an
+    /// interrupt trigger, the kernel returns an [`Error::Io`] error.
+    ///
+    /// [`LineGroup`]: struct.LineGroup.html
+    /// [`get`]: #method.get
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn request_lines(&self, pins: &[u8]) -> Result<LineGroup> {
+        LineGroup::new(&self.inner, pins)
+    }
+
+    /// Monitors the specified pins for rising and falling edges, and returns an
+    /// [`EventStream`] that yields a [`PinEvent`] for every edge as it arrives.
+    ///
+    /// Each event contains the pin, its new logic level, and the kernel's timestamp, which
+    /// makes the stream suitable for logging tools. Duplicate pins are ignored. The pins
+    /// don't need to be retrieved through [`get`], but every pin is configured as an input,
+    /// including any pins that are currently configured as outputs.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` if any of the pins isn't available on
+    /// this model. If a pin is already requested by another process, or used for an
+    /// interrupt trigger, the kernel returns an [`Error::Io`] error.
+    ///
+    /// [`EventStream`]: struct.EventStream.html
+    /// [`PinEvent`]: struct.PinEvent.html
+    /// [`get`]: #method.get
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn event_stream(&self, pins: &[u8]) -> Result<EventStream> {
+        EventStream::new(&self.inner, pins)
+    }
+
+    /// Installs signal handlers for `SIGINT` and `SIGTERM` that reset all pins currently in
+    /// use before the process is terminated.
+    ///
+    /// When a user presses <kbd>Ctrl</kbd> + <kbd>C</kbd>, or the process receives a `SIGTERM`
+    /// signal, `drop` methods aren't called. The installed handler resets the mode of every
+    /// [`InputPin`], [`OutputPin`] and [`IoPin`] that has `reset_on_drop` set to `true`,
+    /// and then restores the signal action that was active before
+    /// `install_cleanup_handler` was called. If that's the default action, the signal is
+    /// raised again to terminate the process. A previously installed signal handler is
+    /// called directly, and a signal that was ignored stays ignored.
+    ///
+    /// Cleanup is best-effort. The built-in pull-up/pull-down resistors aren't disabled,
+    /// and software-based PWM threads and interrupt triggers aren't stopped. Since the
+    /// signal handler can't wait for other threads, any pin whose mode is being changed
+    /// by the interrupted thread at the time the signal arrives is skipped. After calling
+    /// `install_cleanup_handler`, the internal GPIO state is kept alive until the process
+    /// exits. Calling `install_cleanup_handler` more than once has no additional effect.
+    ///
+    /// The `SIGINT` and `SIGTERM` handlers are installed directly with `sigaction` rather
+    /// than through a crate like `signal-hook`, which forwards signals to a separate
+    /// thread. That thread isn't guaranteed to run before the process is terminated, and
+    /// chaining the previous signal actions provides the same interoperability without an
+    /// additional dependency.
+    ///
+    /// Returns an [`Error::Io`] error when [`register_backend`] returns
+    /// [`RegisterBackend::Cdev`], which is the case when the `cdev-only` feature is enabled,
+    /// or RPPAL fell back to the `gpiochip` character device.
+    ///
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`IoPin`]: struct.IoPin.html
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`register_backend`]: #method.register_backend
+    /// [`RegisterBackend::Cdev`]: enum.RegisterBackend.html#variant.Cdev
+    pub fn install_cleanup_handler(&self) -> Result<()> {
+        cleanup::install(&self.inner)
+    }
+
+    /// Configures pins that are forced to a safe logic level when a thread panics.
+    ///
+    /// `set_safe_state_on_panic` installs a panic hook through [`std::panic::set_hook`]. When
+    /// a panic occurs, the hook sets each pin's mode to [`Output`] with the specified level,
+    /// before calling any previously installed panic hook, and before the stack is unwound.
+    /// This offers an additional layer of protection for safety-critical hardware, since
+    /// it also works when compiled with `panic = "abort"`, in which case `drop` methods
+    /// aren't called.
+    ///
+    /// The pins don't need to be retrieved through [`get`], and don't need to be
+    /// available. Calling `set_safe_state_on_panic` again replaces the previously
+    /// configured pins. An empty slice disables the behavior. The hook is only active
+    /// while at least one `Gpio` instance, or any of the pins retrieved through it,
+    /// exists.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` if the GPIO peripheral doesn't expose any
+    /// of the specified pins.
+    ///
+    /// [`std::panic::set_hook`]: https://doc.rust-lang.org/std/panic/fn.set_hook.html
+    /// [`Output`]: enum.Mode.html#variant.Output
+    /// [`get`]: #method.get
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn set_safe_state_on_panic(&self, pins: &[(u8, Level)]) -> Result<()> {
+        if let Some(&(pin, _)) = pins.iter().find(|(pin, _)| *pin >= self.inner.gpio_lines) {
+            return Err(Error::PinNotAvailable(pin));
         }
+
+        *self.inner.safe_state.lock().unwrap() = pins.to_vec();
+
+        let weak_state = Arc::downgrade(&self.inner);
+        self.inner.panic_hook.call_once(move || {
+            let prev_hook = panic::take_hook();
+
+            panic::set_hook(Box::new(move |info| {
+                if let Some(state) = weak_state.upgrade() {
+                    // Don't block if the panic occurred while the lock was held
+                    if let Ok(safe_state) = state.safe_state.try_lock() {
+                        for &(pin, level) in safe_state.iter() {
+                            match level {
+                                Level::Low => state.gpio_mem.set_low(pin),
+                                Level::High => state.gpio_mem.set_high(pin),
+                            }
+
+                            state.gpio_mem.set_mode(pin, Mode::Output);
+                        }
+                    }
+                }
+
+                prev_hook(info);
+            }));
+        });
+
+        Ok(())
     }
 
     /// Blocks until an interrupt is triggered on any of the specified pins, or until a timeout occurs.
@@ -495,6 +1249,8 @@ impl Gpio {
     /// The `timeout` duration indicates how long the call to `poll_interrupts` will block while waiting
     /// for interrupt trigger events, after which an `Ok(None)` is returned.
     /// `timeout` can be set to `None` to wait indefinitely.
+    /// The thread sleeps in a single system call until an event arrives or the timeout
+    /// expires. `timeout` is rounded up to the nearest millisecond.
     ///
     /// When an interrupt event is triggered, `poll_interrupts` returns
     /// `Ok((&`[`InputPin`]`, `[`Event`]`))` containing the corresponding pin and trigger event details. If multiple events
@@ -514,4 +1270,428 @@ impl Gpio {
     ) -> Result<Option<(&'a InputPin, Event)>> {
         (*self.inner.sync_interrupts.lock().unwrap()).poll(pins, reset, timeout)
     }
+
+    /// Returns a snapshot of all pins that currently have a synchronous interrupt
+    /// trigger configured.
+    ///
+    /// Each entry contains the pin's BCM GPIO number, its configured [`Trigger`], and the
+    /// level indicated by the most recent trigger event retrieved through
+    /// [`poll_interrupts`] or [`InputPin::poll_interrupt`]. The level is `None` if no
+    /// event has been retrieved since the interrupt trigger was configured. Entries are
+    /// sorted by pin number.
+    ///
+    /// Asynchronous interrupt triggers configured through [`InputPin::set_async_interrupt`]
+    /// aren't included. `interrupt_status` blocks while another thread is inside
+    /// [`poll_interrupts`] or [`InputPin::poll_interrupt`].
+    ///
+    /// [`Trigger`]: enum.Trigger.html
+    /// [`poll_interrupts`]: #method.poll_interrupts
+    /// [`InputPin::poll_interrupt`]: struct.InputPin.html#method.poll_interrupt
+    /// [`InputPin::set_async_interrupt`]: struct.InputPin.html#method.set_async_interrupt
+    pub fn interrupt_status(&self) -> Vec<(u8, Trigger, Option<Level>)> {
+        self.inner
+            .sync_interrupts
+            .lock()
+            .unwrap()
+            .interrupt_status()
+    }
+
+    /// Reads the logic levels of the specified pins, including pins in both GPIO banks.
+    ///
+    /// Each pin's level is stored at the bit position that matches its BCM GPIO number,
+    /// with a bit set to `1` for [`High`]. All other bits are set to `0`. Pins with a BCM
+    /// GPIO number of 64 or higher are ignored.
+    ///
+    /// On the BCM283x and BCM2711, pins 0-31 and pins 32 and above are read from two
+    /// separate level registers (`GPLEV0` and `GPLEV1`). The second register is read
+    /// immediately after the first one, so the levels of the two banks can be skewed by
+    /// a single register access, which is typically well under a microsecond. On the
+    /// Raspberry Pi 5, all pins on the GPIO header are read from a single register.
+    ///
+    /// With the `cdev-only` feature, or when falling back to the `gpiochip` character
+    /// device, each pin is read separately, so the levels aren't captured at the same point
+    /// in time.
+    ///
+    /// When reading the same pins repeatedly, [`PinMask`] avoids recalculating the mask
+    /// for every read.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`PinMask`]: struct.PinMask.html
+    pub fn read_pins_u64(&self, pins: &[u8]) -> u64 {
+        let mask = pins
+            .iter()
+            .filter(|&&pin| pin < 64)
+            .fold(0u64, |mask, &pin| mask | (1 << pin));
+
+        self.inner.gpio_mem.levels() & mask
+    }
+
+    /// Configures the built-in pull-up/pull-down resistors of all pins selected by `mask`,
+    /// where each bit position matches a pin's BCM GPIO number.
+    ///
+    /// On the BCM283x, the pull-up/pull-down configuration is clocked into all selected
+    /// pins within the same bank with a single control sequence, rather than repeating the
+    /// sequence, and its set-up and hold delays, for each pin. On the BCM2711 and BCM2712,
+    /// all selected pins that share a configuration register are changed with a single
+    /// register write.
+    ///
+    /// The pins don't need to be retrieved through [`get`]. The resistor configuration of
+    /// pins that are owned by an [`InputPin`], [`OutputPin`] or [`IoPin`] is changed as
+    /// well, but isn't disabled when those go out of scope, unless it was configured
+    /// through the pin itself.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` without changing any pins if `mask`
+    /// selects a pin the GPIO peripheral doesn't expose.
+    ///
+    /// [`get`]: #method.get
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`IoPin`]: struct.IoPin.html
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn set_bias_mask(&self, mask: u64, bias: Bias) -> Result<()> {
+        if let Some(pin) =
+            (0..64u8).find(|&pin| mask & (1 << pin) != 0 && pin >= self.inner.gpio_lines)
+        {
+            return Err(Error::PinNotAvailable(pin));
+        }
+
+        self.inner.gpio_mem.set_bias_mask(mask, bias);
+
+        Ok(())
+    }
+
+    /// Touches the memory-mapped GPIO registers, so the first register access in a
+    /// timing-critical section doesn't incur any additional latency.
+    ///
+    /// The kernel usually sets up the page tables for the GPIO registers when they're
+    /// mapped, but the first access to each page can still miss the CPU's TLB, which makes
+    /// it slower than subsequent accesses. Call `warm_up` right before a bit-banged
+    /// transfer where the timing of the first edge matters.
+    ///
+    /// `warm_up` only reads registers, and doesn't affect the state of any pins. It
+    /// has no effect when the `cdev-only` feature is enabled.
+    pub fn warm_up(&self) {
+        self.inner.gpio_mem.prefault();
+    }
+
+    /// Returns the kernel interface used to detect interrupt trigger events.
+    ///
+    /// All synchronous and asynchronous interrupt triggers currently go through
+    /// [`InterruptBackend::CdevV2`]. Including the backend in bug reports helps to
+    /// explain differences in latency and behavior across kernel versions.
+    ///
+    /// [`InterruptBackend::CdevV2`]: enum.InterruptBackend.html#variant.CdevV2
+    pub fn interrupt_backend(&self) -> InterruptBackend {
+        InterruptBackend::CdevV2
+    }
+
+    /// Returns the interface used to change pin modes and levels.
+    ///
+    /// This is [`RegisterBackend::Cdev`] when the `cdev-only` feature is enabled, or when
+    /// RPPAL fell back to the `gpiochip` character device because writes to the
+    /// memory-mapped GPIO registers were ignored. Otherwise, this is
+    /// [`RegisterBackend::MemoryMapped`].
+    ///
+    /// [`RegisterBackend::Cdev`]: enum.RegisterBackend.html#variant.Cdev
+    /// [`RegisterBackend::MemoryMapped`]: enum.RegisterBackend.html#variant.MemoryMapped
+    pub fn register_backend(&self) -> RegisterBackend {
+        self.inner.register_backend
+    }
+
+    /// Sets `strobe` to `strobe_level`, and waits until `ack` reads `ack_level`, or until
+    /// `timeout` expires.
+    ///
+    /// `strobe_and_wait` implements one half of a request/acknowledge handshake, which is
+    /// common on parallel interfaces. The level of `ack` is only checked after `strobe`
+    /// has been changed, so an acknowledge that was still present from a previous
+    /// handshake needs to be cleared first, for instance by waiting for `ack` to return
+    /// to its idle level.
+    ///
+    /// Returns `Ok(true)` as soon as `ack` reads `ack_level`, or `Ok(false)` if it doesn't
+    /// when `timeout` expires. `strobe` is left at `strobe_level` either way. Rather than
+    /// polling the level of `ack` continuously, `strobe_and_wait` requests the edge
+    /// towards `ack_level` through a temporary `gpiochip` line request before `strobe` is
+    /// changed, and blocks until the edge is detected, so no acknowledge is missed, and
+    /// no CPU time is spent while waiting. The active-low configuration of `ack` is taken
+    /// into account.
+    ///
+    /// Returns an [`Error::Io`] error with [`io::ErrorKind::InvalidInput`] without
+    /// changing `strobe` if `ack` already has a synchronous or asynchronous interrupt
+    /// trigger configured, or is part of a [`LineGroup`] or [`EventStream`], since its line
+    /// can't be requested twice.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    /// [`LineGroup`]: struct.LineGroup.html
+    /// [`EventStream`]: struct.EventStream.html
+    pub fn strobe_and_wait(
+        &self,
+        strobe: &mut OutputPin,
+        strobe_level: Level,
+        ack: &InputPin,
+        ack_level: Level,
+        timeout: Duration,
+    ) -> Result<bool> {
+        if self.inner.line_registry.contains(ack.pin()) {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Acknowledge pin's line is already requested",
+            )));
+        }
+
+        let mut waiter = interrupt::LevelWaiter::new(
+            self.inner.cdev.as_raw_fd(),
+            &self.inner.line_registry,
+            ack.pin(),
+            ack_level,
+            ack.active_low(),
+        )?;
+
+        strobe.write(strobe_level);
+
+        waiter.wait(timeout)
+    }
+
+    /// Measures the delay between an edge on `start` and the next matching edge on `stop`.
+    ///
+    /// Both pins need to be configured for synchronous interrupts using
+    /// [`InputPin::set_interrupt`], with triggers that include the edges selected by
+    /// `trigger`. Setting `trigger` to [`Trigger::Both`] accepts either edge.
+    ///
+    /// Any cached interrupt trigger events are cleared. `measure_edge_delay` then waits
+    /// for the first matching edge on `start`, followed by the first matching edge on
+    /// `stop`. Edges on `stop` that occur before the edge on `start` are ignored. Both
+    /// pins are monitored in the same poll set, and the delay is calculated from the
+    /// timestamps the kernel assigned to each event, so it isn't affected by the time it
+    /// takes to wake up the calling thread.
+    ///
+    /// `timeout` limits the duration of the entire measurement, and can be set to `None`
+    /// to wait indefinitely. Returns `Ok(None)` if the timeout expires before both edges
+    /// are detected.
+    ///
+    /// Returns an `io::ErrorKind::InvalidInput` error if `start` and `stop` refer to the
+    /// same pin, or `trigger` is set to [`Trigger::Disabled`].
+    ///
+    /// [`InputPin::set_interrupt`]: struct.InputPin.html#method.set_interrupt
+    /// [`Trigger::Both`]: enum.Trigger.html#variant.Both
+    /// [`Trigger::Disabled`]: enum.Trigger.html#variant.Disabled
+    pub fn measure_edge_delay(
+        &self,
+        start: &InputPin,
+        stop: &InputPin,
+        trigger: Trigger,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Duration>> {
+        if start.pin() == stop.pin() {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Start and stop pins need to be different",
+            )));
+        }
+
+        if trigger == Trigger::Disabled {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Trigger can't be disabled",
+            )));
+        }
+
+        let matches = |event: &Event| trigger == Trigger::Both || event.trigger == trigger;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut sync_interrupts = self.inner.sync_interrupts.lock().unwrap();
+        let mut start_timestamp = None;
+        let mut reset = true;
+
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+            let (pin, event) = match sync_interrupts.poll(&[start, stop], reset, remaining)? {
+                Some(result) => result,
+                None => return Ok(None),
+            };
+
+            reset = false;
+
+            if !matches(&event) {
+                continue;
+            }
+
+            match start_timestamp {
+                None if pin.pin() == start.pin() => start_timestamp = Some(event.timestamp),
+                Some(start_timestamp) if pin.pin() == stop.pin() => {
+                    return Ok(Some(event.timestamp.saturating_sub(start_timestamp)));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Builds a [`Gpio`] instance with a custom configuration.
+///
+/// `GpioBuilder` can be used to manually select the `gpiochip` character device and the
+/// memory device used to access the GPIO registers, instead of relying on the automatic
+/// detection performed by [`Gpio::new`].
+///
+/// All [`Gpio`] instances share the same underlying state. The `gpiochip` and
+/// `gpiomem` options are only applied when no other [`Gpio`] instance (or any of the
+/// pins retrieved through it) currently exists. Otherwise, those options are ignored,
+/// and the existing state is shared.
+///
+/// Interrupts are always configured through the `gpiochip` character device.
+///
+/// [`Gpio`]: struct.Gpio.html
+/// [`Gpio::new`]: struct.Gpio.html#method.new
+#[derive(Debug, Clone)]
+pub struct GpioBuilder {
+    gpiochip: Option<PathBuf>,
+    gpiomem: Option<PathBuf>,
+    reset_on_drop: bool,
+    check_kernel_claims: bool,
+    async_interrupt_threads: usize,
+    verify_register_writes: bool,
+}
+
+impl GpioBuilder {
+    /// Constructs a new `GpioBuilder` with the same configuration used by [`Gpio::new`].
+    ///
+    /// [`Gpio::new`]: struct.Gpio.html#method.new
+    pub fn new() -> GpioBuilder {
+        GpioBuilder {
+            gpiochip: None,
+            gpiomem: None,
+            reset_on_drop: true,
+            check_kernel_claims: false,
+            async_interrupt_threads: 0,
+            verify_register_writes: false,
+        }
+    }
+
+    /// Sets the path of the `gpiochip` character device used for interrupts, for
+    /// instance `/dev/gpiochip0`.
+    ///
+    /// By default, the `gpiochip` device is detected based on its label.
+    pub fn gpiochip<P: AsRef<Path>>(mut self, path: P) -> GpioBuilder {
+        self.gpiochip = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the path of the memory device used to access the GPIO registers.
+    ///
+    /// `/dev/mem` is mapped at the GPIO peripheral's physical address, which requires
+    /// superuser privileges. Any other path, such as `/dev/gpiomem` or `/dev/gpiomem0`,
+    /// is mapped at offset 0.
+    ///
+    /// By default, `/dev/gpiomem` is used, falling back to `/dev/mem` if that fails. On
+    /// the Raspberry Pi 5, `/dev/gpiomem0` is used.
+    ///
+    /// This option is ignored when the `cdev-only` feature is enabled.
+    pub fn gpiomem<P: AsRef<Path>>(mut self, path: P) -> GpioBuilder {
+        self.gpiomem = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the initial value of `reset_on_drop` for all pins retrieved through
+    /// the [`Gpio`] instance.
+    ///
+    /// By default, `reset_on_drop` is set to `true`. The value can still be changed for
+    /// individual pins by calling `set_reset_on_drop`.
+    ///
+    /// [`Gpio`]: struct.Gpio.html
+    pub fn reset_on_drop(mut self, reset_on_drop: bool) -> GpioBuilder {
+        self.reset_on_drop = reset_on_drop;
+        self
+    }
+
+    /// When enabled, [`Gpio::get`] returns `Err(`[`Error::PinClaimed`]`)` for pins the
+    /// kernel reports as in use by anything other than rppal itself.
+    ///
+    /// By default, this is set to `false`, and pins can be changed through the GPIO
+    /// registers even if they're assigned to a peripheral or kernel driver, which may
+    /// interfere with the pin's current owner.
+    ///
+    /// [`Gpio::get`]: struct.Gpio.html#method.get
+    /// [`Error::PinClaimed`]: enum.Error.html#variant.PinClaimed
+    pub fn check_kernel_claims(mut self, check_kernel_claims: bool) -> GpioBuilder {
+        self.check_kernel_claims = check_kernel_claims;
+        self
+    }
+
+    /// Sets the number of shared worker threads used to monitor asynchronous interrupts.
+    ///
+    /// By default, this is set to `0`, and [`InputPin::set_async_interrupt`] spawns a
+    /// dedicated thread for every pin. With one or more shared worker threads, all
+    /// asynchronous interrupts are distributed across those threads instead, which
+    /// reduces the number of threads and context switches when monitoring many pins.
+    /// Each worker thread executes the callbacks for its pins one at a time, so a slow
+    /// callback delays the callbacks for other pins handled by the same thread.
+    ///
+    /// Callbacks are allowed to clear or reconfigure the asynchronous interrupt of any pin,
+    /// including their own. [`InputPin::clear_async_interrupt`] waits for the pin's
+    /// callback to return if it's currently running, unless it's called from within that
+    /// callback. Errors that occur while reading an event, and panics inside a callback,
+    /// stop the interrupt for that pin only, but can't be returned by
+    /// [`InputPin::clear_async_interrupt`].
+    ///
+    /// Like the `gpiochip` and `gpiomem` options, this is only applied when no other
+    /// [`Gpio`] instance currently exists. The worker threads are started immediately,
+    /// and stopped when all [`Gpio`] instances and pins go out of scope.
+    ///
+    /// [`Gpio`]: struct.Gpio.html
+    /// [`InputPin::set_async_interrupt`]: struct.InputPin.html#method.set_async_interrupt
+    /// [`InputPin::clear_async_interrupt`]: struct.InputPin.html#method.clear_async_interrupt
+    pub fn async_interrupt_threads(mut self, threads: usize) -> GpioBuilder {
+        self.async_interrupt_threads = threads;
+        self
+    }
+
+    /// When enabled, a memory-mapped GPIO register is changed and read back to make sure
+    /// writes actually reach the GPIO peripheral. If the write is ignored, which happens
+    /// in some containers and under some security policies, pin modes and levels are
+    /// changed through the `gpiochip` character device instead. The active interface is
+    /// returned by [`Gpio::register_backend`].
+    ///
+    /// By default, this is set to `false`. The check temporarily changes the
+    /// pull-up/pull-down configuration of an output pin on the BCM2711 and BCM2712, or the
+    /// output latch of an input pin on the RP1, which may belong to a different process.
+    /// On older models, none of the readable registers can be changed without affecting
+    /// the pins, and the check is skipped.
+    ///
+    /// Like the `gpiochip` and `gpiomem` options, this is only applied when no other
+    /// [`Gpio`] instance currently exists. It has no effect when the `cdev-only` feature
+    /// is enabled.
+    ///
+    /// [`Gpio`]: struct.Gpio.html
+    /// [`Gpio::register_backend`]: struct.Gpio.html#method.register_backend
+    pub fn verify_register_writes(mut self, verify_register_writes: bool) -> GpioBuilder {
+        self.verify_register_writes = verify_register_writes;
+        self
+    }
+
+    /// Checks whether the current process has the permissions required to access the
+    /// devices selected by the current configuration, without constructing a [`Gpio`]
+    /// instance.
+    ///
+    /// See [`Gpio::check_permissions`] for details.
+    ///
+    /// [`Gpio`]: struct.Gpio.html
+    /// [`Gpio::check_permissions`]: struct.Gpio.html#method.check_permissions
+    pub fn check_permissions(&self) -> result::Result<(), PermissionProblem> {
+        permissions::check(self.gpiochip.as_deref(), self.gpiomem.as_deref())
+    }
+
+    /// Constructs a new [`Gpio`] based on the current configuration.
+    ///
+    /// [`Gpio`]: struct.Gpio.html
+    pub fn build(&self) -> Result<Gpio> {
+        Gpio::with_options(self)
+    }
+}
+
+impl Default for GpioBuilder {
+    fn default() -> GpioBuilder {
+        GpioBuilder::new()
+    }
 }
@@ -0,0 +1,288 @@
+use std::io;
+use std::ops::{Index, IndexMut};
+
+use crate::gpio::{Bias, Error, Gpio, InputPin, Mode, OutputPin, Result};
+
+// Largest number of pins that fit in the bus value
+const MAX_WIDTH: usize = 32;
+
+fn check_width(pins: &[u8], name: &'static str) -> Result<()> {
+    if pins.len() > MAX_WIDTH {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} supports up to {} pins", name, MAX_WIDTH),
+        )));
+    }
+
+    Ok(())
+}
+
+/// An ordered group of output pins that are written as a single value.
+///
+/// `OutputBus` maps each bit of a value to one of its pins, which is useful for driving
+/// parallel interfaces, such as the data lines of a character LCD, or a resistor ladder DAC.
+/// The first pin specified in [`new`] is mapped to bit 0, the second pin to bit 1, etc.
+///
+/// [`write`] changes the output states of all pins through the GPIO set and clear
+/// registers, rather than changing each pin individually. All pins that need to be
+/// set high are changed with a single register write, followed by a single register
+/// write for all pins that need to be set low, which minimizes glitches on the bus.
+/// On the BCM283x and BCM2711, pins 32 and above are located in a separate set of
+/// registers, so mixing pins from both ranges requires additional writes.
+///
+/// The pins are configured as outputs while they're owned by the `OutputBus`, and
+/// reset to their original mode when the `OutputBus` goes out of scope.
+///
+/// Individual pins can be accessed by their bit position through indexing, for instance
+/// `bus[3].set_high()`. Indexing panics if the position is beyond the width of the bus.
+///
+/// [`new`]: #method.new
+/// [`write`]: #method.write
+#[derive(Debug)]
+pub struct OutputBus {
+    gpio: Gpio,
+    pins: Vec<OutputPin>,
+    tristated: bool,
+}
+
+impl OutputBus {
+    /// Constructs a new `OutputBus` for the specified BCM GPIO pins, and configures the
+    /// pins as outputs.
+    ///
+    /// The order of `pins` defines the bit each pin is mapped to, starting at bit 0.
+    ///
+    /// Returns `Err(`[`Error::PinUsed`]`)` if any of the pins is already in use, or
+    /// specified more than once. Returns an [`Error::Io`] error with
+    /// [`io::ErrorKind::InvalidInput`] if more than 32 pins are specified.
+    ///
+    /// [`Error::PinUsed`]: enum.Error.html#variant.PinUsed
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn new(gpio: &Gpio, pins: &[u8]) -> Result<OutputBus> {
+        check_width(pins, "OutputBus")?;
+
+        let pins = pins
+            .iter()
+            .map(|&pin| gpio.get(pin).map(|pin| pin.into_output()))
+            .collect::<Result<Vec<OutputPin>>>()?;
+
+        Ok(OutputBus {
+            gpio: gpio.clone(),
+            pins,
+            tristated: false,
+        })
+    }
+
+    /// Returns the BCM GPIO pins, in bit order.
+    pub fn pins(&self) -> Vec<u8> {
+        self.pins.iter().map(|pin| pin.pin()).collect()
+    }
+
+    /// Returns the number of pins on the bus.
+    pub fn width(&self) -> usize {
+        self.pins.len()
+    }
+
+    /// Sets the output states of all pins based on `value`.
+    ///
+    /// Each pin is set high if its corresponding bit in `value` is `1`, and low otherwise.
+    /// Bits beyond the width of the bus are ignored.
+    pub fn write(&mut self, value: u32) {
+        let (high, low) =
+            self.pins
+                .iter()
+                .enumerate()
+                .fold((0u64, 0u64), |(high, low), (bit, pin)| {
+                    if value & (1 << bit) != 0 {
+                        (high | (1 << pin.pin()), low)
+                    } else {
+                        (high, low | (1 << pin.pin()))
+                    }
+                });
+
+        self.gpio.inner.gpio_mem.set_high_mask(high);
+        self.gpio.inner.gpio_mem.set_low_mask(low);
+    }
+
+    /// Releases the bus by switching all pins to input mode, so another device can
+    /// drive the data lines.
+    ///
+    /// On the BCM283x and BCM2711, the mode of all pins that share a function select
+    /// register is changed with a single register write, which takes at most 3 writes
+    /// for the pins on the GPIO header. On the Raspberry Pi 5, the direction of all pins
+    /// is changed with a single register write.
+    ///
+    /// While the bus is released, [`write`] still updates the output states, which are
+    /// applied once the pins are switched back to outputs with [`drive`]. The pins are
+    /// reset to their original mode when the `OutputBus` goes out of scope.
+    ///
+    /// [`write`]: #method.write
+    /// [`drive`]: #method.drive
+    pub fn tristate(&mut self) {
+        self.gpio
+            .inner
+            .gpio_mem
+            .set_mode_mask(self.mask(), Mode::Input);
+        self.tristated = true;
+    }
+
+    /// Switches all pins back to output mode after the bus has been released through
+    /// [`tristate`].
+    ///
+    /// [`tristate`]: #method.tristate
+    pub fn drive(&mut self) {
+        self.gpio
+            .inner
+            .gpio_mem
+            .set_mode_mask(self.mask(), Mode::Output);
+        self.tristated = false;
+    }
+
+    /// Returns `true` if the bus has been released through [`tristate`].
+    ///
+    /// [`tristate`]: #method.tristate
+    pub fn is_tristated(&self) -> bool {
+        self.tristated
+    }
+
+    fn mask(&self) -> u64 {
+        self.pins
+            .iter()
+            .fold(0u64, |mask, pin| mask | (1 << pin.pin()))
+    }
+}
+
+impl Index<usize> for OutputBus {
+    type Output = OutputPin;
+
+    fn index(&self, bit: usize) -> &OutputPin {
+        &self.pins[bit]
+    }
+}
+
+impl IndexMut<usize> for OutputBus {
+    fn index_mut(&mut self, bit: usize) -> &mut OutputPin {
+        &mut self.pins[bit]
+    }
+}
+
+/// An ordered group of input pins that are read as a single value.
+///
+/// `InputBus` packs the levels of its pins into a single value, which is useful for reading
+/// parallel interfaces, such as the data lines of a parallel ADC, or a BCD rotary switch.
+/// The first pin specified in [`new`] is mapped to bit 0, the second pin to bit 1, etc.
+///
+/// [`read`] samples all pins from a single snapshot of the GPIO level registers, which
+/// avoids skew between the bits. On the BCM283x and BCM2711, pins 32 and above are located
+/// in a separate level register, which is read immediately after the first one.
+///
+/// The pins are configured as inputs while they're owned by the `InputBus`, and
+/// reset to their original mode when the `InputBus` goes out of scope.
+///
+/// Individual pins can be accessed by their bit position through indexing, for instance
+/// `bus[3].is_high()`. Indexing panics if the position is beyond the width of the bus.
+///
+/// [`new`]: #method.new
+/// [`read`]: #method.read
+#[derive(Debug)]
+pub struct InputBus {
+    gpio: Gpio,
+    pins: Vec<InputPin>,
+}
+
+impl InputBus {
+    /// Constructs a new `InputBus` for the specified BCM GPIO pins, configures the pins
+    /// as inputs, and applies the same pull-up/pull-down resistor configuration to
+    /// each pin.
+    ///
+    /// The order of `pins` defines the bit each pin is mapped to, starting at bit 0.
+    ///
+    /// Returns `Err(`[`Error::PinUsed`]`)` if any of the pins is already in use, or
+    /// specified more than once. Returns an [`Error::Io`] error with
+    /// [`io::ErrorKind::InvalidInput`] if more than 32 pins are specified.
+    ///
+    /// [`Error::PinUsed`]: enum.Error.html#variant.PinUsed
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn new(gpio: &Gpio, pins: &[u8], bias: Bias) -> Result<InputBus> {
+        check_width(pins, "InputBus")?;
+
+        let pins = pins
+            .iter()
+            .map(|&pin| gpio.get(pin).map(InputPin::with_current_bias))
+            .collect::<Result<Vec<InputPin>>>()?;
+
+        let mut input_bus = InputBus {
+            gpio: gpio.clone(),
+            pins,
+        };
+
+        input_bus.set_bias(bias);
+
+        Ok(input_bus)
+    }
+
+    /// Configures the built-in pull-up/pull-down resistors of all pins.
+    ///
+    /// On the BCM283x, the pull-up/pull-down configuration is clocked into all pins with
+    /// a single control sequence, rather than repeating the sequence for each pin. On the
+    /// BCM2711, all pins that share a configuration register are changed with a single
+    /// register write.
+    pub fn set_bias(&mut self, bias: Bias) {
+        let mask = self
+            .pins
+            .iter()
+            .fold(0u64, |mask, pin| mask | (1 << pin.pin()));
+
+        self.gpio.inner.gpio_mem.set_bias_mask(mask, bias);
+
+        for pin in &mut self.pins {
+            pin.store_bias(bias);
+        }
+    }
+
+    /// Returns the BCM GPIO pins, in bit order.
+    pub fn pins(&self) -> Vec<u8> {
+        self.pins.iter().map(|pin| pin.pin()).collect()
+    }
+
+    /// Returns the number of pins on the bus.
+    pub fn width(&self) -> usize {
+        self.pins.len()
+    }
+
+    /// Reads the levels of all pins, and packs them into a single value.
+    ///
+    /// Each pin's bit is set to `1` if the pin reads [`High`], and `0` otherwise.
+    /// Bits beyond the width of the bus are set to `0`. Active-low configurations
+    /// aren't taken into account.
+    ///
+    /// With the `cdev-only` feature, or when falling back to the `gpiochip` character
+    /// device, each pin is read separately, so the levels aren't captured at the same point
+    /// in time.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    pub fn read(&self) -> u32 {
+        let levels = self.gpio.inner.gpio_mem.levels();
+
+        self.pins
+            .iter()
+            .enumerate()
+            .filter(|(_, pin)| levels & (1 << pin.pin()) != 0)
+            .fold(0, |value, (bit, _)| value | (1 << bit))
+    }
+}
+
+impl Index<usize> for InputBus {
+    type Output = InputPin;
+
+    fn index(&self, bit: usize) -> &InputPin {
+        &self.pins[bit]
+    }
+}
+
+impl IndexMut<usize> for InputBus {
+    fn index_mut(&mut self, bit: usize) -> &mut InputPin {
+        &mut self.pins[bit]
+    }
+}
@@ -0,0 +1,259 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use crate::gpio::soft_pwm::get_time_ns;
+use crate::gpio::{InputPin, Level, Result, Trigger};
+
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(20);
+const DEFAULT_HOLD_TIME: Duration = Duration::from_secs(1);
+const DEFAULT_DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// Events reported by [`Button`].
+///
+/// [`Button`]: struct.Button.html
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ButtonEvent {
+    /// The button was pressed.
+    Pressed,
+    /// The button was released.
+    Released,
+    /// The button has been held for at least the hold time. Reported once per press,
+    /// while the button is still pressed. Contains how long the button had been held
+    /// when the event was reported.
+    Held(Duration),
+    /// The button was pressed again within the double-click time after it was released.
+    DoubleClick,
+}
+
+/// Debounced push button on a single input pin.
+///
+/// `Button` configures an asynchronous interrupt trigger for both edges, and translates
+/// the level changes into [`ButtonEvent`]s on a dedicated thread. Press and release
+/// timing is based on the kernel's event timestamps, so a busy callback doesn't affect
+/// the measured durations.
+///
+/// Every press is reported as [`Pressed`], and every release as [`Released`]. If the
+/// button is held for at least the hold time (1 s by default), [`Held`] is reported as
+/// soon as the hold time has passed, without waiting for the button to be released. If
+/// the button is pressed within the double-click time
+/// (400 ms by default) after it was released, [`DoubleClick`] is reported right after
+/// [`Pressed`]. Edges are debounced by the kernel, using a debounce period of 20 ms by
+/// default.
+///
+/// Configure the pin's pull-up/pull-down resistor to match the wiring before
+/// constructing a `Button`. A button that connects the pin to ground when pressed
+/// usually needs [`into_input_pullup`] and an `active_level` of [`Low`].
+///
+/// [`ButtonEvent`]: enum.ButtonEvent.html
+/// [`Pressed`]: enum.ButtonEvent.html#variant.Pressed
+/// [`Released`]: enum.ButtonEvent.html#variant.Released
+/// [`Held`]: enum.ButtonEvent.html#variant.Held
+/// [`DoubleClick`]: enum.ButtonEvent.html#variant.DoubleClick
+/// [`into_input_pullup`]: struct.Pin.html#method.into_input_pullup
+/// [`Low`]: enum.Level.html#variant.Low
+#[derive(Debug)]
+pub struct Button {
+    pin: InputPin,
+    active_level: Level,
+    debounce: Duration,
+    hold_time: Duration,
+    double_click_time: Duration,
+    event_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Button {
+    /// Constructs a new `Button` on the specified pin.
+    ///
+    /// `active_level` is the pin's logic level while the button is pressed.
+    pub fn new(pin: InputPin, active_level: Level) -> Button {
+        Button {
+            pin,
+            active_level,
+            debounce: DEFAULT_DEBOUNCE,
+            hold_time: DEFAULT_HOLD_TIME,
+            double_click_time: DEFAULT_DOUBLE_CLICK_TIME,
+            event_thread: None,
+        }
+    }
+
+    /// Sets the debounce period applied to the pin's edges.
+    ///
+    /// The new value is applied the next time [`on_event`] is called.
+    ///
+    /// [`on_event`]: #method.on_event
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// Sets the minimum duration the button needs to be held before [`Held`] is reported.
+    ///
+    /// The new value is applied the next time [`on_event`] is called.
+    ///
+    /// [`Held`]: enum.ButtonEvent.html#variant.Held
+    /// [`on_event`]: #method.on_event
+    pub fn set_hold_time(&mut self, hold_time: Duration) {
+        self.hold_time = hold_time;
+    }
+
+    /// Sets the maximum time between a release and the next press for the press to be
+    /// reported as a [`DoubleClick`].
+    ///
+    /// The new value is applied the next time [`on_event`] is called.
+    ///
+    /// [`DoubleClick`]: enum.ButtonEvent.html#variant.DoubleClick
+    /// [`on_event`]: #method.on_event
+    pub fn set_double_click_time(&mut self, double_click_time: Duration) {
+        self.double_click_time = double_click_time;
+    }
+
+    /// Returns `true` if the button is currently pressed.
+    pub fn is_pressed(&self) -> bool {
+        self.pin.read() == self.active_level
+    }
+
+    /// Executes the callback on a separate thread whenever a [`ButtonEvent`] occurs.
+    ///
+    /// Any previously configured callback, or any interrupt trigger configured on the
+    /// underlying pin, is replaced.
+    ///
+    /// The callback runs on a thread owned by the `Button`, which also keeps track of
+    /// the hold time. A callback that blocks delays any subsequent events, including
+    /// [`Held`].
+    ///
+    /// [`ButtonEvent`]: enum.ButtonEvent.html
+    /// [`Held`]: enum.ButtonEvent.html#variant.Held
+    pub fn on_event<C>(&mut self, mut callback: C) -> Result<()>
+    where
+        C: FnMut(ButtonEvent) + Send + 'static,
+    {
+        self.clear_on_event()?;
+
+        let active_level = self.active_level;
+        let hold_time = self.hold_time;
+        let double_click_time = self.double_click_time;
+
+        let mut pressed = self.is_pressed();
+        let mut pressed_at: Option<Duration> = None;
+        let mut released_at: Option<Duration> = None;
+        let mut double_clicked = false;
+
+        // The interrupt callback only forwards the edges, so the event thread can report
+        // Held while it waits for the release. Dropping the interrupt callback closes the
+        // channel, which stops the event thread.
+        let (sender, receiver) = mpsc::channel::<(Level, Duration)>();
+
+        let event_thread = thread::spawn(move || loop {
+            let message = match pressed_at {
+                Some(since) if pressed => {
+                    // Event timestamps use CLOCK_MONOTONIC, same as get_time_ns()
+                    let now = Duration::from_nanos(get_time_ns() as u64);
+                    let remaining = (since + hold_time).saturating_sub(now);
+
+                    match receiver.recv_timeout(remaining) {
+                        Ok(message) => message,
+                        Err(RecvTimeoutError::Timeout) => {
+                            let now = Duration::from_nanos(get_time_ns() as u64);
+                            callback(ButtonEvent::Held(now.saturating_sub(since)));
+
+                            // Held is only reported once per press
+                            pressed_at = None;
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                _ => match receiver.recv() {
+                    Ok(message) => message,
+                    Err(_) => return,
+                },
+            };
+
+            let (level, timestamp) = message;
+
+            // Ignore repeated edges in the same direction, which can occur when
+            // events were dropped
+            if (level == active_level) == pressed {
+                continue;
+            }
+
+            pressed = !pressed;
+
+            if pressed {
+                callback(ButtonEvent::Pressed);
+
+                if let Some(released_at) = released_at.take() {
+                    if timestamp.saturating_sub(released_at) <= double_click_time {
+                        double_clicked = true;
+                        callback(ButtonEvent::DoubleClick);
+                    }
+                }
+
+                pressed_at = Some(timestamp);
+            } else {
+                pressed_at = None;
+
+                callback(ButtonEvent::Released);
+
+                // The press that completes a double-click can't start another one
+                if !double_clicked {
+                    released_at = Some(timestamp);
+                }
+
+                double_clicked = false;
+            }
+        });
+
+        let result =
+            self.pin
+                .set_async_interrupt(Trigger::Both, Some(self.debounce), move |event| {
+                    let level = match event.trigger {
+                        Trigger::RisingEdge => Level::High,
+                        Trigger::FallingEdge => Level::Low,
+                        _ => return,
+                    };
+
+                    let _ = sender.send((level, event.timestamp));
+                });
+
+        // If the interrupt couldn't be configured, the sender was dropped, and the event
+        // thread has already exited or will exit shortly
+        match result {
+            Ok(()) => self.event_thread = Some(event_thread),
+            Err(_) => {
+                let _ = event_thread.join();
+            }
+        }
+
+        result
+    }
+
+    /// Removes the callback configured by [`on_event`].
+    ///
+    /// [`on_event`]: #method.on_event
+    pub fn clear_on_event(&mut self) -> Result<()> {
+        let result = self.pin.clear_async_interrupt();
+
+        // Removing the interrupt callback closes the channel, so the event thread exits
+        // once it's done reporting any remaining events
+        if let Some(event_thread) = self.event_thread.take() {
+            if result.is_ok() {
+                let _ = event_thread.join();
+            }
+        }
+
+        result
+    }
+
+    /// Consumes the `Button`, and returns the underlying [`InputPin`].
+    ///
+    /// Any callback configured by [`on_event`] is removed.
+    ///
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`on_event`]: #method.on_event
+    pub fn into_inner(mut self) -> InputPin {
+        let _ = self.clear_on_event();
+
+        self.pin
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::gpio::{ioctl, Error, Result};
+
+const PATH_DEV: &str = "/dev";
+const GPIOCHIP_PREFIX: &str = "gpiochip";
+
+/// Information about a `gpiochip` character device.
+///
+/// `ChipInfo`s are retrieved by calling [`list_chips`].
+///
+/// [`list_chips`]: fn.list_chips.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChipInfo {
+    path: PathBuf,
+    name: String,
+    label: String,
+    lines: u32,
+}
+
+impl ChipInfo {
+    /// Returns the path of the character device, for instance `/dev/gpiochip0`.
+    ///
+    /// The path can be passed to [`GpioBuilder::gpiochip`] to select a specific chip.
+    ///
+    /// [`GpioBuilder::gpiochip`]: struct.GpioBuilder.html#method.gpiochip
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the name the kernel assigned to the chip, for instance `gpiochip0`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the chip's label, which usually identifies the driver, for
+    /// instance `pinctrl-bcm2711`.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the number of GPIO lines provided by the chip.
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+}
+
+/// Returns information about all `gpiochip` character devices on the system, sorted by
+/// device number.
+///
+/// Besides the SoC's own GPIO controller, the list may include chips for GPIO expanders,
+/// the firmware-controlled GPIO lines, or HATs.
+///
+/// Returns `Err(`[`Error::PermissionDenied`]`)` if any of the devices can't be opened
+/// because of insufficient permissions.
+///
+/// [`Error::PermissionDenied`]: enum.Error.html#variant.PermissionDenied
+pub fn list_chips() -> Result<Vec<ChipInfo>> {
+    let mut chips = Vec::new();
+
+    for entry in fs::read_dir(PATH_DEV)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let id = match file_name
+            .to_str()
+            .and_then(|name| name.strip_prefix(GPIOCHIP_PREFIX))
+            .and_then(|id| id.parse::<u32>().ok())
+        {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let path = entry.path();
+        let gpiochip = match OpenOptions::new().read(true).open(&path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(Error::PermissionDenied(path.to_string_lossy().into_owned()));
+            }
+            Err(e) => return Err(Error::Io(e)),
+        };
+
+        let chip_info = ioctl::ChipInfo::new(gpiochip.as_raw_fd())?;
+        chips.push((
+            id,
+            ChipInfo {
+                path,
+                name: ioctl::cbuf_to_string(&chip_info.name),
+                label: ioctl::cbuf_to_string(&chip_info.label),
+                lines: chip_info.lines,
+            },
+        ));
+    }
+
+    chips.sort_by_key(|(id, _)| *id);
+
+    Ok(chips.into_iter().map(|(_, chip)| chip).collect())
+}
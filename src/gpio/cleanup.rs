@@ -0,0 +1,141 @@
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Arc;
+
+use libc::{
+    self, c_int, c_void, sighandler_t, siginfo_t, SA_SIGINFO, SIGINT, SIGTERM, SIG_DFL, SIG_IGN,
+};
+
+use crate::gpio::{Error, GpioState, Mode, RegisterBackend, Result};
+
+// Shared state used by the signal handler. Once set, this holds a strong reference that's
+// never released, so the pointer remains valid for the lifetime of the process.
+static CLEANUP_STATE: AtomicPtr<GpioState> = AtomicPtr::new(ptr::null_mut());
+
+const SIGNALS: [c_int; 2] = [SIGINT, SIGTERM];
+
+// Signal actions that were installed before ours, stored in the same order as SIGNALS.
+// Like CLEANUP_STATE, these are set once and never released.
+static PREV_ACTIONS: [AtomicPtr<libc::sigaction>; 2] = [
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+];
+
+pub(crate) fn install(state: &Arc<GpioState>) -> Result<()> {
+    // The gpiochip backend relies on locks and ioctl calls to change a pin's mode,
+    // neither of which can be used safely from within a signal handler
+    if state.register_backend == RegisterBackend::Cdev {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Cleanup handlers aren't supported when pins are configured through the gpiochip character device",
+        )));
+    }
+
+    let state_ptr = Arc::into_raw(state.clone()) as *mut GpioState;
+
+    // Since we're holding on to a strong reference, GpioState can't be dropped and
+    // recreated, so any existing pointer refers to the same instance. Our handlers are
+    // only installed once, so we never store them as the previous action.
+    if CLEANUP_STATE
+        .compare_exchange(
+            ptr::null_mut(),
+            state_ptr,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        )
+        .is_err()
+    {
+        unsafe {
+            drop(Arc::from_raw(state_ptr));
+        }
+
+        return Ok(());
+    }
+
+    for (index, signal) in SIGNALS.iter().enumerate() {
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction =
+                handle_signal as extern "C" fn(c_int, *mut siginfo_t, *mut c_void) as sighandler_t;
+            action.sa_flags = SA_SIGINFO;
+            libc::sigemptyset(&mut action.sa_mask);
+
+            let mut prev_action: libc::sigaction = mem::zeroed();
+            parse_retval!(libc::sigaction(*signal, &action, &mut prev_action))?;
+
+            PREV_ACTIONS[index].store(Box::into_raw(Box::new(prev_action)), Ordering::SeqCst);
+        }
+    }
+
+    Ok(())
+}
+
+extern "C" fn handle_signal(signal: c_int, info: *mut siginfo_t, context: *mut c_void) {
+    let state = CLEANUP_STATE.load(Ordering::SeqCst);
+    if !state.is_null() {
+        // Only touches atomics and memory-mapped registers without waiting for any locks,
+        // which is safe to do from within a signal handler. Pins with a register that's
+        // locked by the interrupted thread are skipped.
+        let state = unsafe { &*state };
+        for pin in 0..state.gpio_lines {
+            let reset_mode = state.reset_modes[pin as usize].swap(0, Ordering::SeqCst);
+            if let Some(mode) = reset_mode.checked_sub(1).and_then(mode_from_u8) {
+                state.gpio_mem.try_set_mode(pin, mode);
+            }
+        }
+    }
+
+    let index = SIGNALS.iter().position(|&s| s == signal).unwrap_or(0);
+    let prev_action = PREV_ACTIONS[index].load(Ordering::SeqCst);
+
+    unsafe {
+        if prev_action.is_null() {
+            // The signal arrived before the previous action was stored
+            libc::signal(signal, SIG_DFL);
+            libc::raise(signal);
+            return;
+        }
+
+        // Subsequent signals are handled by the previous action directly, since the
+        // pins have already been reset
+        let prev_action = &*prev_action;
+        libc::sigaction(signal, prev_action, ptr::null_mut());
+
+        match prev_action.sa_sigaction {
+            // The signal is blocked until we return, after which the default action applies
+            SIG_DFL => {
+                libc::raise(signal);
+            }
+            SIG_IGN => (),
+            handler if prev_action.sa_flags & SA_SIGINFO != 0 => {
+                let handler: extern "C" fn(c_int, *mut siginfo_t, *mut c_void) =
+                    mem::transmute(handler);
+                handler(signal, info, context);
+            }
+            handler => {
+                let handler: extern "C" fn(c_int) = mem::transmute(handler);
+                handler(signal);
+            }
+        }
+    }
+}
+
+fn mode_from_u8(mode: u8) -> Option<Mode> {
+    Some(match mode {
+        m if m == Mode::Input as u8 => Mode::Input,
+        m if m == Mode::Output as u8 => Mode::Output,
+        m if m == Mode::Alt0 as u8 => Mode::Alt0,
+        m if m == Mode::Alt1 as u8 => Mode::Alt1,
+        m if m == Mode::Alt2 as u8 => Mode::Alt2,
+        m if m == Mode::Alt3 as u8 => Mode::Alt3,
+        m if m == Mode::Alt4 as u8 => Mode::Alt4,
+        m if m == Mode::Alt5 as u8 => Mode::Alt5,
+        m if m == Mode::Alt6 as u8 => Mode::Alt6,
+        m if m == Mode::Alt7 as u8 => Mode::Alt7,
+        m if m == Mode::Alt8 as u8 => Mode::Alt8,
+        m if m == Mode::Null as u8 => Mode::Null,
+        _ => return None,
+    })
+}
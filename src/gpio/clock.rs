@@ -0,0 +1,215 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use crate::gpio::dma::{Registers, CLK_OFFSET, PAGE_SIZE};
+use crate::gpio::{Error, Mode, Result};
+use crate::system::{DeviceInfo, GpioInterface, SoC};
+
+// Clock manager register offsets (u32). Each general-purpose clock has a control
+// register, followed by a divisor register.
+const CM_GP0CTL: usize = 0x70 / 4;
+
+const CM_PASSWD: u32 = 0x5a << 24;
+const CM_ENAB: u32 = 1 << 4;
+const CM_BUSY: u32 = 1 << 7;
+const CM_MASH_1: u32 = 1 << 9;
+const CM_SRC_OSCILLATOR: u32 = 1;
+const CM_SRC_PLLC: u32 = 5;
+const CM_SRC_PLLD: u32 = 6;
+const CM_SRC_HDMI_AUX: u32 = 7;
+
+// Largest integer divisor supported by the clock manager
+const MAX_DIVISOR: u32 = 4095;
+// The fractional part of the divisor is stored as a 12-bit value
+const DIVISOR_FRACTION: f64 = 4096.0;
+
+// Output frequencies above this limit aren't reliably driven by the GPIO pads
+const MAX_FREQUENCY: f64 = 125_000_000.0;
+
+// Clock source frequencies in hertz (Hz)
+const OSCILLATOR_FREQUENCY: u32 = 19_200_000;
+const OSCILLATOR_FREQUENCY_BCM2711: u32 = 54_000_000;
+const PLLD_FREQUENCY: u32 = 500_000_000;
+const PLLD_FREQUENCY_BCM2711: u32 = 750_000_000;
+
+/// Clock sources for the general-purpose clocks.
+///
+/// The oscillator runs at 19.2 MHz (54 MHz on the BCM2711), and PLLD at 500 MHz
+/// (750 MHz on the BCM2711). The PLLC and HDMI auxiliary frequencies depend on the
+/// firmware configuration, and PLLC may change when the core frequency is scaled, so
+/// [`Oscillator`] and [`PllD`] are the recommended sources for a stable output frequency.
+///
+/// [`Oscillator`]: #variant.Oscillator
+/// [`PllD`]: #variant.PllD
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ClockSource {
+    Oscillator,
+    PllC,
+    PllD,
+    HdmiAux,
+}
+
+impl ClockSource {
+    fn register_value(self) -> u32 {
+        match self {
+            ClockSource::Oscillator => CM_SRC_OSCILLATOR,
+            ClockSource::PllC => CM_SRC_PLLC,
+            ClockSource::PllD => CM_SRC_PLLD,
+            ClockSource::HdmiAux => CM_SRC_HDMI_AUX,
+        }
+    }
+}
+
+// Returns the general-purpose clock and alternate function mode available for the pin.
+// Pins that aren't exposed on the GPIO header are left out, as well as GPCLK1 on
+// GPIO 42/44, which is used internally on some models.
+pub(crate) fn gpclk(pin: u8) -> Option<(usize, Mode)> {
+    match pin {
+        4 => Some((0, Mode::Alt0)),
+        5 => Some((1, Mode::Alt0)),
+        6 => Some((2, Mode::Alt0)),
+        20 => Some((0, Mode::Alt5)),
+        21 => Some((1, Mode::Alt5)),
+        _ => None,
+    }
+}
+
+// General-purpose clock generator (GPCLK0-2). The clock is stopped when GpClock
+// goes out of scope.
+#[derive(Debug)]
+pub(crate) struct GpClock {
+    clk: Registers,
+    ctl: usize,
+    source: u32,
+}
+
+impl GpClock {
+    // Starts the clock generator with the specified source and divisor. The
+    // fractional part of divisor is applied through 1-stage MASH noise-shaping,
+    // which adds some jitter to the output.
+    pub(crate) fn new(clock: usize, source: ClockSource, divisor: f64) -> Result<GpClock> {
+        let device_info = DeviceInfo::new().map_err(|_| Error::UnknownModel)?;
+        if device_info.gpio_interface() != GpioInterface::Bcm {
+            return Err(Error::UnknownModel);
+        }
+
+        let divi = divisor.trunc();
+        let divf = ((divisor - divi) * DIVISOR_FRACTION).round();
+        // MASH noise-shaping requires an integer part of at least 2
+        let min_divi = if divf > 0.0 { 2.0 } else { 1.0 };
+        if !(min_divi..=f64::from(MAX_DIVISOR)).contains(&divi) {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "GPCLK divisor needs to be between 1.0 and 4095.0",
+            )));
+        }
+
+        GpClock::configure(
+            &device_info,
+            clock,
+            source.register_value(),
+            divi as u32,
+            (divf as u32).min(MAX_DIVISOR),
+        )?
+        .ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "GPCLK is already in use",
+            ))
+        })
+    }
+
+    // Starts the clock generator with the closest achievable frequency using an
+    // integer divisor. Returns Ok(None) if the requested frequency is out of range,
+    // the clock is already in use, or the SoC doesn't support general-purpose clocks.
+    pub(crate) fn with_frequency(clock: usize, frequency: f64) -> Result<Option<GpClock>> {
+        let device_info = match DeviceInfo::new() {
+            Ok(device_info) if device_info.gpio_interface() == GpioInterface::Bcm => device_info,
+            _ => return Ok(None),
+        };
+
+        let sources = if device_info.soc() == SoC::Bcm2711 {
+            [
+                (CM_SRC_OSCILLATOR, OSCILLATOR_FREQUENCY_BCM2711),
+                (CM_SRC_PLLD, PLLD_FREQUENCY_BCM2711),
+            ]
+        } else {
+            [
+                (CM_SRC_OSCILLATOR, OSCILLATOR_FREQUENCY),
+                (CM_SRC_PLLD, PLLD_FREQUENCY),
+            ]
+        };
+
+        // Pick the source that gets closest to the requested frequency. The
+        // oscillator is preferred when both are equally accurate.
+        let mut best: Option<(u32, u32, f64)> = None;
+        for &(source, source_frequency) in &sources {
+            let divisor = (f64::from(source_frequency) / frequency).round();
+            if divisor < 1.0 || divisor > f64::from(MAX_DIVISOR) {
+                continue;
+            }
+
+            let output = f64::from(source_frequency) / divisor;
+            if output > MAX_FREQUENCY {
+                continue;
+            }
+
+            let error = (output - frequency).abs();
+            if best.map_or(true, |(_, _, best_error)| error < best_error) {
+                best = Some((source, divisor as u32, error));
+            }
+        }
+
+        match best {
+            Some((source, divisor, _)) => {
+                GpClock::configure(&device_info, clock, source, divisor, 0)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn configure(
+        device_info: &DeviceInfo,
+        clock: usize,
+        source: u32,
+        divi: u32,
+        divf: u32,
+    ) -> Result<Option<GpClock>> {
+        let clk = Registers::map(device_info.peripheral_base() + CLK_OFFSET, PAGE_SIZE)?;
+        let ctl = CM_GP0CTL + clock * 2;
+
+        // Leave the clock alone if it's already in use by the firmware or another process
+        if clk.read(ctl) & CM_ENAB > 0 {
+            return Ok(None);
+        }
+
+        let mash = if divf > 0 { CM_MASH_1 } else { 0 };
+        let gp_clock = GpClock { clk, ctl, source };
+
+        gp_clock.stop();
+        gp_clock.clk.write(ctl + 1, CM_PASSWD | (divi << 12) | divf);
+        gp_clock.clk.write(ctl, CM_PASSWD | mash | source);
+        gp_clock.clk.write(ctl, CM_PASSWD | mash | CM_ENAB | source);
+
+        Ok(Some(gp_clock))
+    }
+
+    fn stop(&self) {
+        self.clk.write(self.ctl, CM_PASSWD | self.source);
+        while self.clk.read(self.ctl) & CM_BUSY > 0 {
+            thread::sleep(Duration::from_micros(10));
+        }
+    }
+}
+
+impl Drop for GpClock {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Register access is limited to the clock generator's own control and divisor registers
+unsafe impl Send for GpClock {}
+
+unsafe impl Sync for GpClock {}
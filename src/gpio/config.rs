@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::gpio::{Bias, Error, GpioState, Level, Mode, Result};
+
+/// Restores the original modes of a group of pins when it goes out of scope.
+///
+/// `ConfigGuard`s are constructed by calling [`Gpio::apply_config_reversible`].
+///
+/// The original modes are restored when the `ConfigGuard` is dropped, which also
+/// happens on an early return, or while unwinding after a panic. Pins are restored in
+/// the reverse order they were configured in.
+///
+/// [`Gpio::apply_config_reversible`]: struct.Gpio.html#method.apply_config_reversible
+#[derive(Debug)]
+pub struct ConfigGuard {
+    gpio_state: Arc<GpioState>,
+    prev_modes: Vec<(u8, Mode)>,
+}
+
+impl ConfigGuard {
+    pub(crate) fn new(gpio_state: Arc<GpioState>, config: &[(u8, Mode)]) -> Result<ConfigGuard> {
+        if let Some(&(pin, _)) = config.iter().find(|(pin, _)| *pin >= gpio_state.gpio_lines) {
+            return Err(Error::PinNotAvailable(pin));
+        }
+
+        let mut prev_modes: Vec<(u8, Mode)> = Vec::with_capacity(config.len());
+        for &(pin, mode) in config {
+            // Only the mode a pin had before it was first changed needs to be restored
+            if !prev_modes.iter().any(|&(prev_pin, _)| prev_pin == pin) {
+                prev_modes.push((pin, gpio_state.gpio_mem.mode(pin)));
+            }
+
+            gpio_state.gpio_mem.set_mode(pin, mode);
+        }
+
+        Ok(ConfigGuard {
+            gpio_state,
+            prev_modes,
+        })
+    }
+
+    // Tracks the pins' original modes without changing any modes, for callers that apply
+    // the new modes themselves
+    pub(crate) fn record(gpio_state: Arc<GpioState>, prev_modes: Vec<(u8, Mode)>) -> ConfigGuard {
+        ConfigGuard {
+            gpio_state,
+            prev_modes,
+        }
+    }
+
+    /// Returns the pins and their original modes, in the order they were first configured.
+    pub fn prev_modes(&self) -> &[(u8, Mode)] {
+        &self.prev_modes
+    }
+
+    /// Restores the original modes right away.
+    ///
+    /// This has the same effect as dropping the `ConfigGuard`.
+    pub fn restore(self) {}
+}
+
+impl Drop for ConfigGuard {
+    fn drop(&mut self) {
+        for &(pin, mode) in self.prev_modes.iter().rev() {
+            self.gpio_state.gpio_mem.set_mode(pin, mode);
+        }
+    }
+}
+
+/// Snapshot of a pin's configuration, as returned by [`Gpio::export_state`].
+///
+/// With the `serde` feature enabled, `PinState` implements `Serialize` and `Deserialize`,
+/// so snapshots can be stored in any format supported by `serde`.
+///
+/// [`Gpio::export_state`]: struct.Gpio.html#method.export_state
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PinState {
+    /// BCM GPIO number.
+    pub pin: u8,
+    /// Pin mode.
+    pub mode: Mode,
+    /// Logic level. For outputs, this is the output state.
+    pub level: Level,
+    /// Built-in pull-up/pull-down resistor configuration.
+    ///
+    /// [`Gpio::export_state`] sets this to `None` if the resistor configuration can't be
+    /// read back. The pull-up/pull-down registers of the BCM283x can't be read, so this
+    /// is always `None` on the Raspberry Pi 3 and earlier models, unless the pin is
+    /// accessed through the `gpiochip` character device and its resistor configuration is
+    /// known. When importing, `None` leaves the current configuration unchanged.
+    ///
+    /// [`Gpio::export_state`]: struct.Gpio.html#method.export_state
+    pub bias: Option<Bias>,
+}
+
+pub(crate) fn export_state(gpio_state: &GpioState) -> Vec<PinState> {
+    (0..gpio_state.gpio_lines)
+        .map(|pin| PinState {
+            pin,
+            mode: gpio_state.gpio_mem.mode(pin),
+            level: gpio_state.gpio_mem.level(pin),
+            bias: gpio_state.gpio_mem.bias(pin),
+        })
+        .collect()
+}
+
+pub(crate) fn import_state(gpio_state: &GpioState, states: &[PinState]) -> Result<()> {
+    if let Some(state) = states
+        .iter()
+        .find(|state| state.pin >= gpio_state.gpio_lines)
+    {
+        return Err(Error::PinNotAvailable(state.pin));
+    }
+
+    for state in states {
+        // Set the output state before switching to output mode to prevent glitches
+        if state.mode == Mode::Output {
+            match state.level {
+                Level::Low => gpio_state.gpio_mem.set_low(state.pin),
+                Level::High => gpio_state.gpio_mem.set_high(state.pin),
+            }
+        }
+
+        gpio_state.gpio_mem.set_mode(state.pin, state.mode);
+
+        if let Some(bias) = state.bias {
+            gpio_state.gpio_mem.set_bias(state.pin, bias);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,604 @@
+#![allow(clippy::unnecessary_cast)]
+
+use std::fs::OpenOptions;
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+use libc::{self, c_void, off_t, size_t, MAP_FAILED, MAP_SHARED, O_SYNC, PROT_READ, PROT_WRITE};
+
+use crate::gpio::{Error, OutputPin, Result};
+use crate::system::{DeviceInfo, GpioInterface, SoC};
+
+mod mailbox;
+
+use self::mailbox::Mailbox;
+
+const PATH_DEV_MEM: &str = "/dev/mem";
+pub(crate) const PAGE_SIZE: usize = 4096;
+
+// Bus addresses used by the DMA controller to access the peripherals
+const BUS_GPSET0: u32 = 0x7e20_001c;
+const BUS_GPCLR0: u32 = 0x7e20_0028;
+const BUS_PWM_FIF1: u32 = 0x7e20_c018;
+
+// Offsets from the peripheral base address
+const DMA_OFFSET: u32 = 0x00_7000;
+const PWM_OFFSET: u32 = 0x20_c000;
+pub(crate) const CLK_OFFSET: u32 = 0x10_1000;
+
+// DMA channel register offsets (u32). Each channel occupies 0x100 bytes.
+const DMA_CHANNEL_SIZE: usize = 0x100;
+const DMA_CS: usize = 0;
+const DMA_CONBLK_AD: usize = 1;
+const DMA_DEBUG: usize = 8;
+
+const DMA_CS_ACTIVE: u32 = 1 << 0;
+const DMA_CS_END: u32 = 1 << 1;
+const DMA_CS_INT: u32 = 1 << 2;
+const DMA_CS_PRIORITY: u32 = 8 << 16;
+const DMA_CS_PANIC_PRIORITY: u32 = 8 << 20;
+const DMA_CS_WAIT_FOR_OUTSTANDING_WRITES: u32 = 1 << 28;
+const DMA_CS_ABORT: u32 = 1 << 30;
+const DMA_CS_RESET: u32 = 1 << 31;
+
+const DMA_TI_WAIT_RESP: u32 = 1 << 3;
+const DMA_TI_DEST_DREQ: u32 = 1 << 6;
+const DMA_TI_PERMAP_PWM: u32 = 5 << 16;
+const DMA_TI_NO_WIDE_BURSTS: u32 = 1 << 26;
+
+// Clear the read last not set, FIFO and read error flags
+const DMA_DEBUG_CLEAR_ERRORS: u32 = 0b111;
+
+// DMA channels that are normally left unused by the kernel. On the BCM2711,
+// channel 14 is a DMA4 channel, which uses a different control block layout.
+const DMA_CHANNEL: usize = 14;
+const DMA_CHANNEL_BCM2711: usize = 7;
+
+// PWM register offsets (u32)
+const PWM_CTL: usize = 0;
+const PWM_DMAC: usize = 0x08 / 4;
+const PWM_RNG1: usize = 0x10 / 4;
+
+const PWM_CTL_PWEN1: u32 = 1 << 0;
+const PWM_CTL_MODE1: u32 = 1 << 1;
+const PWM_CTL_USEF1: u32 = 1 << 5;
+const PWM_CTL_CLRF1: u32 = 1 << 6;
+
+const PWM_DMAC_ENAB: u32 = 1 << 31;
+const PWM_DMAC_THRESHOLD: u32 = (15 << 8) | 15;
+
+// Clock manager register offsets (u32)
+const CM_PWMCTL: usize = 0xa0 / 4;
+const CM_PWMDIV: usize = 0xa4 / 4;
+
+const CM_PASSWD: u32 = 0x5a << 24;
+const CM_ENAB: u32 = 1 << 4;
+const CM_BUSY: u32 = 1 << 7;
+const CM_SRC_PLLD: u32 = 6;
+
+// PLLD frequency in hertz (Hz)
+const PLLD_FREQUENCY: u32 = 500_000_000;
+const PLLD_FREQUENCY_BCM2711: u32 = 750_000_000;
+
+// The PWM peripheral is clocked at 10 MHz, which sets the resolution granularity to 100 ns.
+const PWM_CLOCK: u32 = 10_000_000;
+const PWM_CLOCK_NS: u64 = 1_000_000_000 / PWM_CLOCK as u64;
+
+// Smallest supported tick. Shorter ticks overload the DMA controller.
+const MIN_RESOLUTION: Duration = Duration::from_micros(1);
+
+// Default period, which is suitable for most servos
+const DEFAULT_PERIOD: Duration = Duration::from_millis(20);
+
+// VideoCore memory allocation flags
+const MEM_FLAG_DIRECT: u32 = 1 << 2;
+const MEM_FLAG_COHERENT: u32 = 1 << 3;
+
+// DMA control block (datasheet @ 4.2.1.1). Needs to be 32-byte aligned.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct ControlBlock {
+    transfer_info: u32,
+    source_addr: u32,
+    dest_addr: u32,
+    transfer_len: u32,
+    stride: u32,
+    next_block_addr: u32,
+    reserved: [u32; 2],
+}
+
+const CONTROL_BLOCK_SIZE: usize = std::mem::size_of::<ControlBlock>();
+
+// Memory-mapped peripheral registers
+#[derive(Debug)]
+pub(crate) struct Registers {
+    ptr: *mut u32,
+    size: usize,
+}
+
+impl Registers {
+    pub(crate) fn map(address: u32, size: usize) -> Result<Registers> {
+        if cfg!(feature = "cdev-only") {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Memory-mapped peripheral access is disabled by the cdev-only feature",
+            )));
+        }
+
+        let mem_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(O_SYNC)
+            .open(PATH_DEV_MEM)
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::PermissionDenied {
+                    Error::PermissionDenied(String::from(PATH_DEV_MEM))
+                } else {
+                    Error::Io(e)
+                }
+            })?;
+
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                PROT_READ | PROT_WRITE,
+                MAP_SHARED,
+                mem_file.as_raw_fd(),
+                address as off_t,
+            )
+        };
+
+        if ptr == MAP_FAILED {
+            return Err(Error::Io(io::Error::last_os_error()));
+        }
+
+        Ok(Registers {
+            ptr: ptr as *mut u32,
+            size,
+        })
+    }
+
+    #[inline(always)]
+    pub(crate) fn read(&self, offset: usize) -> u32 {
+        unsafe { ptr::read_volatile(self.ptr.add(offset)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn write(&self, offset: usize, value: u32) {
+        unsafe {
+            ptr::write_volatile(self.ptr.add(offset), value);
+        }
+    }
+}
+
+impl Drop for Registers {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut c_void, self.size as size_t);
+        }
+    }
+}
+
+// Physically contiguous memory allocated through the VideoCore mailbox
+#[derive(Debug)]
+struct DmaMemory {
+    mailbox: Mailbox,
+    handle: u32,
+    bus_addr: u32,
+    mem: Registers,
+}
+
+impl DmaMemory {
+    fn new(size: usize, soc: SoC) -> Result<DmaMemory> {
+        let mailbox = Mailbox::new()?;
+
+        // The BCM2835 needs to use the L2 coherent alias, because the ARM core doesn't
+        // bypass the L2 cache.
+        let flags = if soc == SoC::Bcm2835 {
+            MEM_FLAG_DIRECT | MEM_FLAG_COHERENT
+        } else {
+            MEM_FLAG_DIRECT
+        };
+
+        let handle = mailbox.allocate(size as u32, PAGE_SIZE as u32, flags)?;
+        let bus_addr = match mailbox.lock(handle) {
+            Ok(bus_addr) => bus_addr,
+            Err(e) => {
+                let _ = mailbox.release(handle);
+                return Err(e);
+            }
+        };
+
+        let mem = match Registers::map(mailbox::bus_to_phys(bus_addr), size) {
+            Ok(mem) => mem,
+            Err(e) => {
+                let _ = mailbox.unlock(handle);
+                let _ = mailbox.release(handle);
+                return Err(e);
+            }
+        };
+
+        Ok(DmaMemory {
+            mailbox,
+            handle,
+            bus_addr,
+            mem,
+        })
+    }
+
+    fn bus_addr(&self, offset: usize) -> u32 {
+        self.bus_addr + offset as u32
+    }
+
+    fn write(&self, offset: usize, value: u32) {
+        self.mem.write(offset / 4, value);
+    }
+
+    fn write_control_block(&self, offset: usize, control_block: ControlBlock) {
+        unsafe {
+            ptr::write_volatile(
+                (self.mem.ptr as *mut u8).add(offset) as *mut ControlBlock,
+                control_block,
+            );
+        }
+    }
+}
+
+impl Drop for DmaMemory {
+    fn drop(&mut self) {
+        let _ = self.mailbox.unlock(self.handle);
+        let _ = self.mailbox.release(self.handle);
+    }
+}
+
+/// DMA-based pulse generator for multiple output pins.
+///
+/// `DmaWave` programs the BCM283x/BCM2711 DMA controller to write precomputed
+/// values to the GPIO set and clear registers at a fixed tick. Because the pulses are
+/// generated by hardware, they aren't affected by scheduling/preemption, which
+/// results in glitch-free output on all pins simultaneously. This makes `DmaWave`
+/// well-suited for multi-servo control.
+///
+/// Every period, each pin is set high at the start of the cycle, and set low after
+/// its configured pulse width has elapsed. The pulse width is rounded down to a
+/// multiple of the resolution.
+///
+/// The DMA transfers are paced by the PWM peripheral, which means the hardware [`Pwm`]
+/// peripheral and the analog audio output can't be used while a `DmaWave` is active.
+/// `DmaWave` requires access to `/dev/mem` and `/dev/vcio`, which usually means your
+/// application needs to be run with superuser privileges. `DmaWave` isn't supported
+/// on the Raspberry Pi 5, and only supports BCM GPIO 0-31.
+///
+/// [`Pwm`]: ../pwm/struct.Pwm.html
+#[derive(Debug)]
+pub struct DmaWave {
+    pins: Vec<OutputPin>,
+    // Pulse width for each pin, as a number of ticks
+    widths: Vec<usize>,
+    ticks: usize,
+    resolution: Duration,
+    memory: DmaMemory,
+    dma: Registers,
+    dma_channel: usize,
+    pwm: Registers,
+    clk: Registers,
+}
+
+// Memory layout: control blocks, set mask, pacing word, one clear mask per tick.
+impl DmaWave {
+    /// Constructs a new `DmaWave` for the specified pins, using a 20 ms period.
+    ///
+    /// `resolution` sets the duration of a single tick. Pulse widths are rounded down
+    /// to a multiple of `resolution`. The minimum resolution is 1 µs, and it should be a
+    /// multiple of 100 ns.
+    ///
+    /// All pins are kept low until a pulse width is configured with [`set_pulse`].
+    ///
+    /// [`set_pulse`]: #method.set_pulse
+    pub fn new(pins: Vec<OutputPin>, resolution: Duration) -> Result<DmaWave> {
+        DmaWave::with_period(pins, DEFAULT_PERIOD, resolution)
+    }
+
+    /// Constructs a new `DmaWave` for the specified pins, using a custom period.
+    ///
+    /// `period` indicates the time it takes to complete one cycle, and should be a
+    /// multiple of `resolution`. Short resolutions combined with long periods require
+    /// large amounts of memory. Each tick uses 68 bytes.
+    ///
+    /// All pins are kept low until a pulse width is configured with [`set_pulse`].
+    ///
+    /// [`set_pulse`]: #method.set_pulse
+    pub fn with_period(
+        pins: Vec<OutputPin>,
+        period: Duration,
+        resolution: Duration,
+    ) -> Result<DmaWave> {
+        let device_info = DeviceInfo::new().map_err(|_| Error::UnknownModel)?;
+        if device_info.gpio_interface() != GpioInterface::Bcm {
+            return Err(Error::UnknownModel);
+        }
+
+        for pin in &pins {
+            if pin.pin() >= 32 {
+                return Err(Error::PinNotAvailable(pin.pin()));
+            }
+        }
+
+        if resolution < MIN_RESOLUTION || period < resolution {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "DmaWave resolution needs to be at least 1 µs, and shorter than the period",
+            )));
+        }
+
+        let ticks = (period.as_nanos() / resolution.as_nanos()) as usize;
+        let range = (resolution.as_nanos() as u64 / PWM_CLOCK_NS) as u32;
+
+        let (dma_channel, plld) = if device_info.soc() == SoC::Bcm2711 {
+            (DMA_CHANNEL_BCM2711, PLLD_FREQUENCY_BCM2711)
+        } else {
+            (DMA_CHANNEL, PLLD_FREQUENCY)
+        };
+
+        let peripheral_base = device_info.peripheral_base();
+        let dma = Registers::map(peripheral_base + DMA_OFFSET, PAGE_SIZE)?;
+        let pwm = Registers::map(peripheral_base + PWM_OFFSET, PAGE_SIZE)?;
+        let clk = Registers::map(peripheral_base + CLK_OFFSET, PAGE_SIZE)?;
+
+        let size = ((1 + 2 * ticks) * CONTROL_BLOCK_SIZE + (2 + ticks) * 4 + PAGE_SIZE - 1)
+            & !(PAGE_SIZE - 1);
+        let memory = DmaMemory::new(size, device_info.soc())?;
+
+        let widths = vec![0; pins.len()];
+        let mut dma_wave = DmaWave {
+            pins,
+            widths,
+            ticks,
+            resolution,
+            memory,
+            dma,
+            dma_channel,
+            pwm,
+            clk,
+        };
+
+        for pin in dma_wave.pins.iter_mut() {
+            pin.set_low();
+        }
+
+        dma_wave.init_control_blocks();
+        dma_wave.init_pwm(plld, range);
+        dma_wave.start_dma();
+
+        Ok(dma_wave)
+    }
+
+    /// Returns the duration of a single tick.
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    /// Returns the period.
+    pub fn period(&self) -> Duration {
+        self.resolution * self.ticks as u32
+    }
+
+    /// Sets the pulse width for the specified BCM GPIO pin.
+    ///
+    /// `width` is rounded down to a multiple of the resolution, and limited to the
+    /// period. A pulse width of zero keeps the pin low. The new pulse width is
+    /// applied to the output within a single period.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` if the pin isn't part of this `DmaWave`.
+    ///
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn set_pulse(&mut self, pin: u8, width: Duration) -> Result<()> {
+        let index = self
+            .pins
+            .iter()
+            .position(|p| p.pin() == pin)
+            .ok_or(Error::PinNotAvailable(pin))?;
+
+        let ticks = ((width.as_nanos() / self.resolution.as_nanos()) as usize).min(self.ticks);
+        let old_ticks = self.widths[index];
+        self.widths[index] = ticks;
+
+        // Update the tick where the pin is set low before removing the old one, so
+        // the DMA controller never skips a clear during an active cycle.
+        self.update_clear_mask(ticks);
+        self.update_clear_mask(old_ticks);
+        self.update_set_mask();
+
+        Ok(())
+    }
+
+    /// Returns the pulse width for the specified BCM GPIO pin.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` if the pin isn't part of this `DmaWave`.
+    ///
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn pulse(&self, pin: u8) -> Result<Duration> {
+        let index = self
+            .pins
+            .iter()
+            .position(|p| p.pin() == pin)
+            .ok_or(Error::PinNotAvailable(pin))?;
+
+        Ok(self.resolution * self.widths[index] as u32)
+    }
+
+    fn set_mask_offset(&self) -> usize {
+        (1 + 2 * self.ticks) * CONTROL_BLOCK_SIZE
+    }
+
+    fn pace_offset(&self) -> usize {
+        self.set_mask_offset() + 4
+    }
+
+    fn clear_mask_offset(&self, tick: usize) -> usize {
+        self.set_mask_offset() + 8 + tick * 4
+    }
+
+    fn update_set_mask(&self) {
+        let mask = self
+            .pins
+            .iter()
+            .zip(&self.widths)
+            .filter(|(_, &width)| width > 0)
+            .fold(0u32, |mask, (pin, _)| mask | (1 << pin.pin()));
+
+        self.memory.write(self.set_mask_offset(), mask);
+    }
+
+    fn update_clear_mask(&self, tick: usize) {
+        // Pins with a 100% duty cycle are never cleared
+        if tick >= self.ticks {
+            return;
+        }
+
+        let mask = self
+            .pins
+            .iter()
+            .zip(&self.widths)
+            .filter(|(_, &width)| width == tick)
+            .fold(0u32, |mask, (pin, _)| mask | (1 << pin.pin()));
+
+        self.memory.write(self.clear_mask_offset(tick), mask);
+    }
+
+    fn init_control_blocks(&self) {
+        let num_blocks = 1 + 2 * self.ticks;
+
+        self.memory.write(self.set_mask_offset(), 0);
+        self.memory.write(self.pace_offset(), 0);
+
+        // All pins start with a pulse width of zero, which means they're cleared on the first tick
+        let initial_mask = self
+            .pins
+            .iter()
+            .fold(0u32, |mask, pin| mask | (1 << pin.pin()));
+        for tick in 0..self.ticks {
+            self.memory.write(
+                self.clear_mask_offset(tick),
+                if tick == 0 { initial_mask } else { 0 },
+            );
+        }
+
+        for block in 0..num_blocks {
+            let next_block = (block + 1) % num_blocks;
+
+            let (transfer_info, source_addr, dest_addr) = if block == 0 {
+                // Start of the cycle. Set all pins with a non-zero pulse width.
+                (
+                    DMA_TI_NO_WIDE_BURSTS | DMA_TI_WAIT_RESP,
+                    self.memory.bus_addr(self.set_mask_offset()),
+                    BUS_GPSET0,
+                )
+            } else if block % 2 == 1 {
+                // Clear all pins with a pulse width that ends at this tick
+                (
+                    DMA_TI_NO_WIDE_BURSTS | DMA_TI_WAIT_RESP,
+                    self.memory
+                        .bus_addr(self.clear_mask_offset((block - 1) / 2)),
+                    BUS_GPCLR0,
+                )
+            } else {
+                // Wait for the PWM peripheral to request new data, which paces the transfers
+                (
+                    DMA_TI_NO_WIDE_BURSTS | DMA_TI_WAIT_RESP | DMA_TI_DEST_DREQ | DMA_TI_PERMAP_PWM,
+                    self.memory.bus_addr(self.pace_offset()),
+                    BUS_PWM_FIF1,
+                )
+            };
+
+            self.memory.write_control_block(
+                block * CONTROL_BLOCK_SIZE,
+                ControlBlock {
+                    transfer_info,
+                    source_addr,
+                    dest_addr,
+                    transfer_len: 4,
+                    stride: 0,
+                    next_block_addr: self.memory.bus_addr(next_block * CONTROL_BLOCK_SIZE),
+                    reserved: [0; 2],
+                },
+            );
+        }
+    }
+
+    fn init_pwm(&self, plld: u32, range: u32) {
+        // Stop the PWM peripheral and its clock
+        self.pwm.write(PWM_CTL, 0);
+        thread::sleep(Duration::from_micros(10));
+        self.clk.write(CM_PWMCTL, CM_PASSWD | CM_SRC_PLLD);
+        while self.clk.read(CM_PWMCTL) & CM_BUSY > 0 {
+            thread::sleep(Duration::from_micros(10));
+        }
+
+        // Configure the PWM clock, and restart it
+        self.clk
+            .write(CM_PWMDIV, CM_PASSWD | ((plld / PWM_CLOCK) << 12));
+        self.clk.write(CM_PWMCTL, CM_PASSWD | CM_ENAB | CM_SRC_PLLD);
+        thread::sleep(Duration::from_micros(10));
+
+        // Every FIFO word takes range clock cycles to shift out, which sets the tick duration
+        self.pwm.write(PWM_RNG1, range);
+        self.pwm.write(PWM_DMAC, PWM_DMAC_ENAB | PWM_DMAC_THRESHOLD);
+        self.pwm.write(PWM_CTL, PWM_CTL_CLRF1);
+        thread::sleep(Duration::from_micros(10));
+        self.pwm
+            .write(PWM_CTL, PWM_CTL_USEF1 | PWM_CTL_MODE1 | PWM_CTL_PWEN1);
+    }
+
+    fn start_dma(&self) {
+        let base = self.dma_channel * DMA_CHANNEL_SIZE / 4;
+
+        self.dma.write(base + DMA_CS, DMA_CS_RESET);
+        thread::sleep(Duration::from_micros(10));
+        self.dma.write(base + DMA_CS, DMA_CS_INT | DMA_CS_END);
+        self.dma.write(base + DMA_DEBUG, DMA_DEBUG_CLEAR_ERRORS);
+        self.dma
+            .write(base + DMA_CONBLK_AD, self.memory.bus_addr(0));
+        self.dma.write(
+            base + DMA_CS,
+            DMA_CS_WAIT_FOR_OUTSTANDING_WRITES
+                | DMA_CS_PANIC_PRIORITY
+                | DMA_CS_PRIORITY
+                | DMA_CS_ACTIVE,
+        );
+    }
+
+    fn stop_dma(&self) {
+        let base = self.dma_channel * DMA_CHANNEL_SIZE / 4;
+
+        self.dma.write(base + DMA_CS, DMA_CS_ABORT);
+        thread::sleep(Duration::from_micros(10));
+        self.dma.write(base + DMA_CS, DMA_CS_RESET);
+        thread::sleep(Duration::from_micros(10));
+    }
+}
+
+impl Drop for DmaWave {
+    /// Stops the DMA transfers and the PWM peripheral, and sets all pins low.
+    fn drop(&mut self) {
+        self.stop_dma();
+
+        self.pwm.write(PWM_CTL, 0);
+        self.pwm.write(PWM_DMAC, 0);
+        self.clk.write(CM_PWMCTL, CM_PASSWD | CM_SRC_PLLD);
+
+        for pin in self.pins.iter_mut() {
+            pin.set_low();
+        }
+    }
+}
+
+// Required because of the raw pointers to our memory-mapped registers
+unsafe impl Send for DmaWave {}
+
+unsafe impl Sync for DmaWave {}
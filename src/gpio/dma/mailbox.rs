@@ -0,0 +1,116 @@
+#![allow(clippy::unnecessary_cast)]
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+
+use crate::gpio::{Error, Result};
+
+#[cfg(target_env = "gnu")]
+type IoctlLong = libc::c_ulong;
+#[cfg(target_env = "musl")]
+type IoctlLong = libc::c_int;
+
+const PATH_DEV_VCIO: &str = "/dev/vcio";
+
+// _IOWR(100, 0, char *)
+const IOCTL_MBOX_PROPERTY: IoctlLong =
+    (3 << 30) | ((mem::size_of::<*mut u8>() as IoctlLong) << 16) | (100 << 8) as IoctlLong;
+
+const REQUEST: u32 = 0x0000_0000;
+const RESPONSE_SUCCESS: u32 = 0x8000_0000;
+
+const TAG_ALLOCATE_MEMORY: u32 = 0x0003_000c;
+const TAG_LOCK_MEMORY: u32 = 0x0003_000d;
+const TAG_UNLOCK_MEMORY: u32 = 0x0003_000e;
+const TAG_RELEASE_MEMORY: u32 = 0x0003_000f;
+
+// Alias bits that need to be removed from a VideoCore bus address to get the
+// ARM physical address.
+const BUS_ALIAS_MASK: u32 = 0xc000_0000;
+
+// Communicates with the VideoCore firmware through the mailbox property interface,
+// which we need to allocate physically contiguous memory the DMA controller can access.
+#[derive(Debug)]
+pub struct Mailbox {
+    vcio: File,
+}
+
+impl Mailbox {
+    pub fn new() -> Result<Mailbox> {
+        let vcio = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(PATH_DEV_VCIO)
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::PermissionDenied {
+                    Error::PermissionDenied(String::from(PATH_DEV_VCIO))
+                } else {
+                    Error::Io(e)
+                }
+            })?;
+
+        Ok(Mailbox { vcio })
+    }
+
+    // Allocates memory on the GPU, and returns its handle
+    pub fn allocate(&self, size: u32, align: u32, flags: u32) -> Result<u32> {
+        self.property(TAG_ALLOCATE_MEMORY, &[size, align, flags])
+    }
+
+    // Locks memory in place, and returns its bus address
+    pub fn lock(&self, handle: u32) -> Result<u32> {
+        self.property(TAG_LOCK_MEMORY, &[handle])
+    }
+
+    pub fn unlock(&self, handle: u32) -> Result<()> {
+        self.property(TAG_UNLOCK_MEMORY, &[handle])?;
+
+        Ok(())
+    }
+
+    pub fn release(&self, handle: u32) -> Result<()> {
+        self.property(TAG_RELEASE_MEMORY, &[handle])?;
+
+        Ok(())
+    }
+
+    // Sends a single property tag, and returns the first value of the response
+    fn property(&self, tag: u32, values: &[u32]) -> Result<u32> {
+        // Buffer size (bytes), request code, tag, value buffer size (bytes),
+        // request/response size, values, end tag.
+        let mut buffer = [0u32; 16];
+        let len = 6 + values.len();
+
+        buffer[0] = (len * mem::size_of::<u32>()) as u32;
+        buffer[1] = REQUEST;
+        buffer[2] = tag;
+        buffer[3] = mem::size_of_val(values) as u32;
+        buffer[4] = mem::size_of_val(values) as u32;
+        buffer[5..5 + values.len()].copy_from_slice(values);
+        buffer[5 + values.len()] = 0;
+
+        parse_retval!(unsafe {
+            libc::ioctl(
+                self.vcio.as_raw_fd(),
+                IOCTL_MBOX_PROPERTY,
+                buffer.as_mut_ptr(),
+            )
+        })?;
+
+        if buffer[1] != RESPONSE_SUCCESS {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "VideoCore mailbox request failed",
+            )));
+        }
+
+        Ok(buffer[5])
+    }
+}
+
+// Converts a VideoCore bus address to an ARM physical address
+pub fn bus_to_phys(bus_addr: u32) -> u32 {
+    bus_addr & !BUS_ALIAS_MASK
+}
@@ -2,11 +2,13 @@
 #![allow(dead_code)]
 
 use std::io;
+use std::ptr;
 use std::result;
 use std::time::Duration;
 
 use libc::{
-    self, c_int, c_void, EFD_NONBLOCK, EFD_SEMAPHORE, EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD,
+    self, c_int, c_void, itimerspec, timespec, CLOCK_MONOTONIC, EFD_NONBLOCK, EFD_SEMAPHORE,
+    EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD, TFD_CLOEXEC, TFD_NONBLOCK, TFD_TIMER_ABSTIME,
 };
 
 pub use libc::{epoll_event, EPOLLERR, EPOLLET, EPOLLIN, EPOLLPRI};
@@ -48,6 +50,69 @@ impl Drop for EventFd {
     }
 }
 
+// Timer that makes its fd readable when it expires, so it can be monitored through epoll
+#[derive(Debug)]
+pub struct TimerFd {
+    fd: i32,
+}
+
+impl TimerFd {
+    pub fn new() -> Result<TimerFd> {
+        Ok(TimerFd {
+            fd: parse_retval!(unsafe {
+                libc::timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK | TFD_CLOEXEC)
+            })?,
+        })
+    }
+
+    // Arms the timer to expire once at the specified CLOCK_MONOTONIC time in nanoseconds,
+    // or disarms it if deadline_ns is 0
+    pub fn set_deadline(&self, deadline_ns: i64) -> Result<()> {
+        let value = itimerspec {
+            it_interval: timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            it_value: timespec {
+                tv_sec: (deadline_ns / 1_000_000_000) as libc::time_t,
+                tv_nsec: (deadline_ns % 1_000_000_000) as libc::c_long,
+            },
+        };
+
+        parse_retval!(unsafe {
+            libc::timerfd_settime(self.fd, TFD_TIMER_ABSTIME, &value, ptr::null_mut())
+        })?;
+
+        Ok(())
+    }
+
+    // Returns the number of expirations since the last call, or 0 if the timer hasn't
+    // expired
+    pub fn expirations(&self) -> Result<u64> {
+        let mut buffer: u64 = 0;
+
+        match parse_retval!(unsafe {
+            libc::read(self.fd, &mut buffer as *mut u64 as *mut c_void, 8)
+        }) {
+            Ok(_) => Ok(buffer),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn fd(&self) -> i32 {
+        self.fd
+    }
+}
+
+impl Drop for TimerFd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Epoll {
     fd: c_int,
@@ -95,8 +160,11 @@ impl Epoll {
             return Ok(0);
         }
 
+        // epoll_wait() has millisecond granularity. Round up, so we never return
+        // before the requested timeout has elapsed, which would cause callers that
+        // wait for the remaining time to wake up repeatedly.
         let timeout = if let Some(duration) = timeout {
-            (duration.as_secs() * 1_000 + u64::from(duration.subsec_millis())) as c_int
+            ((duration.as_nanos() + 999_999) / 1_000_000).min(c_int::MAX as u128) as c_int
         } else {
             -1
         };
@@ -0,0 +1,132 @@
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::gpio::{ioctl, Error, EventClock, GpioState, Level, Result, Trigger};
+
+/// Interrupt trigger event reported by [`EventStream`].
+///
+/// [`EventStream`]: struct.EventStream.html
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PinEvent {
+    /// BCM GPIO number of the pin that triggered the event.
+    pub pin: u8,
+    /// Logic level of the pin after the edge, where a rising edge is reported as
+    /// [`High`], and a falling edge as [`Low`].
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Low`]: enum.Level.html#variant.Low
+    pub level: Level,
+    /// Best estimate of time of event occurrence, measured in elapsed time since the system
+    /// was booted.
+    pub timestamp: Duration,
+    /// Sequence number for this event in the sequence of events for this pin.
+    ///
+    /// The sequence number is assigned by the kernel, starting at 1 for the first event on
+    /// each pin. A gap between the sequence numbers of two consecutive events for the same
+    /// pin indicates events were dropped.
+    pub seqno: u32,
+}
+
+/// A blocking iterator over the edges detected on a group of pins.
+///
+/// `EventStream`s are constructed by calling [`Gpio::event_stream`].
+///
+/// All pins are monitored for both rising and falling edges through a single `gpiochip`
+/// character device line request. Events are buffered by the kernel, and returned in the
+/// order they occurred, with the kernel's timestamps. Calling [`next`] blocks until the
+/// next event arrives. The iterator never ends, but it returns an `Err` if reading an
+/// event fails.
+///
+/// Like [`LineGroup`], requesting the lines doesn't take ownership of the pins in rppal.
+/// However, the kernel only detects edges on inputs, so every requested line is
+/// configured as an input. Any pin that was configured as an output stops driving its
+/// output level, and remains an input after the `EventStream` is dropped.
+///
+/// [`Gpio::event_stream`]: struct.Gpio.html#method.event_stream
+/// [`next`]: #method.next
+/// [`LineGroup`]: struct.LineGroup.html
+#[derive(Debug)]
+pub struct EventStream {
+    event_request: ioctl::EventRequest,
+    line_registry: Arc<ioctl::LineRegistry>,
+    pins: Vec<u8>,
+}
+
+impl EventStream {
+    pub(crate) fn new(gpio_state: &GpioState, pins: &[u8]) -> Result<EventStream> {
+        let mut mask = 0u64;
+        let mut unique_pins = Vec::with_capacity(pins.len());
+        for &pin in pins {
+            if pin >= gpio_state.gpio_lines || pin as usize >= ioctl::LINES_MAX {
+                return Err(Error::PinNotAvailable(pin));
+            }
+
+            // Ignore duplicates, since the kernel rejects them
+            if mask & (1 << pin) == 0 {
+                mask |= 1 << pin;
+                unique_pins.push(pin);
+            }
+        }
+
+        let event_request = ioctl::EventRequest::with_lines(
+            gpio_state.cdev.as_raw_fd(),
+            &unique_pins,
+            Trigger::Both,
+            None,
+            ioctl::EventOptions {
+                active_low: false,
+                buffer_size: ioctl::EVENT_BUFFER_SIZE,
+                clock: EventClock::Monotonic,
+                retry_timeout: Duration::default(),
+            },
+        )?;
+
+        gpio_state
+            .line_registry
+            .register(&unique_pins, event_request.fd(), false);
+
+        Ok(EventStream {
+            event_request,
+            line_registry: gpio_state.line_registry.clone(),
+            pins: unique_pins,
+        })
+    }
+
+    /// Returns the monitored BCM GPIO pins, in the order they were specified.
+    pub fn pins(&self) -> &[u8] {
+        &self.pins
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = Result<PinEvent>;
+
+    fn next(&mut self) -> Option<Result<PinEvent>> {
+        // This blocks until the kernel reports the next event
+        let line_event = match ioctl::LineEvent::new(self.event_request.fd()) {
+            Ok(line_event) => line_event,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let pin = line_event.offset as u8;
+        let event = line_event.into_event();
+
+        Some(Ok(PinEvent {
+            pin,
+            level: match event.trigger {
+                Trigger::RisingEdge => Level::High,
+                _ => Level::Low,
+            },
+            timestamp: event.timestamp,
+            seqno: event.seqno,
+        }))
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        // The event request is closed after this returns
+        self.line_registry.unregister(self.event_request.fd());
+    }
+}
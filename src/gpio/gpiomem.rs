@@ -1,13 +1,108 @@
-use crate::gpio::{Bias, Level, Mode};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
 
+use crate::gpio::ioctl::LineDrive;
+use crate::gpio::{Bias, Error, Level, Mode, Result};
+
+#[cfg(not(feature = "cdev-only"))]
 pub mod bcm;
+pub mod cdev;
+#[cfg(not(feature = "cdev-only"))]
 pub mod rp1;
 
 pub(crate) trait GpioRegisters: std::fmt::Debug + Sync + Send {
     fn set_high(&self, pin: u8);
     fn set_low(&self, pin: u8);
+    // Sets all pins selected by mask high or low, where each bit position matches the
+    // pin's BCM GPIO number
+    fn set_high_mask(&self, mask: u64);
+    fn set_low_mask(&self, mask: u64);
     fn level(&self, pin: u8) -> Level;
+    // Returns the levels of all pins, with each pin's level stored at the bit position
+    // that matches its BCM GPIO number
+    fn levels(&self) -> u64;
     fn mode(&self, pin: u8) -> Mode;
+    // Returns the raw function select value (GPFSELn on BCM, FUNCSEL on RP1)
+    fn function_select(&self, pin: u8) -> u8;
+    // Returns the pad group that shares its drive strength, slew rate and hysteresis
+    // configuration with the pin
+    fn pad_group(&self, pin: u8) -> u8;
     fn set_mode(&self, pin: u8, mode: Mode);
+    // Sets the mode without waiting for any locks, so it can be called from within a
+    // signal handler. Returns false if the mode couldn't be changed without waiting.
+    // Implementations that take a lock in set_mode need to override this.
+    #[cfg_attr(feature = "cdev-only", allow(dead_code))]
+    fn try_set_mode(&self, pin: u8, mode: Mode) -> bool {
+        self.set_mode(pin, mode);
+        true
+    }
+    // Sets the mode of all pins selected by mask, where each bit position matches the
+    // pin's BCM GPIO number
+    fn set_mode_mask(&self, mask: u64, mode: Mode) {
+        for pin in (0..64).filter(|pin| mask & (1 << pin) != 0) {
+            self.set_mode(pin, mode);
+        }
+    }
     fn set_bias(&self, pin: u8, bias: Bias);
+    // Returns the pin's bias, or None if it can't be read back
+    fn bias(&self, _pin: u8) -> Option<Bias> {
+        None
+    }
+    // Configures the same bias for all pins selected by mask, where each bit position
+    // matches the pin's BCM GPIO number
+    fn set_bias_mask(&self, mask: u64, bias: Bias) {
+        for pin in (0..64).filter(|pin| mask & (1 << pin) != 0) {
+            self.set_bias(pin, bias);
+        }
+    }
+    // Waits until all previous register writes have completed
+    fn flush(&self);
+    // Selects the output drive mode enforced by the kernel, which is only supported when
+    // the pin is accessed through the gpiochip character device
+    fn set_drive(&self, _pin: u8, _drive: LineDrive) -> Result<()> {
+        Err(Error::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Output drive modes are only supported when pins are configured through the gpiochip character device",
+        )))
+    }
+    // Releases an open-drain output, and blocks until the line reads high, or until timeout
+    // expires. Only supported when the pin is accessed through the gpiochip character
+    // device, where the kernel enforces the drive mode.
+    fn wait_for_release(&self, _pin: u8, _timeout: Duration) -> Result<bool> {
+        Err(Error::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Waiting for an open-drain output requires pins to be configured through the gpiochip character device",
+        )))
+    }
+    // Returns true if the pin's line is currently requested by this implementation
+    fn holds_line(&self, _pin: u8) -> bool {
+        false
+    }
+    // Reads a register on every memory page that's accessed by the other methods, so
+    // the page table and TLB entries are loaded before any timing-critical accesses
+    fn prefault(&self) {}
+    // Returns pointers to the registers that set and clear the pin's output, and the
+    // pin's bit mask for both, or None if the registers aren't memory-mapped
+    fn output_registers(&self, _pin: u8) -> Option<(*mut u32, *mut u32, u32)> {
+        None
+    }
+    // Changes a register that's not expected to affect any pins, and reads it back to
+    // make sure writes actually reach the peripheral. Returns true if there's no way to
+    // verify this without side effects.
+    #[cfg_attr(feature = "cdev-only", allow(dead_code))]
+    fn verify_writable(&self) -> bool {
+        true
+    }
+}
+
+// Replaces Permission Denied I/O errors with a more descriptive error that contains the path
+#[cfg_attr(feature = "cdev-only", allow(dead_code))]
+pub(crate) fn permission_denied(err: Error, path: &Path) -> Error {
+    match err {
+        Error::Io(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            Error::PermissionDenied(path.display().to_string())
+        }
+        err => err,
+    }
 }
@@ -3,14 +3,16 @@ use std::fs::OpenOptions;
 use std::io;
 use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::io::AsRawFd;
+use std::path::Path;
 use std::ptr;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{self, AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 
 use libc::{self, c_void, off_t, size_t, MAP_FAILED, MAP_SHARED, O_SYNC, PROT_READ, PROT_WRITE};
 
-use crate::gpio::gpiomem::GpioRegisters;
+use crate::gpio::gpiomem::{permission_denied, GpioRegisters};
+use crate::gpio::mem;
 use crate::gpio::{Bias, Error, Level, Mode, Result};
 use crate::system::{DeviceInfo, SoC};
 
@@ -20,7 +22,6 @@ const PATH_DEV_MEM: &str = "/dev/mem";
 // The BCM2711 (RPi4) has GPIO-related 32-bit registers #0 .. #60, an address space of 61 registers (datasheet @ 5.1).
 const GPIO_MEM_REGISTERS: usize = 61;
 const GPIO_MEM_SIZE: usize = GPIO_MEM_REGISTERS * std::mem::size_of::<u32>();
-const GPFSEL0: usize = 0x00;
 const GPSET0: usize = 0x1c / std::mem::size_of::<u32>();
 const GPCLR0: usize = 0x28 / std::mem::size_of::<u32>();
 const GPLEV0: usize = 0x34 / std::mem::size_of::<u32>();
@@ -55,10 +56,32 @@ impl fmt::Debug for GpioMem {
 }
 
 impl GpioMem {
-    pub fn open() -> Result<GpioMem> {
+    pub fn open(path: Option<&Path>) -> Result<GpioMem> {
+        let mem_ptr = match path {
+            // /dev/mem needs to be mapped at the appropriate offset for our SoC
+            Some(path) if path == Path::new(PATH_DEV_MEM) => {
+                Self::map_devmem().map_err(|e| permission_denied(e, path))?
+            }
+            Some(path) => Self::map_devgpiomem(path).map_err(|e| permission_denied(e, path))?,
+            None => Self::map_default()?,
+        };
+
+        let locks = init_array!(AtomicBool::new(false), GPIO_MEM_REGISTERS);
+
+        // Identify which SoC we're using.
+        let soc = DeviceInfo::new().map_err(|_| Error::UnknownModel)?.soc();
+
+        Ok(GpioMem {
+            mem_ptr,
+            locks,
+            soc,
+        })
+    }
+
+    fn map_default() -> Result<*mut u32> {
         // Try /dev/gpiomem first. If that fails, try /dev/mem instead. If neither works,
         // report back the error that's the most relevant.
-        let mem_ptr = match Self::map_devgpiomem() {
+        let mem_ptr = match Self::map_devgpiomem(Path::new(PATH_DEV_GPIOMEM)) {
             Ok(ptr) => ptr,
             Err(gpiomem_err) => match Self::map_devmem() {
                 Ok(ptr) => ptr,
@@ -78,19 +101,10 @@ impl GpioMem {
             },
         };
 
-        let locks = init_array!(AtomicBool::new(false), GPIO_MEM_REGISTERS);
-
-        // Identify which SoC we're using.
-        let soc = DeviceInfo::new().map_err(|_| Error::UnknownModel)?.soc();
-
-        Ok(GpioMem {
-            mem_ptr,
-            locks,
-            soc,
-        })
+        Ok(mem_ptr)
     }
 
-    fn map_devgpiomem() -> Result<*mut u32> {
+    fn map_devgpiomem(path: &Path) -> Result<*mut u32> {
         // Open /dev/gpiomem with read/write/sync flags. This might fail if
         // /dev/gpiomem doesn't exist (< Raspbian Jessie), or /dev/gpiomem
         // doesn't have the appropriate permissions, or the current user is
@@ -99,7 +113,7 @@ impl GpioMem {
             .read(true)
             .write(true)
             .custom_flags(O_SYNC)
-            .open(PATH_DEV_GPIOMEM)?;
+            .open(path)?;
 
         // Memory-map /dev/gpiomem at offset 0
         let gpiomem_ptr = unsafe {
@@ -149,6 +163,32 @@ impl GpioMem {
         Ok(mem_ptr as *mut u32)
     }
 
+    // Registers that pack multiple pins (GPFSELn, GPPUD/GPPUDCLKn and GPIO_PUP_PDN_CNTRL_REGn)
+    // are updated with a read-modify-write sequence. Since GpioMem is shared between all
+    // pins, each register is protected by a spinlock to prevent concurrent updates to
+    // different pins in the same register from overwriting each other.
+    #[inline(always)]
+    fn try_lock(&self, index: usize) -> bool {
+        self.locks[index]
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    // Yields instead of spinning while the lock is held, since the thread holding it may
+    // have been preempted, and can't release it until it's scheduled again on single-core
+    // models.
+    #[inline(always)]
+    fn lock(&self, index: usize) {
+        while !self.try_lock(index) {
+            thread::yield_now();
+        }
+    }
+
+    #[inline(always)]
+    fn unlock(&self, index: usize) {
+        self.locks[index].store(false, Ordering::Release);
+    }
+
     #[inline(always)]
     fn read(&self, offset: usize) -> u32 {
         unsafe { ptr::read_volatile(self.mem_ptr.add(offset)) }
@@ -173,35 +213,94 @@ impl Drop for GpioMem {
 impl GpioRegisters for GpioMem {
     #[inline(always)]
     fn set_high(&self, pin: u8) {
-        let offset = GPSET0 + pin as usize / 32;
-        let shift = pin % 32;
+        let (offset, shift) = mem::set_location(pin);
 
         self.write(offset, 1 << shift);
     }
 
     #[inline(always)]
     fn set_low(&self, pin: u8) {
-        let offset = GPCLR0 + pin as usize / 32;
-        let shift = pin % 32;
+        let (offset, shift) = mem::clr_location(pin);
 
         self.write(offset, 1 << shift);
     }
 
+    fn set_high_mask(&self, mask: u64) {
+        if mask as u32 > 0 {
+            self.write(GPSET0, mask as u32);
+        }
+
+        if (mask >> 32) as u32 > 0 {
+            self.write(GPSET0 + 1, (mask >> 32) as u32);
+        }
+    }
+
+    fn set_low_mask(&self, mask: u64) {
+        if mask as u32 > 0 {
+            self.write(GPCLR0, mask as u32);
+        }
+
+        if (mask >> 32) as u32 > 0 {
+            self.write(GPCLR0 + 1, (mask >> 32) as u32);
+        }
+    }
+
     #[inline(always)]
     fn level(&self, pin: u8) -> Level {
-        let offset = GPLEV0 + pin as usize / 32;
-        let shift = pin % 32;
+        let (offset, shift) = mem::lev_location(pin);
         let reg_value = self.read(offset);
 
         unsafe { std::mem::transmute((reg_value >> shift) as u8 & 0b1) }
     }
 
-    fn mode(&self, pin: u8) -> Mode {
-        let offset = GPFSEL0 + pin as usize / 10;
-        let shift = (pin % 10) * 3;
+    fn levels(&self) -> u64 {
+        u64::from(self.read(GPLEV0)) | (u64::from(self.read(GPLEV0 + 1)) << 32)
+    }
+
+    fn function_select(&self, pin: u8) -> u8 {
+        let (offset, shift) = mem::fsel_location(pin);
         let reg_value = self.read(offset);
 
-        match (reg_value >> shift) as u8 & 0b111 {
+        (reg_value >> shift) as u8 & 0b111
+    }
+
+    fn flush(&self) {
+        // Peripheral accesses are completed in order, so reading back a register
+        // guarantees all previous writes have landed
+        atomic::fence(Ordering::SeqCst);
+        self.read(GPLEV0);
+        atomic::fence(Ordering::SeqCst);
+    }
+
+    fn prefault(&self) {
+        // All registers fit in a single page
+        self.read(GPLEV0);
+    }
+
+    fn output_registers(&self, pin: u8) -> Option<(*mut u32, *mut u32, u32)> {
+        let (set_offset, shift) = mem::set_location(pin);
+        let (clr_offset, _) = mem::clr_location(pin);
+
+        unsafe {
+            Some((
+                self.mem_ptr.add(set_offset),
+                self.mem_ptr.add(clr_offset),
+                1 << shift,
+            ))
+        }
+    }
+
+    fn pad_group(&self, pin: u8) -> u8 {
+        // Each pad group is controlled by one of the PADS0-2 registers
+        match pin {
+            0..=27 => 0,
+            28..=45 => 1,
+            _ => 2,
+        }
+    }
+
+    fn mode(&self, pin: u8) -> Mode {
+        match self.function_select(pin) {
             FSEL_INPUT => Mode::Input,
             FSEL_OUTPUT => Mode::Output,
             FSEL_ALT0 => Mode::Alt0,
@@ -215,29 +314,11 @@ impl GpioRegisters for GpioMem {
     }
 
     fn set_mode(&self, pin: u8, mode: Mode) {
-        let offset = GPFSEL0 + pin as usize / 10;
-        let shift = (pin % 10) * 3;
-
-        loop {
-            if self.locks[offset]
-                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-                .is_ok()
-            {
-                break;
-            }
-        }
+        let (offset, shift) = mem::fsel_location(pin);
 
-        let fsel_mode = match mode {
-            Mode::Input => FSEL_INPUT,
-            Mode::Output => FSEL_OUTPUT,
-            Mode::Alt0 => FSEL_ALT0,
-            Mode::Alt1 => FSEL_ALT1,
-            Mode::Alt2 => FSEL_ALT2,
-            Mode::Alt3 => FSEL_ALT3,
-            Mode::Alt4 => FSEL_ALT4,
-            Mode::Alt5 => FSEL_ALT5,
-            _ => FSEL_INPUT,
-        };
+        self.lock(offset);
+
+        let fsel_mode = fsel_value(mode);
 
         let reg_value = self.read(offset);
         self.write(
@@ -245,23 +326,120 @@ impl GpioRegisters for GpioMem {
             (reg_value & !(0b111 << shift)) | ((fsel_mode as u32) << shift),
         );
 
-        self.locks[offset].store(false, Ordering::SeqCst);
+        self.unlock(offset);
+    }
+
+    fn try_set_mode(&self, pin: u8, mode: Mode) -> bool {
+        let (offset, shift) = mem::fsel_location(pin);
+
+        if !self.try_lock(offset) {
+            return false;
+        }
+
+        let reg_value = self.read(offset);
+        self.write(
+            offset,
+            (reg_value & !(0b111 << shift)) | ((fsel_value(mode) as u32) << shift),
+        );
+
+        self.unlock(offset);
+
+        true
+    }
+
+    fn set_mode_mask(&self, mask: u64, mode: Mode) {
+        let fsel_mode = fsel_value(mode) as u32;
+
+        // Each GPFSELn register holds the function select values for 10 pins, so all
+        // selected pins within the same register are changed with a single write.
+        let mut registers = [(0u32, 0u32); 7];
+        for pin in (0..64u8).filter(|pin| mask & (1 << pin) != 0) {
+            let (_, shift) = mem::fsel_location(pin);
+            let (clear, set) = &mut registers[pin as usize / 10];
+
+            *clear |= 0b111 << shift;
+            *set |= fsel_mode << shift;
+        }
+
+        for (index, &(clear, set)) in registers.iter().enumerate() {
+            if clear == 0 {
+                continue;
+            }
+
+            let (offset, _) = mem::fsel_location(index as u8 * 10);
+
+            self.lock(offset);
+
+            let reg_value = self.read(offset);
+            self.write(offset, (reg_value & !clear) | set);
+
+            self.unlock(offset);
+        }
+    }
+
+    fn verify_writable(&self) -> bool {
+        // Only the pull-up/pull-down registers on BCM2711 and BCM2712 can be read back.
+        // On older SoCs, every readable register affects the pins when it's changed.
+        if self.soc != SoC::Bcm2711 && self.soc != SoC::Bcm2712 {
+            return true;
+        }
+
+        // Changing the bias of an output has no effect on its level. If none of the
+        // header pins are outputs, use an input, and restore its bias right away.
+        let pin = (0..28)
+            .find(|&pin| self.mode(pin) == Mode::Output)
+            .or_else(|| (0..28).find(|&pin| self.mode(pin) == Mode::Input));
+        let pin = match pin {
+            Some(pin) => pin as usize,
+            None => return true,
+        };
+
+        let offset = GPPUD_CNTRL_REG0 + pin / 16;
+        let shift = (pin % 16) * 2;
+        let lock = GPPUD_CNTRL_REG0 + pin / 32;
+
+        self.lock(lock);
+
+        let reg_value = self.read(offset);
+        // Temporarily disable the bias, or select pull-down if it's already disabled
+        let test_value = if reg_value & (0b11 << shift) == 0 {
+            reg_value | (0b10 << shift)
+        } else {
+            reg_value & !(0b11 << shift)
+        };
+        self.write(offset, test_value);
+        let writable = self.read(offset) == test_value;
+        self.write(offset, reg_value);
+
+        self.unlock(lock);
+
+        writable
+    }
+
+    fn bias(&self, pin: u8) -> Option<Bias> {
+        // Only the pull-up/pull-down registers on BCM2711 and BCM2712 can be read back
+        if self.soc != SoC::Bcm2711 && self.soc != SoC::Bcm2712 {
+            return None;
+        }
+
+        let offset = GPPUD_CNTRL_REG0 + pin as usize / 16;
+        let shift = (pin as usize % 16) * 2;
+
+        match (self.read(offset) >> shift) & 0b11 {
+            0b00 => Some(Bias::Off),
+            0b01 => Some(Bias::PullUp),
+            0b10 => Some(Bias::PullDown),
+            _ => None,
+        }
     }
 
     fn set_bias(&self, pin: u8, bias: Bias) {
-        // Offset for register.
-        let offset: usize;
-        // Bit shift for pin position within register value.
-        let shift: u8;
+        self.set_bias_mask(1 << pin, bias);
+    }
 
+    fn set_bias_mask(&self, mask: u64, bias: Bias) {
         // BCM2711 (RPi4) and BCM2712 (RPi5) need special handling.
         if self.soc == SoC::Bcm2711 || self.soc == SoC::Bcm2712 {
-            offset = GPPUD_CNTRL_REG0 + pin as usize / 16;
-            shift = pin % 16 * 2;
-
-            // Index for lock is different than register.
-            let lock = GPPUD_CNTRL_REG0 + pin as usize / 32;
-
             // Pull up vs pull down has a reverse bit pattern on BCM2711 vs others.
             let pud = match bias {
                 Bias::Off => 0b00u32,
@@ -269,65 +447,96 @@ impl GpioRegisters for GpioMem {
                 Bias::PullUp => 0b01,
             };
 
-            loop {
-                if self.locks[lock]
-                    .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-                    .is_ok()
-                {
-                    break;
+            // Each register holds the configuration for 16 pins, so all selected pins
+            // within the same register are updated with a single write.
+            for register in 0..4 {
+                let pins = (mask >> (register * 16)) as u16;
+                if pins == 0 {
+                    continue;
                 }
-            }
 
-            let reg_value = self.read(offset);
-            self.write(offset, (reg_value & !(0b11 << shift)) | (pud << shift));
+                let offset = GPPUD_CNTRL_REG0 + register;
+                let (clear, set) = (0..16)
+                    .filter(|bit| pins & (1 << bit) != 0)
+                    .fold((0u32, 0u32), |(clear, set), bit| {
+                        (clear | (0b11 << (bit * 2)), set | (pud << (bit * 2)))
+                    });
+
+                // Index for lock is different than register.
+                let lock = GPPUD_CNTRL_REG0 + register / 2;
+
+                self.lock(lock);
+
+                let reg_value = self.read(offset);
+                self.write(offset, (reg_value & !clear) | set);
 
-            self.locks[lock].store(false, Ordering::SeqCst);
+                self.unlock(lock);
+            }
         } else {
-            offset = GPPUDCLK0 + pin as usize / 32;
-            shift = pin % 32;
-
-            loop {
-                if self.locks[GPPUD]
-                    .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-                    .is_ok()
-                {
-                    if self.locks[offset]
-                        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-                        .is_ok()
-                    {
-                        break;
-                    } else {
-                        self.locks[GPPUD].store(false, Ordering::SeqCst);
+            // A single GPPUD sequence clocks the control signal into all selected pins
+            // within the same GPPUDCLKn register.
+            for bank in 0..2 {
+                let pins = (mask >> (bank * 32)) as u32;
+                if pins == 0 {
+                    continue;
+                }
+
+                let offset = GPPUDCLK0 + bank;
+
+                // GPPUD and GPPUDCLKn are used as a pair, so we need to hold both locks
+                loop {
+                    if self.try_lock(GPPUD) {
+                        if self.try_lock(offset) {
+                            break;
+                        }
+
+                        self.unlock(GPPUD);
                     }
+
+                    thread::yield_now();
                 }
-            }
 
-            // Set the control signal in GPPUD.
-            let reg_value = self.read(GPPUD);
-            self.write(GPPUD, (reg_value & !0b11) | ((bias as u32) & 0b11));
+                // Set the control signal in GPPUD.
+                let reg_value = self.read(GPPUD);
+                self.write(GPPUD, (reg_value & !0b11) | ((bias as u32) & 0b11));
 
-            // The datasheet mentions waiting at least 150 cycles for set-up and hold, but
-            // doesn't state which clock is used. This is likely the VPU clock (see
-            // https://www.raspberrypi.org/forums/viewtopic.php?f=72&t=163352). At either
-            // 250MHz or 400MHz, a 5µs delay + overhead is more than adequate.
+                // The datasheet mentions waiting at least 150 cycles for set-up and hold, but
+                // doesn't state which clock is used. This is likely the VPU clock (see
+                // https://www.raspberrypi.org/forums/viewtopic.php?f=72&t=163352). At either
+                // 250MHz or 400MHz, a 5µs delay + overhead is more than adequate.
 
-            // Set-up time for the control signal. >= 5µs
-            thread::sleep(Duration::new(0, 5000));
-            // Clock the control signal into the selected pin.
-            self.write(offset, 1 << shift);
+                // Set-up time for the control signal. >= 5µs
+                thread::sleep(Duration::new(0, 5000));
+                // Clock the control signal into the selected pins.
+                self.write(offset, pins);
 
-            // Hold time for the control signal. >= 5µs
-            thread::sleep(Duration::new(0, 5000));
-            // Remove the control signal and clock.
-            self.write(GPPUD, reg_value & !0b11);
-            self.write(offset, 0);
+                // Hold time for the control signal. >= 5µs
+                thread::sleep(Duration::new(0, 5000));
+                // Remove the control signal and clock.
+                self.write(GPPUD, reg_value & !0b11);
+                self.write(offset, 0);
 
-            self.locks[offset].store(false, Ordering::SeqCst);
-            self.locks[GPPUD].store(false, Ordering::SeqCst);
+                self.unlock(offset);
+                self.unlock(GPPUD);
+            }
         }
     }
 }
 
+fn fsel_value(mode: Mode) -> u8 {
+    match mode {
+        Mode::Input => FSEL_INPUT,
+        Mode::Output => FSEL_OUTPUT,
+        Mode::Alt0 => FSEL_ALT0,
+        Mode::Alt1 => FSEL_ALT1,
+        Mode::Alt2 => FSEL_ALT2,
+        Mode::Alt3 => FSEL_ALT3,
+        Mode::Alt4 => FSEL_ALT4,
+        Mode::Alt5 => FSEL_ALT5,
+        _ => FSEL_INPUT,
+    }
+}
+
 // Required because of the raw pointer to our memory-mapped file
 unsafe impl Send for GpioMem {}
 
@@ -0,0 +1,353 @@
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::gpio::gpiomem::GpioRegisters;
+use crate::gpio::interrupt;
+use crate::gpio::ioctl::{
+    ChipInfo, LineDirection, LineDrive, LineInfo, LineRegistry, LineRequest, LineValues,
+};
+use crate::gpio::{Bias, Error, Level, Mode, Result};
+
+// Maximum number of lines we keep track of, which matches the range of BCM GPIO numbers
+const LINES: usize = u8::MAX as usize;
+
+// Function select values returned by function_select(), which match GPFSELn on BCM
+const FSEL_INPUT: u8 = 0b000;
+const FSEL_OUTPUT: u8 = 0b001;
+
+#[derive(Debug)]
+struct LineState {
+    // Mode set through set_mode(), or None if it hasn't been changed by us
+    mode: Option<Mode>,
+    bias: Bias,
+    level: Level,
+    // Most recently read level, which is returned if the level can't be read
+    last_read: Level,
+    drive: LineDrive,
+    // Outputs are kept requested, since the kernel may reset a line's configuration
+    // once it's released
+    request: Option<LineRequest>,
+}
+
+impl Default for LineState {
+    fn default() -> LineState {
+        LineState {
+            mode: None,
+            bias: Bias::Off,
+            level: Level::Low,
+            last_read: Level::Low,
+            drive: LineDrive::PushPull,
+            request: None,
+        }
+    }
+}
+
+// Drop-in replacement for the memory-mapped BCM and RP1 register access, which
+// configures and accesses all lines through the gpiochip character device instead.
+//
+// Only the Input and Output modes are supported. Alternate function modes can't be
+// selected through the uAPI, so changing a pin to an Alt mode is ignored.
+//
+// Every line is read with a separate ioctl call, so levels() doesn't return a snapshot
+// of all pins at a single point in time.
+#[derive(Debug)]
+pub struct GpioMem {
+    cdev: File,
+    lines: Mutex<Vec<LineState>>,
+    // Requests held by interrupt triggers, LineGroup and EventStream
+    line_registry: Arc<LineRegistry>,
+}
+
+impl GpioMem {
+    pub fn open(cdev: File, line_registry: Arc<LineRegistry>) -> GpioMem {
+        let mut lines = Vec::with_capacity(LINES);
+        lines.resize_with(LINES, LineState::default);
+
+        GpioMem {
+            cdev,
+            lines: Mutex::new(lines),
+            line_registry,
+        }
+    }
+
+    fn lock_lines(&self) -> MutexGuard<'_, Vec<LineState>> {
+        self.lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Applies the line's stored direction and bias. Outputs keep their line request, while
+    // inputs are configured through a temporary request, so they remain available for
+    // interrupt triggers.
+    fn configure(&self, pin: u8, line: &mut LineState) {
+        let direction = match line.mode {
+            Some(Mode::Output) => LineDirection::Output(line.level, line.drive),
+            _ => LineDirection::Input,
+        };
+
+        if let (LineDirection::Output(..), Some(request)) = (direction, line.request.as_mut()) {
+            // Ignore errors, since there's no way to report them here
+            let _ = request.reconfigure(direction, line.bias);
+            return;
+        }
+
+        line.request = None;
+        if let Ok(request) =
+            LineRequest::configured(self.cdev.as_raw_fd(), u32::from(pin), direction, line.bias)
+        {
+            if let LineDirection::Output(..) = direction {
+                line.request = Some(request);
+            }
+        }
+    }
+
+    fn write(&self, pin: u8, level: Level) {
+        let mut lines = self.lock_lines();
+        if let Some(line) = lines.get_mut(pin as usize) {
+            line.level = level;
+
+            if let Some(ref request) = line.request {
+                let bits = if level == Level::High { 1 } else { 0 };
+                let _ = request.set_levels(LineValues::new(bits, 1));
+            }
+        }
+    }
+
+    fn current_mode(&self, pin: u8, line: &LineState) -> Mode {
+        if let Some(mode) = line.mode {
+            return mode;
+        }
+
+        match LineInfo::new(self.cdev.as_raw_fd(), u32::from(pin)) {
+            Ok(line_info) if line_info.flags().output() => Mode::Output,
+            _ => Mode::Input,
+        }
+    }
+
+    // Reads the level through the line's own request, or a request held elsewhere in
+    // rppal. Inputs configured through set_mode() are requested temporarily. Any other
+    // line may be in use by a peripheral, and some GPIO drivers reset a line to an input
+    // when it's released, so those lines aren't requested. If the level can't be read,
+    // the most recently read level is returned.
+    fn read_requested(&self, pin: u8) -> Result<bool> {
+        LineRequest::new(self.cdev.as_raw_fd(), &[u32::from(pin)])
+            .and_then(|request| request.levels(1))
+            .map(|values| values.bits & 1 != 0)
+    }
+
+    fn read(&self, pin: u8, line: &mut LineState) -> Level {
+        let level = match line.request {
+            Some(ref request) => Some(request.levels(1).map(|values| values.bits & 1 != 0)),
+            None => match self.line_registry.level(pin) {
+                Some(level) => Some(level.map(|level| level == Level::High)),
+                None if line.mode == Some(Mode::Input) => Some(self.read_requested(pin)),
+                None => None,
+            },
+        };
+
+        if let Some(Ok(high)) = level {
+            line.last_read = if high { Level::High } else { Level::Low };
+        }
+
+        line.last_read
+    }
+}
+
+impl GpioRegisters for GpioMem {
+    fn set_high(&self, pin: u8) {
+        self.write(pin, Level::High);
+    }
+
+    fn set_low(&self, pin: u8) {
+        self.write(pin, Level::Low);
+    }
+
+    fn set_high_mask(&self, mask: u64) {
+        for pin in (0..64).filter(|pin| mask & (1 << pin) != 0) {
+            self.write(pin, Level::High);
+        }
+    }
+
+    fn set_low_mask(&self, mask: u64) {
+        for pin in (0..64).filter(|pin| mask & (1 << pin) != 0) {
+            self.write(pin, Level::Low);
+        }
+    }
+
+    fn level(&self, pin: u8) -> Level {
+        let mut lines = self.lock_lines();
+        match lines.get_mut(pin as usize) {
+            Some(line) => self.read(pin, line),
+            None => Level::Low,
+        }
+    }
+
+    fn levels(&self) -> u64 {
+        let mut lines = self.lock_lines();
+        let num_lines = match ChipInfo::new(self.cdev.as_raw_fd()) {
+            Ok(chip_info) => (chip_info.lines as usize).min(64),
+            Err(_) => return 0,
+        };
+
+        (0..num_lines)
+            .filter(|&pin| self.read(pin as u8, &mut lines[pin]) == Level::High)
+            .fold(0, |levels, pin| levels | (1 << pin))
+    }
+
+    fn bias(&self, pin: u8) -> Option<Bias> {
+        let lines = self.lock_lines();
+        let line = lines.get(pin as usize)?;
+
+        // Lines we've configured use the stored bias. The kernel only reports the bias
+        // for lines that are currently requested.
+        if line.mode.is_some() {
+            return Some(line.bias);
+        }
+
+        let flags = LineInfo::new(self.cdev.as_raw_fd(), u32::from(pin))
+            .ok()?
+            .flags();
+
+        if flags.bias_pull_up() {
+            Some(Bias::PullUp)
+        } else if flags.bias_pull_down() {
+            Some(Bias::PullDown)
+        } else if flags.bias_disabled() {
+            Some(Bias::Off)
+        } else {
+            None
+        }
+    }
+
+    fn mode(&self, pin: u8) -> Mode {
+        let lines = self.lock_lines();
+        match lines.get(pin as usize) {
+            Some(line) => self.current_mode(pin, line),
+            None => Mode::Input,
+        }
+    }
+
+    fn function_select(&self, pin: u8) -> u8 {
+        match self.mode(pin) {
+            Mode::Output => FSEL_OUTPUT,
+            _ => FSEL_INPUT,
+        }
+    }
+
+    fn pad_group(&self, _pin: u8) -> u8 {
+        // Pad configuration isn't exposed through the uAPI
+        0
+    }
+
+    fn try_set_mode(&self, _pin: u8, _mode: Mode) -> bool {
+        // Changing a line's direction requires the line state lock and an ioctl call
+        false
+    }
+
+    fn set_mode(&self, pin: u8, mode: Mode) {
+        let mode = match mode {
+            Mode::Input | Mode::Output => mode,
+            _ => return,
+        };
+
+        let mut lines = self.lock_lines();
+        if let Some(line) = lines.get_mut(pin as usize) {
+            // The drive mode only applies while the pin is configured as an output
+            if mode == Mode::Input {
+                line.drive = LineDrive::PushPull;
+            }
+
+            line.mode = Some(mode);
+            self.configure(pin, line);
+        }
+    }
+
+    fn set_bias(&self, pin: u8, bias: Bias) {
+        let mut lines = self.lock_lines();
+        if let Some(line) = lines.get_mut(pin as usize) {
+            if line.mode.is_none() {
+                // Make sure an existing output isn't turned into an input, and
+                // keeps its current level
+                let mode = self.current_mode(pin, line);
+                if mode == Mode::Output {
+                    if let Ok(high) = self.read_requested(pin) {
+                        line.level = if high { Level::High } else { Level::Low };
+                    }
+                }
+
+                line.mode = Some(mode);
+            }
+
+            line.bias = bias;
+            self.configure(pin, line);
+        }
+    }
+
+    fn holds_line(&self, pin: u8) -> bool {
+        self.lock_lines()
+            .get(pin as usize)
+            .map_or(false, |line| line.request.is_some())
+    }
+
+    fn flush(&self) {
+        // Every ioctl call completes before it returns
+    }
+
+    fn set_drive(&self, pin: u8, drive: LineDrive) -> Result<()> {
+        let mut lines = self.lock_lines();
+        let line = lines
+            .get_mut(pin as usize)
+            .ok_or(Error::PinNotAvailable(pin))?;
+
+        line.drive = drive;
+
+        // Inputs pick up the drive mode once they're configured as outputs
+        if let Some(ref mut request) = line.request {
+            request.reconfigure(LineDirection::Output(line.level, drive), line.bias)?;
+        }
+
+        Ok(())
+    }
+
+    fn wait_for_release(&self, pin: u8, timeout: Duration) -> Result<bool> {
+        {
+            let mut lines = self.lock_lines();
+            let line = lines
+                .get_mut(pin as usize)
+                .ok_or(Error::PinNotAvailable(pin))?;
+
+            if line.mode != Some(Mode::Output) || line.drive != LineDrive::OpenDrain {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Pin isn't configured as an open-drain output",
+                )));
+            }
+
+            // A released open-drain output doesn't drive the line, so the output request
+            // can be replaced by an input request that detects the rising edge
+            line.level = Level::High;
+            line.request = None;
+        }
+
+        let result = interrupt::wait_for_level(
+            self.cdev.as_raw_fd(),
+            &self.line_registry,
+            pin,
+            Level::High,
+            timeout,
+        );
+
+        // Request the output again, with any level that was set while we were waiting,
+        // unless the pin has been reconfigured in the meantime
+        let mut lines = self.lock_lines();
+        let line = &mut lines[pin as usize];
+        if line.mode == Some(Mode::Output) && line.request.is_none() {
+            self.configure(pin, line);
+        }
+
+        result
+    }
+}
@@ -5,14 +5,16 @@ use std::fs::OpenOptions;
 use std::io;
 use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::io::AsRawFd;
+use std::path::Path;
 use std::ptr;
+use std::sync::atomic::{self, Ordering};
 
 use libc::{self, c_void, size_t, MAP_FAILED, MAP_SHARED, O_SYNC, PROT_READ, PROT_WRITE};
 
 use crate::gpio::{Bias, Error, Level, Mode, Result};
 use crate::system::{DeviceInfo, SoC};
 
-use super::GpioRegisters;
+use super::{permission_denied, GpioRegisters};
 
 const PATH_DEV_GPIOMEM: &str = "/dev/gpiomem0";
 
@@ -100,8 +102,9 @@ impl fmt::Debug for GpioMem {
 }
 
 impl GpioMem {
-    pub fn open() -> Result<GpioMem> {
-        let mem_ptr = Self::map_devgpiomem()?;
+    pub fn open(path: Option<&Path>) -> Result<GpioMem> {
+        let path = path.unwrap_or_else(|| Path::new(PATH_DEV_GPIOMEM));
+        let mem_ptr = Self::map_devgpiomem(path).map_err(|e| permission_denied(e, path))?;
 
         // Identify which SoC we're using.
         let soc = DeviceInfo::new().map_err(|_| Error::UnknownModel)?.soc();
@@ -109,7 +112,7 @@ impl GpioMem {
         Ok(GpioMem { mem_ptr, soc })
     }
 
-    fn map_devgpiomem() -> Result<*mut u32> {
+    fn map_devgpiomem(path: &Path) -> Result<*mut u32> {
         // Open gpiomem with read/write/sync flags. This might fail if the
         // gpiomem cdev doesn't exist (< Raspbian Jessie), or gpiomem
         // doesn't have the appropriate permissions, or the current user is
@@ -118,7 +121,7 @@ impl GpioMem {
             .read(true)
             .write(true)
             .custom_flags(O_SYNC)
-            .open(PATH_DEV_GPIOMEM)?;
+            .open(path)?;
 
         // Memory-map gpiomem at offset 0
         let gpiomem_ptr = unsafe {
@@ -215,6 +218,19 @@ impl GpioRegisters for GpioMem {
         self.write(offset, 1 << pin);
     }
 
+    fn set_high_mask(&self, mask: u64) {
+        let offset = (SYS_RIO0_OFFSET + RIO_OUT + SET_OFFSET) / REG_SIZE;
+
+        // Only the first 28 GPIOs are accessible
+        self.write(offset, mask as u32 & 0x0fff_ffff);
+    }
+
+    fn set_low_mask(&self, mask: u64) {
+        let offset = (SYS_RIO0_OFFSET + RIO_OUT + CLR_OFFSET) / REG_SIZE;
+
+        self.write(offset, mask as u32 & 0x0fff_ffff);
+    }
+
     #[inline(always)]
     fn level(&self, pin: u8) -> Level {
         let offset = (SYS_RIO0_OFFSET + RIO_IN) / REG_SIZE;
@@ -223,12 +239,81 @@ impl GpioRegisters for GpioMem {
         unsafe { std::mem::transmute((reg_value >> pin) as u8 & 0b1) }
     }
 
-    fn mode(&self, pin: u8) -> Mode {
+    fn levels(&self) -> u64 {
+        let offset = (SYS_RIO0_OFFSET + RIO_IN) / REG_SIZE;
+
+        // Only the first 28 GPIOs are accessible
+        u64::from(self.read(offset) & 0x0fff_ffff)
+    }
+
+    fn function_select(&self, pin: u8) -> u8 {
         let offset =
             (IO_BANK0_OFFSET + GPIO_CTRL + (pin as usize * GPIO_OFFSET) + RW_OFFSET) / REG_SIZE;
         let reg_value = self.read(offset);
 
-        match (reg_value & CTRL_FUNCSEL_MASK) as u8 {
+        ((reg_value & CTRL_FUNCSEL_MASK) >> CTRL_FUNCSEL_LSB) as u8
+    }
+
+    fn verify_writable(&self) -> bool {
+        // The output latch of an input pin can be changed without affecting the pin
+        let oe = self.read((SYS_RIO0_OFFSET + RIO_OE) / REG_SIZE);
+        let pin = match (0..28).find(|pin| oe & (1 << pin) == 0) {
+            Some(pin) => pin,
+            None => return true,
+        };
+
+        let offset = (SYS_RIO0_OFFSET + RIO_OUT) / REG_SIZE;
+        let xor_offset = (SYS_RIO0_OFFSET + RIO_OUT + XOR_OFFSET) / REG_SIZE;
+
+        let before = self.read(offset) & (1 << pin);
+        self.write(xor_offset, 1 << pin);
+        let after = self.read(offset) & (1 << pin);
+        self.write(xor_offset, 1 << pin);
+
+        before != after
+    }
+
+    fn flush(&self) {
+        // Peripheral accesses are completed in order, so reading back a register
+        // guarantees all previous writes have landed
+        atomic::fence(Ordering::SeqCst);
+        self.read((SYS_RIO0_OFFSET + RIO_IN) / REG_SIZE);
+        atomic::fence(Ordering::SeqCst);
+    }
+
+    fn prefault(&self) {
+        // The atomic SET and CLR aliases are located on separate pages
+        for offset in &[
+            IO_BANK0_OFFSET + GPIO_STATUS,
+            SYS_RIO0_OFFSET + RIO_IN,
+            SYS_RIO0_OFFSET + RIO_OUT + SET_OFFSET,
+            SYS_RIO0_OFFSET + RIO_OUT + CLR_OFFSET,
+            PADS_BANK0_OFFSET + PADS_GPIO,
+        ] {
+            self.read(offset / REG_SIZE);
+        }
+    }
+
+    fn output_registers(&self, pin: u8) -> Option<(*mut u32, *mut u32, u32)> {
+        let set_offset = (SYS_RIO0_OFFSET + RIO_OUT + SET_OFFSET) / REG_SIZE;
+        let clr_offset = (SYS_RIO0_OFFSET + RIO_OUT + CLR_OFFSET) / REG_SIZE;
+
+        unsafe {
+            Some((
+                self.mem_ptr.add(set_offset),
+                self.mem_ptr.add(clr_offset),
+                1 << pin,
+            ))
+        }
+    }
+
+    fn pad_group(&self, _pin: u8) -> u8 {
+        // All user-accessible pins are located in PADS_BANK0
+        0
+    }
+
+    fn mode(&self, pin: u8) -> Mode {
+        match self.function_select(pin) {
             FSEL_ALT0 => Mode::Alt0,
             FSEL_ALT1 => Mode::Alt1,
             FSEL_ALT2 => Mode::Alt2,
@@ -283,6 +368,50 @@ impl GpioRegisters for GpioMem {
         self.write(offset, reg_value);
     }
 
+    fn set_mode_mask(&self, mask: u64, mode: Mode) {
+        // Only the first 28 GPIOs are accessible
+        let mask = mask as u32 & 0x0fff_ffff;
+
+        if mode != Mode::Input && mode != Mode::Output {
+            for pin in (0..28).filter(|pin| mask & (1 << pin) != 0) {
+                self.set_mode(pin, mode);
+            }
+
+            return;
+        }
+
+        // Pins that are already configured as GPIO only need their direction changed,
+        // which is done for all of them with a single write to the atomic alias of RIO_OE
+        let mut direction_mask = 0u32;
+        for pin in (0..28).filter(|pin| mask & (1 << pin) != 0) {
+            if self.function_select(pin) == FSEL_ALT5 {
+                direction_mask |= 1 << pin;
+            } else {
+                self.set_mode(pin, mode);
+            }
+        }
+
+        if direction_mask > 0 {
+            let offset = match mode {
+                Mode::Output => (SYS_RIO0_OFFSET + RIO_OE + SET_OFFSET) / REG_SIZE,
+                _ => (SYS_RIO0_OFFSET + RIO_OE + CLR_OFFSET) / REG_SIZE,
+            };
+
+            self.write(offset, direction_mask);
+        }
+    }
+
+    fn bias(&self, pin: u8) -> Option<Bias> {
+        let offset = (PADS_BANK0_OFFSET + PADS_GPIO + (pin as usize * PADS_OFFSET)) / REG_SIZE;
+
+        match (self.read(offset) & PADS_BIAS_MASK) >> PADS_BIAS_LSB {
+            PADS_BIAS_OFF => Some(Bias::Off),
+            PADS_BIAS_DOWN => Some(Bias::PullDown),
+            PADS_BIAS_UP => Some(Bias::PullUp),
+            _ => None,
+        }
+    }
+
     fn set_bias(&self, pin: u8, bias: Bias) {
         let offset =
             (PADS_BANK0_OFFSET + PADS_GPIO + (pin as usize * PADS_OFFSET) + RW_OFFSET) / REG_SIZE;
@@ -1,19 +1,24 @@
 #![allow(dead_code)]
 
+use std::collections::HashMap;
 use std::fmt;
-use std::thread;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread::{self, ThreadId};
 use std::time::{Duration, Instant};
 
 use crate::gpio::epoll::{epoll_event, Epoll, EventFd, EPOLLERR, EPOLLET, EPOLLIN, EPOLLPRI};
 use crate::gpio::ioctl;
 use crate::gpio::pin::InputPin;
-use crate::gpio::{Error, Event, Result, Trigger};
+use crate::gpio::{CallbackAction, Error, Event, EventClock, Level, Result, Trigger};
 
 pub(crate) struct EventLoop {
     poll: Epoll,
     events: Vec<epoll_event>,
     trigger_status: Vec<TriggerStatus>,
     cdev_fd: i32,
+    registry: Arc<ioctl::LineRegistry>,
 }
 
 #[derive(Debug)]
@@ -21,23 +26,32 @@ struct Interrupt {
     pin: u8,
     trigger: Trigger,
     debounce: Option<Duration>,
+    options: ioctl::EventOptions,
     cdev_fd: i32,
     event_request: ioctl::EventRequest,
+    registry: Arc<ioctl::LineRegistry>,
 }
 
 impl Interrupt {
     fn new(
         cdev_fd: i32,
+        registry: Arc<ioctl::LineRegistry>,
         pin: u8,
         trigger: Trigger,
         debounce: Option<Duration>,
+        options: ioctl::EventOptions,
     ) -> Result<Interrupt> {
+        let event_request = ioctl::EventRequest::new(cdev_fd, pin, trigger, debounce, options)?;
+        registry.register(&[pin], event_request.fd(), options.active_low);
+
         Ok(Interrupt {
             pin,
             trigger,
             debounce,
+            options,
             cdev_fd,
-            event_request: ioctl::EventRequest::new(cdev_fd, pin, trigger, debounce)?,
+            event_request,
+            registry,
         })
     }
 
@@ -53,8 +67,9 @@ impl Interrupt {
         self.pin
     }
 
-    fn set_trigger(&mut self, trigger: Trigger) -> Result<()> {
+    fn set_trigger(&mut self, trigger: Trigger, options: ioctl::EventOptions) -> Result<()> {
         self.trigger = trigger;
+        self.options = options;
 
         self.reset()
     }
@@ -66,14 +81,32 @@ impl Interrupt {
 
     fn reset(&mut self) -> Result<()> {
         // Close the old event fd before opening a new one
+        self.registry.unregister(self.event_request.fd());
         self.event_request.close();
-        self.event_request =
-            ioctl::EventRequest::new(self.cdev_fd, self.pin, self.trigger, self.debounce)?;
+        self.event_request = ioctl::EventRequest::new(
+            self.cdev_fd,
+            self.pin,
+            self.trigger,
+            self.debounce,
+            self.options,
+        )?;
+        self.registry.register(
+            &[self.pin],
+            self.event_request.fd(),
+            self.options.active_low,
+        );
 
         Ok(())
     }
 }
 
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        // The event request is closed after this returns
+        self.registry.unregister(self.event_request.fd());
+    }
+}
+
 #[derive(Debug)]
 struct TriggerStatus {
     interrupt: Option<Interrupt>,
@@ -93,7 +126,11 @@ impl fmt::Debug for EventLoop {
 }
 
 impl EventLoop {
-    pub fn new(cdev_fd: i32, capacity: usize) -> Result<EventLoop> {
+    pub fn new(
+        cdev_fd: i32,
+        registry: Arc<ioctl::LineRegistry>,
+        capacity: usize,
+    ) -> Result<EventLoop> {
         let mut trigger_status = Vec::with_capacity(capacity);
 
         // Initialize trigger_status while circumventing the Copy/Clone requirement
@@ -110,6 +147,7 @@ impl EventLoop {
             events: vec![epoll_event { events: 0, u64: 0 }; capacity],
             trigger_status,
             cdev_fd,
+            registry,
         })
     }
 
@@ -148,7 +186,9 @@ impl EventLoop {
         // Loop until we get any of the events we're waiting for, or a timeout occurs
         let now = Instant::now();
         loop {
-            let num_events = self.poll.wait(&mut self.events, timeout)?;
+            // Only wait for the remaining time if we've already looped
+            let remaining = timeout.map(|t| t.saturating_sub(now.elapsed()));
+            let num_events = self.poll.wait(&mut self.events, remaining)?;
 
             // No events means a timeout occurred
             if num_events == 0 {
@@ -178,11 +218,10 @@ impl EventLoop {
             }
 
             // It's possible a pin we're not waiting for continuously triggers
-            // an interrupt, causing repeated loops with calls to poll() using a
-            // reset timeout value. Make sure we haven't been looping longer than
-            // the requested timeout.
+            // an interrupt, causing repeated loops. Make sure we haven't been
+            // looping longer than the requested timeout.
             if let Some(t) = timeout {
-                if now.elapsed() > t {
+                if now.elapsed() >= t {
                     return Ok(None);
                 }
             }
@@ -194,17 +233,19 @@ impl EventLoop {
         pin: u8,
         trigger: Trigger,
         debounce: Option<Duration>,
+        options: ioctl::EventOptions,
     ) -> Result<()> {
         let trigger_status = &mut self.trigger_status[pin as usize];
 
         trigger_status.triggered = false;
+        trigger_status.event = Event::default();
 
         // Interrupt already exists. We just need to change the trigger.
         if let Some(ref mut interrupt) = trigger_status.interrupt {
-            if interrupt.trigger != trigger {
+            if interrupt.trigger != trigger || interrupt.options != options {
                 // This requires a new event request, so the fd might change
                 self.poll.delete(interrupt.fd())?;
-                interrupt.set_trigger(trigger)?;
+                interrupt.set_trigger(trigger, options)?;
                 self.poll
                     .add(interrupt.fd(), u64::from(pin), EPOLLIN | EPOLLPRI)?;
             }
@@ -213,7 +254,14 @@ impl EventLoop {
         }
 
         // Register a new interrupt
-        let interrupt = Interrupt::new(self.cdev_fd, pin, trigger, debounce)?;
+        let interrupt = Interrupt::new(
+            self.cdev_fd,
+            self.registry.clone(),
+            pin,
+            trigger,
+            debounce,
+            options,
+        )?;
         self.poll
             .add(interrupt.fd(), u64::from(pin), EPOLLIN | EPOLLPRI)?;
         trigger_status.interrupt = Some(interrupt);
@@ -221,11 +269,51 @@ impl EventLoop {
         Ok(())
     }
 
+    pub fn reset_interrupt(&mut self, pin: u8) -> Result<()> {
+        let trigger_status = &mut self.trigger_status[pin as usize];
+
+        trigger_status.triggered = false;
+
+        // Discard any pending trigger events by replacing the event request
+        if let Some(ref mut interrupt) = trigger_status.interrupt {
+            self.poll.delete(interrupt.fd())?;
+            interrupt.reset()?;
+            self.poll
+                .add(interrupt.fd(), u64::from(pin), EPOLLIN | EPOLLPRI)?;
+        }
+
+        Ok(())
+    }
+
+    // Returns the pin number, trigger and last-seen level for every configured interrupt
+    pub fn interrupt_status(&self) -> Vec<(u8, Trigger, Option<Level>)> {
+        self.trigger_status
+            .iter()
+            .filter_map(|trigger_status| {
+                trigger_status.interrupt.as_ref().map(|interrupt| {
+                    let level = match trigger_status.event.trigger {
+                        Trigger::RisingEdge => Some(Level::High),
+                        Trigger::FallingEdge => Some(Level::Low),
+                        _ => None,
+                    };
+
+                    (interrupt.pin, interrupt.trigger, level)
+                })
+            })
+            .collect()
+    }
+
+    pub fn has_interrupt(&self, pin: u8) -> bool {
+        self.trigger_status[pin as usize].interrupt.is_some()
+    }
+
     pub fn clear_interrupt(&mut self, pin: u8) -> Result<()> {
         let trigger_status = &mut self.trigger_status[pin as usize];
 
         trigger_status.triggered = false;
 
+        trigger_status.event = Event::default();
+
         if let Some(interrupt) = trigger_status.interrupt.take() {
             self.poll.delete(interrupt.fd())?;
         }
@@ -234,25 +322,395 @@ impl EventLoop {
     }
 }
 
+// Blocks until the pin reads the specified level, or until timeout expires. The edge
+// towards level is detected through a temporary line request, so the calling thread
+// doesn't use any CPU time while waiting. The line can't already be requested.
+pub(crate) fn wait_for_level(
+    cdev_fd: i32,
+    registry: &Arc<ioctl::LineRegistry>,
+    pin: u8,
+    level: Level,
+    timeout: Duration,
+) -> Result<bool> {
+    LevelWaiter::new(cdev_fd, registry, pin, level, false)?.wait(timeout)
+}
+
+// Temporary line request that detects the edge towards level. Creating the LevelWaiter
+// before changing anything that causes the edge guarantees the edge isn't missed. With
+// active_low set, level is the logic level after inverting the physical level.
+pub(crate) struct LevelWaiter {
+    interrupt: Interrupt,
+    poll: Epoll,
+    registry: Arc<ioctl::LineRegistry>,
+    pin: u8,
+    level: Level,
+}
+
+impl LevelWaiter {
+    pub(crate) fn new(
+        cdev_fd: i32,
+        registry: &Arc<ioctl::LineRegistry>,
+        pin: u8,
+        level: Level,
+        active_low: bool,
+    ) -> Result<LevelWaiter> {
+        let trigger = match level {
+            Level::High => Trigger::RisingEdge,
+            Level::Low => Trigger::FallingEdge,
+        };
+
+        let interrupt = Interrupt::new(
+            cdev_fd,
+            registry.clone(),
+            pin,
+            trigger,
+            None,
+            ioctl::EventOptions {
+                active_low,
+                buffer_size: ioctl::EVENT_BUFFER_SIZE,
+                clock: EventClock::Monotonic,
+                retry_timeout: Duration::default(),
+            },
+        )?;
+
+        let poll = Epoll::new()?;
+        poll.add(interrupt.fd(), 0, EPOLLIN | EPOLLPRI)?;
+
+        Ok(LevelWaiter {
+            interrupt,
+            poll,
+            registry: registry.clone(),
+            pin,
+            level,
+        })
+    }
+
+    pub(crate) fn wait(&mut self, timeout: Duration) -> Result<bool> {
+        let mut events = [epoll_event { events: 0, u64: 0 }; 1];
+        let start = Instant::now();
+        loop {
+            // The level is read through the interrupt's line request, and checked after
+            // every event, in case the edge was a glitch
+            if let Some(current) = self.registry.level(self.pin) {
+                if current? == self.level {
+                    return Ok(true);
+                }
+            }
+
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if remaining == Duration::ZERO {
+                return Ok(false);
+            }
+
+            if self.poll.wait(&mut events, Some(remaining))? > 0 {
+                self.interrupt.event()?;
+            }
+        }
+    }
+}
+
+type AsyncCallback = Box<dyn FnMut(Event) -> CallbackAction + Send>;
+
+// Shared worker threads that monitor asynchronous interrupts for multiple pins, as
+// an alternative to spawning a dedicated thread for every pin.
+pub(crate) struct AsyncWorkers {
+    workers: Vec<Arc<Worker>>,
+    poll_threads: Vec<thread::JoinHandle<()>>,
+    next_worker: AtomicUsize,
+}
+
+impl fmt::Debug for AsyncWorkers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncWorkers")
+            .field("workers", &self.workers.len())
+            .field("next_worker", &self.next_worker)
+            .finish()
+    }
+}
+
+impl AsyncWorkers {
+    pub fn new(threads: usize) -> Result<AsyncWorkers> {
+        let mut workers = Vec::with_capacity(threads);
+        let mut poll_threads = Vec::with_capacity(threads);
+
+        for _ in 0..threads {
+            let worker = Arc::new(Worker::new()?);
+            let thread_worker = worker.clone();
+
+            workers.push(worker);
+            poll_threads.push(thread::spawn(move || thread_worker.run()));
+        }
+
+        Ok(AsyncWorkers {
+            workers,
+            poll_threads,
+            next_worker: AtomicUsize::new(0),
+        })
+    }
+
+    // Assigns interrupts to the worker threads in a round-robin fashion
+    fn next(&self) -> &Arc<Worker> {
+        let index = self.next_worker.fetch_add(1, Ordering::Relaxed) % self.workers.len();
+
+        &self.workers[index]
+    }
+}
+
+impl Drop for AsyncWorkers {
+    fn drop(&mut self) {
+        for worker in &self.workers {
+            let _ = worker.tx.notify();
+        }
+
+        // The last reference to GpioState could be dropped by a callback running on one
+        // of the worker threads, which can't wait for itself to exit
+        let current = thread::current().id();
+        for poll_thread in self.poll_threads.drain(..) {
+            if poll_thread.thread().id() != current {
+                let _ = poll_thread.join();
+            }
+        }
+    }
+}
+
+struct Worker {
+    poll: Epoll,
+    tx: EventFd,
+    entries: Mutex<WorkerEntries>,
+    // Set once the worker thread starts running
+    thread_id: Mutex<Option<ThreadId>>,
+}
+
+struct WorkerEntries {
+    // Entries are identified by a unique id rather than their fd, so events that were
+    // returned by epoll before an entry was removed can't be matched with a new entry
+    // that reuses the same fd
+    entries: HashMap<u64, WorkerEntry>,
+    next_id: u64,
+}
+
+struct WorkerEntry {
+    interrupt: Interrupt,
+    // Callbacks are executed without holding the entries lock, so they're free to add and
+    // remove entries. The callback is taken out once the entry is removed, which waits for
+    // any callback that's currently running.
+    callback: Arc<Mutex<Option<AsyncCallback>>>,
+    last_edge: Arc<Mutex<Option<Instant>>>,
+}
+
+impl fmt::Debug for Worker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker")
+            .field("poll", &self.poll)
+            .field("tx", &self.tx)
+            .field("entries", &format_args!("{{ .. }}"))
+            .finish()
+    }
+}
+
+impl Worker {
+    fn new() -> Result<Worker> {
+        let poll = Epoll::new()?;
+        let tx = EventFd::new()?;
+
+        // The eventfd becomes readable when AsyncWorkers is dropped. Id 0 is never
+        // assigned to an interrupt.
+        poll.add(tx.fd(), 0, EPOLLERR | EPOLLET | EPOLLIN)?;
+
+        Ok(Worker {
+            poll,
+            tx,
+            entries: Mutex::new(WorkerEntries {
+                entries: HashMap::new(),
+                next_id: 1,
+            }),
+            thread_id: Mutex::new(None),
+        })
+    }
+
+    fn add(
+        &self,
+        interrupt: Interrupt,
+        callback: AsyncCallback,
+        last_edge: Arc<Mutex<Option<Instant>>>,
+    ) -> Result<u64> {
+        let mut entries = self.lock_entries();
+        let id = entries.next_id;
+        entries.next_id += 1;
+
+        self.poll.add(interrupt.fd(), id, EPOLLIN | EPOLLPRI)?;
+        entries.entries.insert(
+            id,
+            WorkerEntry {
+                interrupt,
+                callback: Arc::new(Mutex::new(Some(callback))),
+                last_edge,
+            },
+        );
+
+        Ok(id)
+    }
+
+    fn remove(&self, id: u64) -> Result<()> {
+        let entry = match self.lock_entries().entries.remove(&id) {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+
+        let result = self.poll.delete(entry.interrupt.fd());
+
+        // Wait for the callback to finish if it's currently running, so it won't be called
+        // again once we return. A callback that removes its own entry can't wait for
+        // itself, and is dropped by the worker thread when it returns.
+        let on_worker_thread = *self
+            .thread_id
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            == Some(thread::current().id());
+
+        let callback = if on_worker_thread {
+            match entry.callback.try_lock() {
+                Ok(mut callback) => callback.take(),
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().take(),
+                Err(TryLockError::WouldBlock) => None,
+            }
+        } else {
+            entry
+                .callback
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .take()
+        };
+
+        // Drop the callback after its lock is released
+        drop(callback);
+
+        Ok(result?)
+    }
+
+    fn lock_entries(&self) -> MutexGuard<'_, WorkerEntries> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn run(&self) {
+        *self
+            .thread_id
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(thread::current().id());
+
+        let mut events = [epoll_event { events: 0, u64: 0 }; 16];
+        loop {
+            let num_events = match self.poll.wait(&mut events, None) {
+                Ok(num_events) => num_events,
+                Err(_) => return,
+            };
+
+            for event in &events[0..num_events] {
+                let id = event.u64;
+                if id == 0 {
+                    return; // AsyncWorkers is being dropped
+                }
+
+                let pending = match self.lock_entries().entries.get_mut(&id) {
+                    Some(entry) => match entry.interrupt.event() {
+                        Ok(event) => {
+                            *entry.last_edge.lock().unwrap() = Some(Instant::now());
+
+                            Some((event, entry.callback.clone()))
+                        }
+                        // Stop monitoring the pin, similar to a dedicated thread exiting
+                        // because of an error
+                        Err(_) => None,
+                    },
+                    // The entry was removed after epoll returned the event
+                    None => continue,
+                };
+
+                let stop = match pending {
+                    Some((event, callback)) => {
+                        let mut callback = callback
+                            .lock()
+                            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+                        match callback.as_mut() {
+                            // A panicking callback only stops monitoring its own pin
+                            Some(callback) => {
+                                panic::catch_unwind(AssertUnwindSafe(|| callback(event)))
+                                    .map_or(true, |action| action == CallbackAction::Stop)
+                            }
+                            // The entry was removed before we acquired the lock
+                            None => false,
+                        }
+                    }
+                    None => true,
+                };
+
+                if stop {
+                    let entry = self.lock_entries().entries.remove(&id);
+                    if let Some(entry) = entry {
+                        let _ = self.poll.delete(entry.interrupt.fd());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum AsyncMonitor {
+    // Dedicated thread for a single pin
+    Thread {
+        poll_thread: Option<thread::JoinHandle<Result<()>>>,
+        tx: EventFd,
+    },
+    // Entry registered with one of the shared worker threads
+    Shared {
+        worker: Arc<Worker>,
+        id: u64,
+    },
+}
+
 #[derive(Debug)]
 pub struct AsyncInterrupt {
-    poll_thread: Option<thread::JoinHandle<Result<()>>>,
-    tx: EventFd,
+    monitor: AsyncMonitor,
+    // Time at which the most recent event was received
+    last_edge: Arc<Mutex<Option<Instant>>>,
 }
 
 impl AsyncInterrupt {
+    #[allow(clippy::too_many_arguments)]
     pub fn new<C>(
         fd: i32,
+        registry: &Arc<ioctl::LineRegistry>,
         pin: u8,
         trigger: Trigger,
         debounce: Option<Duration>,
+        options: ioctl::EventOptions,
+        workers: &AsyncWorkers,
         mut callback: C,
     ) -> Result<AsyncInterrupt>
     where
-        C: FnMut(Event) + Send + 'static,
+        C: FnMut(Event) -> CallbackAction + Send + 'static,
     {
+        let last_edge = Arc::new(Mutex::new(None));
+
+        if !workers.workers.is_empty() {
+            let interrupt = Interrupt::new(fd, registry.clone(), pin, trigger, debounce, options)?;
+            let worker = workers.next().clone();
+            let id = worker.add(interrupt, Box::new(callback), last_edge.clone())?;
+
+            return Ok(AsyncInterrupt {
+                monitor: AsyncMonitor::Shared { worker, id },
+                last_edge,
+            });
+        }
+
         let tx = EventFd::new()?;
         let rx = tx.fd();
+        let thread_last_edge = last_edge.clone();
+        let registry = registry.clone();
 
         let poll_thread = thread::spawn(move || -> Result<()> {
             let poll = Epoll::new()?;
@@ -260,7 +718,7 @@ impl AsyncInterrupt {
             // rx becomes readable when the main thread calls notify()
             poll.add(rx, rx as u64, EPOLLERR | EPOLLET | EPOLLIN)?;
 
-            let mut interrupt = Interrupt::new(fd, pin, trigger, debounce)?;
+            let mut interrupt = Interrupt::new(fd, registry, pin, trigger, debounce, options)?;
             poll.add(interrupt.fd(), interrupt.fd() as u64, EPOLLIN | EPOLLPRI)?;
 
             let mut events = [epoll_event { events: 0, u64: 0 }; 2];
@@ -272,7 +730,12 @@ impl AsyncInterrupt {
                         if fd == rx {
                             return Ok(()); // The main thread asked us to stop
                         } else if fd == interrupt.fd() {
-                            callback(interrupt.event()?);
+                            let event = interrupt.event()?;
+                            *thread_last_edge.lock().unwrap() = Some(Instant::now());
+
+                            if callback(event) == CallbackAction::Stop {
+                                return Ok(()); // The callback asked us to stop
+                            }
                         }
                     }
                 }
@@ -280,22 +743,37 @@ impl AsyncInterrupt {
         });
 
         Ok(AsyncInterrupt {
-            poll_thread: Some(poll_thread),
-            tx,
+            monitor: AsyncMonitor::Thread {
+                poll_thread: Some(poll_thread),
+                tx,
+            },
+            last_edge,
         })
     }
 
+    pub fn last_edge(&self) -> Option<Instant> {
+        *self.last_edge.lock().unwrap()
+    }
+
     pub fn stop(&mut self) -> Result<()> {
-        self.tx.notify()?;
+        match self.monitor {
+            AsyncMonitor::Thread {
+                ref mut poll_thread,
+                ref tx,
+            } => {
+                tx.notify()?;
+
+                if let Some(poll_thread) = poll_thread.take() {
+                    match poll_thread.join() {
+                        Ok(r) => return r,
+                        Err(_) => return Err(Error::ThreadPanic),
+                    }
+                }
 
-        if let Some(poll_thread) = self.poll_thread.take() {
-            match poll_thread.join() {
-                Ok(r) => return r,
-                Err(_) => return Err(Error::ThreadPanic),
+                Ok(())
             }
+            AsyncMonitor::Shared { ref worker, id } => worker.remove(id),
         }
-
-        Ok(())
     }
 }
 
@@ -1,15 +1,19 @@
 #![allow(clippy::unnecessary_cast)]
 #![allow(dead_code)]
 
-use crate::gpio::{Error, Event, Result, Trigger};
-use libc::{self, c_int, c_void, ENOENT};
+use crate::gpio::{Bias, Error, Event, EventClock, Level, Result, Trigger};
+use libc::{self, c_int, c_void, EBUSY, ENOENT};
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io;
 use std::mem;
 use std::os::unix::io::AsRawFd;
-use std::time::Duration;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[cfg(target_env = "gnu")]
 type IoctlLong = libc::c_ulong;
@@ -22,9 +26,13 @@ const DRIVER_NAME: &[u8] = b"pinctrl-bcm2835\0";
 const DRIVER_NAME_BCM2711: &[u8] = b"pinctrl-bcm2711\0";
 const DRIVER_NAME_BCM2712: &[u8] = b"pinctrl-rp1\0";
 
-// Minimum number of interrupt trigger events buffered by the kernel.
-// This is only a suggested value. The kernel may alter it.
-const EVENT_BUFFER_SIZE: u32 = 1024;
+// Default event buffer size. This is only a suggested value, since the kernel currently
+// caps the buffer at 16 events for each of the maximum of 64 lines per request.
+pub const EVENT_BUFFER_SIZE: u32 = 1024;
+
+// Delay limits when retrying a line request for a busy line
+const RETRY_MIN_BACKOFF: Duration = Duration::from_millis(1);
+const RETRY_MAX_BACKOFF: Duration = Duration::from_millis(100);
 
 // The first 27 offsets correspond to the 40-pin header
 pub const MAX_OFFSET: u32 = 27;
@@ -83,7 +91,7 @@ const NAME_BUFSIZE: usize = 32;
 const LABEL_BUFSIZE: usize = 32;
 
 // Maximum number of requested lines.
-const LINES_MAX: usize = 64;
+pub const LINES_MAX: usize = 64;
 // Maximum number of configuration attributes.
 const LINE_NUM_ATTRS_MAX: usize = 10;
 
@@ -264,7 +272,7 @@ impl LineFlags {
     }
 
     pub fn bias_disabled(&self) -> bool {
-        (self.flags & LINE_FLAG_EDGE_FALLING) > 0
+        (self.flags & LINE_FLAG_BIAS_DISABLED) > 0
     }
 
     pub fn event_clock_realtime(&self) -> bool {
@@ -401,6 +409,61 @@ pub struct LineConfig {
     pub attrs: [LineConfigAttribute; LINE_NUM_ATTRS_MAX],
 }
 
+// Line direction used by LineRequest::configured(). Outputs include their initial level
+// and drive mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineDirection {
+    Input,
+    Output(Level, LineDrive),
+}
+
+// Output drive mode. With open-drain or open-source, the kernel tristates the line
+// instead of driving it high or low, respectively.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineDrive {
+    PushPull,
+    OpenDrain,
+    OpenSource,
+}
+
+impl LineConfig {
+    fn configured(direction: LineDirection, bias: Bias) -> LineConfig {
+        let mut line_config = LineConfig {
+            flags: match direction {
+                LineDirection::Input => LINE_FLAG_INPUT,
+                LineDirection::Output(_, LineDrive::PushPull) => LINE_FLAG_OUTPUT,
+                LineDirection::Output(_, LineDrive::OpenDrain) => {
+                    LINE_FLAG_OUTPUT | LINE_FLAG_OPEN_DRAIN
+                }
+                LineDirection::Output(_, LineDrive::OpenSource) => {
+                    LINE_FLAG_OUTPUT | LINE_FLAG_OPEN_SOURCE
+                }
+            } | match bias {
+                Bias::Off => LINE_FLAG_BIAS_DISABLED,
+                Bias::PullDown => LINE_FLAG_BIAS_PULL_DOWN,
+                Bias::PullUp => LINE_FLAG_BIAS_PULL_UP,
+            },
+            ..Default::default()
+        };
+
+        if let LineDirection::Output(level, _) = direction {
+            line_config.num_attrs = 1;
+            line_config.attrs[0] = LineConfigAttribute {
+                attr: LineAttribute {
+                    id: LINE_ATTR_ID_OUTPUT_VALUES,
+                    padding: 0,
+                    value: LineAttributeValue {
+                        values: if level == Level::High { 1 } else { 0 },
+                    },
+                },
+                mask: 1,
+            };
+        }
+
+        line_config
+    }
+}
+
 impl fmt::Debug for LineConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("LineConfig")
@@ -453,10 +516,11 @@ impl fmt::Debug for LineRequest {
 }
 
 impl LineRequest {
-    pub fn new(cdev_fd: c_int, offset: u32) -> Result<LineRequest> {
+    // Requests one or more lines, while leaving their direction as-is
+    pub fn new(cdev_fd: c_int, offsets: &[u32]) -> Result<LineRequest> {
         let mut line_request = LineRequest::default();
-        line_request.offsets[0] = offset;
-        line_request.num_lines = 1;
+        line_request.offsets[..offsets.len()].copy_from_slice(offsets);
+        line_request.num_lines = offsets.len() as u32;
 
         // Set consumer label, so other processes know we're monitoring this event
         line_request.consumer[0..CONSUMER_LABEL.len()].copy_from_slice(CONSUMER_LABEL.as_bytes());
@@ -471,8 +535,45 @@ impl LineRequest {
         }
     }
 
-    pub fn levels(&self) -> Result<LineValues> {
-        let mut line_values = LineValues::new(0, 0x01);
+    // Requests a single line, and configures its direction and bias
+    pub fn configured(
+        cdev_fd: c_int,
+        offset: u32,
+        direction: LineDirection,
+        bias: Bias,
+    ) -> Result<LineRequest> {
+        let mut line_request = LineRequest::default();
+        line_request.offsets[0] = offset;
+        line_request.num_lines = 1;
+        line_request.consumer[0..CONSUMER_LABEL.len()].copy_from_slice(CONSUMER_LABEL.as_bytes());
+        line_request.config = LineConfig::configured(direction, bias);
+
+        parse_retval!(unsafe { libc::ioctl(cdev_fd, GPIO_V2_GET_LINE_IOCTL, &mut line_request) })?;
+
+        // If the fd is zero or negative, an error occurred
+        if line_request.fd <= 0 {
+            Err(Error::Io(std::io::Error::last_os_error()))
+        } else {
+            Ok(line_request)
+        }
+    }
+
+    // Changes the direction and bias of a line requested through configured()
+    pub fn reconfigure(&mut self, direction: LineDirection, bias: Bias) -> Result<()> {
+        let mut line_config = LineConfig::configured(direction, bias);
+
+        parse_retval!(unsafe {
+            libc::ioctl(self.fd, GPIO_V2_LINE_SET_CONFIG_IOCTL, &mut line_config)
+        })?;
+
+        self.config = line_config;
+
+        Ok(())
+    }
+
+    // Bit positions in mask and the returned values match the index of each requested line
+    pub fn levels(&self, mask: u64) -> Result<LineValues> {
+        let mut line_values = LineValues::new(0, mask);
 
         parse_retval!(unsafe {
             libc::ioctl(self.fd, GPIO_V2_LINE_GET_VALUES_IOCTL, &mut line_values)
@@ -481,6 +582,14 @@ impl LineRequest {
         Ok(line_values)
     }
 
+    pub fn set_levels(&self, mut line_values: LineValues) -> Result<()> {
+        parse_retval!(unsafe {
+            libc::ioctl(self.fd, GPIO_V2_LINE_SET_VALUES_IOCTL, &mut line_values)
+        })?;
+
+        Ok(())
+    }
+
     pub fn close(&mut self) {
         if self.fd > 0 {
             unsafe {
@@ -557,6 +666,9 @@ impl LineEvent {
     pub(crate) fn into_event(self) -> Event {
         Event {
             timestamp: Duration::from_nanos(self.timestamp_ns),
+            // Interrupt triggers request a single line, so seqno and line_seqno are
+            // identical. EventStream requests multiple lines, where only line_seqno
+            // matches the documented per-pin sequence number.
             seqno: self.line_seqno,
             trigger: match self.id {
                 LINE_EVENT_RISING_EDGE => Trigger::RisingEdge,
@@ -566,6 +678,15 @@ impl LineEvent {
     }
 }
 
+// Line configuration options for interrupt trigger event requests
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EventOptions {
+    pub active_low: bool,
+    pub buffer_size: u32,
+    pub clock: EventClock,
+    pub retry_timeout: Duration,
+}
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct EventRequest {
@@ -578,13 +699,28 @@ impl EventRequest {
         pin: u8,
         trigger: Trigger,
         debounce: Option<Duration>,
+        options: EventOptions,
+    ) -> Result<EventRequest> {
+        EventRequest::with_lines(cdev_fd, &[pin], trigger, debounce, options)
+    }
+
+    // Requests edge detection for multiple lines through a single line request. The
+    // caller makes sure the pins are unique, and don't exceed LINES_MAX.
+    pub fn with_lines(
+        cdev_fd: c_int,
+        pins: &[u8],
+        trigger: Trigger,
+        debounce: Option<Duration>,
+        options: EventOptions,
     ) -> Result<EventRequest> {
         let mut line_request = LineRequest::default();
-        line_request.offsets[0] = u32::from(pin);
-        line_request.num_lines = 1;
+        for (offset, &pin) in line_request.offsets.iter_mut().zip(pins) {
+            *offset = u32::from(pin);
+        }
+        line_request.num_lines = pins.len() as u32;
 
         // Suggest a minimum number of buffered events
-        line_request.event_buffer_size = EVENT_BUFFER_SIZE;
+        line_request.event_buffer_size = options.buffer_size;
 
         // Set consumer label, so other processes know we're monitoring this event
         line_request.consumer[0..CONSUMER_LABEL.len()].copy_from_slice(CONSUMER_LABEL.as_bytes());
@@ -597,6 +733,12 @@ impl EventRequest {
                     Trigger::RisingEdge => LINE_FLAG_EDGE_RISING,
                     Trigger::FallingEdge => LINE_FLAG_EDGE_FALLING,
                     Trigger::Both => LINE_FLAG_EDGE_RISING + LINE_FLAG_EDGE_FALLING,
+                }
+                // The kernel inverts the reported edges for active-low lines
+                + if options.active_low { LINE_FLAG_ACTIVE_LOW } else { 0 }
+                + match options.clock {
+                    EventClock::Monotonic => 0,
+                    EventClock::Realtime => LINE_FLAG_EVENT_CLOCK_REALTIME,
                 },
             ..Default::default()
         };
@@ -612,11 +754,15 @@ impl EventRequest {
                         debounce_period_us: debounce.as_micros() as u32,
                     },
                 },
-                mask: 1,
+                mask: if pins.len() >= LINES_MAX {
+                    u64::MAX
+                } else {
+                    (1 << pins.len()) - 1
+                },
             };
         }
 
-        parse_retval!(unsafe { libc::ioctl(cdev_fd, GPIO_V2_GET_LINE_IOCTL, &mut line_request) })?;
+        get_line(cdev_fd, &mut line_request, options.retry_timeout)?;
 
         // If the fd is zero or negative, an error occurred
         if line_request.fd <= 0 {
@@ -647,6 +793,109 @@ impl Drop for EventRequest {
     }
 }
 
+#[derive(Debug, Copy, Clone)]
+struct RegisteredLine {
+    fd: c_int,
+    // Index of the line within the request
+    index: u32,
+    active_low: bool,
+}
+
+// Line requests currently held by interrupt triggers, LineGroup and EventStream. The
+// kernel doesn't allow a line to be requested twice, so the gpiochip backend reads a pin's
+// level through an existing request instead.
+//
+// A request needs to be unregistered before its fd is closed. Reads are performed while
+// the lock is held, which guarantees the fd stays open until the read completes.
+#[derive(Debug, Default)]
+pub(crate) struct LineRegistry {
+    lines: Mutex<HashMap<u8, RegisteredLine>>,
+}
+
+impl LineRegistry {
+    fn lock_lines(&self) -> MutexGuard<'_, HashMap<u8, RegisteredLine>> {
+        self.lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub(crate) fn register(&self, pins: &[u8], fd: c_int, active_low: bool) {
+        let mut lines = self.lock_lines();
+        for (index, &pin) in pins.iter().enumerate() {
+            lines.insert(
+                pin,
+                RegisteredLine {
+                    fd,
+                    index: index as u32,
+                    active_low,
+                },
+            );
+        }
+    }
+
+    pub(crate) fn unregister(&self, fd: c_int) {
+        self.lock_lines().retain(|_, line| line.fd != fd);
+    }
+
+    pub(crate) fn contains(&self, pin: u8) -> bool {
+        self.lock_lines().contains_key(&pin)
+    }
+
+    // Returns None if none of the registered requests include the pin
+    pub(crate) fn level(&self, pin: u8) -> Option<Result<Level>> {
+        let lines = self.lock_lines();
+        let line = lines.get(&pin)?;
+
+        let mut line_values = LineValues::new(0, 1 << line.index);
+        let result = parse_retval!(unsafe {
+            libc::ioctl(line.fd, GPIO_V2_LINE_GET_VALUES_IOCTL, &mut line_values)
+        });
+
+        // Active-low requests report inverted levels
+        Some(result.map_err(Error::Io).map(|_| {
+            if (line_values.bits & (1 << line.index) != 0) != line.active_low {
+                Level::High
+            } else {
+                Level::Low
+            }
+        }))
+    }
+}
+
+// Requests the lines. If a line is busy, the request is retried with an exponential
+// backoff until retry_timeout has elapsed.
+fn get_line(cdev_fd: c_int, line_request: &mut LineRequest, retry_timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + retry_timeout;
+    let mut backoff = RETRY_MIN_BACKOFF;
+
+    loop {
+        match parse_retval!(unsafe {
+            libc::ioctl(cdev_fd, GPIO_V2_GET_LINE_IOCTL, &mut *line_request)
+        }) {
+            Err(ref e) if e.raw_os_error() == Some(EBUSY) && Instant::now() < deadline => {
+                thread::sleep(backoff.min(deadline.saturating_duration_since(Instant::now())));
+                backoff = (backoff * 2).min(RETRY_MAX_BACKOFF);
+            }
+            result => return result.map(|_| ()).map_err(Error::Io),
+        }
+    }
+}
+
+// Open a specific gpiochip device, and verify it's a valid gpiochip
+pub fn open_gpiochip(path: &Path) -> Result<File> {
+    let gpiochip = match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            return Err(Error::PermissionDenied(path.display().to_string()));
+        }
+        Err(e) => return Err(Error::from(e)),
+    };
+
+    ChipInfo::new(gpiochip.as_raw_fd())?;
+
+    Ok(gpiochip)
+}
+
 // Find the correct gpiochip device based on its label
 pub fn find_gpiochip() -> Result<File> {
     for id in 0..=255 {
@@ -0,0 +1,139 @@
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+
+use crate::gpio::{ioctl, Error, GpioState, Result};
+
+/// A group of pins requested through a single `gpiochip` character device line request.
+///
+/// `LineGroup`s are constructed by calling [`Gpio::request_lines`].
+///
+/// A `LineGroup` reads or changes the logic levels of all its pins with a single `ioctl`
+/// call, which makes the operation atomic as far as the kernel is concerned. Unlike the
+/// memory-mapped register access used by [`InputPin`] and [`OutputPin`], this works the
+/// same way on every Raspberry Pi model.
+///
+/// The lines are requested without changing their direction. Use [`OutputPin`] or
+/// [`IoPin`] to configure the pins as inputs or outputs. Requesting the lines doesn't
+/// take ownership of the pins in rppal; it does mark them as in use by the kernel, which
+/// prevents other processes from requesting them, and causes [`Gpio::get`] to return
+/// [`Error::PinClaimed`] for those pins. Retrieve any pins you need through [`Gpio::get`]
+/// before requesting the lines. Depending on the GPIO driver, the kernel may reset the
+/// lines to inputs when the `LineGroup` goes out of scope.
+///
+/// [`Gpio::request_lines`]: struct.Gpio.html#method.request_lines
+/// [`Gpio::get`]: struct.Gpio.html#method.get
+/// [`Error::PinClaimed`]: enum.Error.html#variant.PinClaimed
+/// [`InputPin`]: struct.InputPin.html
+/// [`OutputPin`]: struct.OutputPin.html
+/// [`IoPin`]: struct.IoPin.html
+#[derive(Debug)]
+pub struct LineGroup {
+    line_request: ioctl::LineRequest,
+    line_registry: Arc<ioctl::LineRegistry>,
+    pins: Vec<u8>,
+    mask: u64,
+}
+
+impl LineGroup {
+    pub(crate) fn new(gpio_state: &GpioState, pins: &[u8]) -> Result<LineGroup> {
+        let mut mask = 0u64;
+        let mut unique_pins = Vec::with_capacity(pins.len());
+        for &pin in pins {
+            if pin >= gpio_state.gpio_lines || pin as usize >= ioctl::LINES_MAX {
+                return Err(Error::PinNotAvailable(pin));
+            }
+
+            // Ignore duplicates, since the kernel rejects them
+            if mask & (1 << pin) == 0 {
+                mask |= 1 << pin;
+                unique_pins.push(pin);
+            }
+        }
+
+        let offsets: Vec<u32> = unique_pins.iter().map(|&pin| u32::from(pin)).collect();
+        let line_request = ioctl::LineRequest::new(gpio_state.cdev.as_raw_fd(), &offsets)?;
+        gpio_state
+            .line_registry
+            .register(&unique_pins, line_request.fd, false);
+
+        Ok(LineGroup {
+            line_request,
+            line_registry: gpio_state.line_registry.clone(),
+            pins: unique_pins,
+            mask,
+        })
+    }
+
+    /// Returns the requested BCM GPIO pins, in the order they were specified.
+    pub fn pins(&self) -> &[u8] {
+        &self.pins
+    }
+
+    /// Returns the bit mask of the requested pins, where each bit position matches the pin's
+    /// BCM GPIO number.
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    /// Reads the logic levels of all requested pins.
+    ///
+    /// Each pin's level is stored at the bit position that matches its BCM GPIO number,
+    /// with a bit set to `1` for [`High`]. All other bits are set to `0`.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    pub fn get_values(&self) -> Result<u64> {
+        let line_mask = if self.pins.len() == 64 {
+            u64::MAX
+        } else {
+            (1 << self.pins.len()) - 1
+        };
+
+        let line_values = self.line_request.levels(line_mask)?;
+
+        Ok(self
+            .pins
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| line_values.bits & (1 << index) != 0)
+            .fold(0, |values, (_, &pin)| values | (1 << pin)))
+    }
+
+    /// Sets the logic levels of the pins selected by `mask`.
+    ///
+    /// Bit positions in `mask` and `values` match the pins' BCM GPIO numbers. For every pin
+    /// selected in `mask`, the output is set to [`High`] if the bit in `values` is `1`, and
+    /// [`Low`] otherwise. Bits for pins that aren't part of the `LineGroup` are ignored.
+    ///
+    /// All selected pins need to be configured as outputs, otherwise the kernel
+    /// returns an error.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Low`]: enum.Level.html#variant.Low
+    pub fn set_values(&self, mask: u64, values: u64) -> Result<()> {
+        let mut line_mask = 0u64;
+        let mut line_bits = 0u64;
+        for (index, &pin) in self.pins.iter().enumerate() {
+            if mask & (1 << pin) != 0 {
+                line_mask |= 1 << index;
+
+                if values & (1 << pin) != 0 {
+                    line_bits |= 1 << index;
+                }
+            }
+        }
+
+        if line_mask == 0 {
+            return Ok(());
+        }
+
+        self.line_request
+            .set_levels(ioctl::LineValues::new(line_bits, line_mask))
+    }
+}
+
+impl Drop for LineGroup {
+    fn drop(&mut self) {
+        // The line request is closed after this returns
+        self.line_registry.unregister(self.line_request.fd);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::gpio::{Error, Gpio, Result};
+
+/// A preconfigured set of pins whose logic levels can be read simultaneously.
+///
+/// `PinMask` reads the levels of all selected pins with as few register accesses as possible,
+/// which is useful when sampling parallel data lines, for instance when interfacing with a
+/// parallel ADC.
+///
+/// Reading a `PinMask` doesn't require ownership of the selected pins, and doesn't change
+/// their mode. Make sure the pins are configured as inputs before reading their levels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinMask {
+    pins: Vec<u8>,
+    mask: u64,
+}
+
+impl PinMask {
+    /// Constructs a new `PinMask` for the specified BCM GPIO pins.
+    ///
+    /// Duplicate pins are ignored.
+    ///
+    /// Returns `Err(`[`Error::PinNotAvailable`]`)` if any of the pins can't be addressed
+    /// because its BCM GPIO number is 64 or higher.
+    ///
+    /// [`Error::PinNotAvailable`]: enum.Error.html#variant.PinNotAvailable
+    pub fn new(pins: &[u8]) -> Result<PinMask> {
+        let mut mask = 0u64;
+        let mut unique_pins = Vec::with_capacity(pins.len());
+        for &pin in pins {
+            if pin >= 64 {
+                return Err(Error::PinNotAvailable(pin));
+            }
+
+            // Ignore duplicates
+            if mask & (1 << pin) == 0 {
+                mask |= 1 << pin;
+                unique_pins.push(pin);
+            }
+        }
+
+        Ok(PinMask {
+            pins: unique_pins,
+            mask,
+        })
+    }
+
+    /// Returns the selected BCM GPIO pins, in the order they were specified.
+    pub fn pins(&self) -> &[u8] {
+        &self.pins
+    }
+
+    /// Returns the bit mask of the selected pins, where each bit position matches the pin's
+    /// BCM GPIO number.
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    /// Reads the logic levels of the selected pins.
+    ///
+    /// Each pin's level is stored at the bit position that matches its BCM GPIO number,
+    /// with a bit set to `1` for [`High`]. All other bits are set to `0`.
+    ///
+    /// With the `cdev-only` feature, or when falling back to the `gpiochip` character
+    /// device, each pin is read separately, so the levels aren't captured at the same point
+    /// in time.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    pub fn read(&self, gpio: &Gpio) -> u64 {
+        gpio.inner.gpio_mem.levels() & self.mask
+    }
+
+    /// Reads the logic levels of the selected pins, and packs them into the lowest bits.
+    ///
+    /// The level of the first pin specified in [`new`] is stored in bit 0, the second in
+    /// bit 1, etc., with a bit set to `1` for [`High`].
+    ///
+    /// [`new`]: #method.new
+    /// [`High`]: enum.Level.html#variant.High
+    pub fn read_compact(&self, gpio: &Gpio) -> u64 {
+        let levels = gpio.inner.gpio_mem.levels();
+
+        self.pins
+            .iter()
+            .enumerate()
+            .fold(0u64, |value, (index, &pin)| {
+                value | (((levels >> pin) & 0b1) << index)
+            })
+    }
+}
@@ -0,0 +1,59 @@
+//! Register locations of the BCM283x and BCM2711 GPIO peripheral.
+//!
+//! The functions in this module return the location of a pin's bits within the GPIO
+//! registers, as used internally by rppal when accessing `/dev/gpiomem` or `/dev/mem`.
+//! They're intended for diagnostic tools, and for educational purposes.
+//!
+//! Each function returns a `(register_index, bit_shift)` pair. `register_index` is the
+//! index of the 32-bit register, counted from the start of the GPIO peripheral, so the
+//! register's byte offset is `register_index * 4`. `bit_shift` is the position of the
+//! pin's least significant bit within that register.
+//!
+//! The register layout described here doesn't apply to the RP1 on the Raspberry Pi 5,
+//! which uses separate control and status registers for each pin.
+
+// Register indices (u32) of the first register in each group
+const GPFSEL0: usize = 0;
+const GPSET0: usize = 0x1c / 4;
+const GPCLR0: usize = 0x28 / 4;
+const GPLEV0: usize = 0x34 / 4;
+const GPPUPPDN0: usize = 0xe4 / 4;
+
+/// Returns the location of the pin's 3-bit function select field in the `GPFSELn`
+/// registers.
+///
+/// Each `GPFSELn` register contains the function select fields for 10 pins.
+pub const fn fsel_location(pin: u8) -> (usize, u8) {
+    (GPFSEL0 + pin as usize / 10, (pin % 10) * 3)
+}
+
+/// Returns the location of the pin's bit in the `GPSETn` registers.
+///
+/// Writing a `1` to the bit sets the pin's output state high. Writing a `0` has no effect.
+pub const fn set_location(pin: u8) -> (usize, u8) {
+    (GPSET0 + pin as usize / 32, pin % 32)
+}
+
+/// Returns the location of the pin's bit in the `GPCLRn` registers.
+///
+/// Writing a `1` to the bit sets the pin's output state low. Writing a `0` has no effect.
+pub const fn clr_location(pin: u8) -> (usize, u8) {
+    (GPCLR0 + pin as usize / 32, pin % 32)
+}
+
+/// Returns the location of the pin's bit in the `GPLEVn` registers.
+///
+/// The bit reflects the pin's current logic level, regardless of its mode.
+pub const fn lev_location(pin: u8) -> (usize, u8) {
+    (GPLEV0 + pin as usize / 32, pin % 32)
+}
+
+/// Returns the location of the pin's 2-bit pull-up/pull-down field in the
+/// `GPIO_PUP_PDN_CNTRL_REGn` registers.
+///
+/// These registers are only available on the BCM2711 (Raspberry Pi 4, 400 and Compute
+/// Module 4). Earlier SoCs configure the resistors through the `GPPUD` and `GPPUDCLKn`
+/// registers instead.
+pub const fn pull_location(pin: u8) -> (usize, u8) {
+    (GPPUPPDN0 + pin as usize / 16, (pin % 16) * 2)
+}
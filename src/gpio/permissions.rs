@@ -0,0 +1,192 @@
+use std::error;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::ptr;
+use std::result;
+
+use libc::{self, c_char, gid_t};
+
+use crate::gpio::{ioctl, Error};
+#[cfg(not(feature = "cdev-only"))]
+use crate::system::{DeviceInfo, GpioInterface};
+
+#[cfg(not(feature = "cdev-only"))]
+const PATH_DEV_GPIOMEM: &str = "/dev/gpiomem";
+#[cfg(not(feature = "cdev-only"))]
+const PATH_DEV_GPIOMEM_RP1: &str = "/dev/gpiomem0";
+const PATH_DEV_GPIOCHIPN: &str = "/dev/gpiochipN";
+
+// Group read and write permission bits
+const MODE_GROUP_RW: u32 = 0o060;
+
+/// Describes why the current process is unable to access one of the devices required
+/// by [`Gpio`].
+///
+/// `PermissionProblem`s are returned by [`Gpio::check_permissions`].
+///
+/// [`Gpio`]: struct.Gpio.html
+/// [`Gpio::check_permissions`]: struct.Gpio.html#method.check_permissions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermissionProblem {
+    /// Device not found.
+    ///
+    /// The device file doesn't exist, which usually means the kernel driver isn't loaded,
+    /// or the operating system doesn't support the Raspberry Pi's GPIO peripheral. Contains
+    /// the path of the missing device.
+    DeviceNotFound(String),
+    /// Not in group.
+    ///
+    /// The device can be accessed by members of a specific group, but the current user
+    /// isn't a member of that group. Adding the user to the group (for instance with
+    /// `sudo usermod -a -G gpio <user>`), and logging in again, should fix the problem.
+    NotInGroup {
+        /// Path of the device.
+        path: String,
+        /// Name of the group that owns the device.
+        group: String,
+    },
+    /// Access denied.
+    ///
+    /// The device exists, but the current user doesn't have read and write access, and
+    /// group membership wouldn't solve the problem. This usually means the `udev` rules
+    /// aren't configured to set the appropriate permissions. Contains the path of the
+    /// device.
+    AccessDenied(String),
+}
+
+impl fmt::Display for PermissionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PermissionProblem::DeviceNotFound(ref path) => write!(f, "Device not found: {}", path),
+            PermissionProblem::NotInGroup {
+                ref path,
+                ref group,
+            } => write!(
+                f,
+                "Access to {} requires membership of group {}",
+                path, group
+            ),
+            PermissionProblem::AccessDenied(ref path) => write!(f, "Access denied: {}", path),
+        }
+    }
+}
+
+impl error::Error for PermissionProblem {}
+
+// Checks the same devices Gpio::with_options() opens for the specified options
+#[cfg_attr(feature = "cdev-only", allow(unused_variables))]
+pub(crate) fn check(
+    gpiochip: Option<&Path>,
+    gpiomem: Option<&Path>,
+) -> result::Result<(), PermissionProblem> {
+    #[cfg(not(feature = "cdev-only"))]
+    match gpiomem {
+        Some(path) => check_device(path)?,
+        None => {
+            let path = match DeviceInfo::new().map(|device_info| device_info.gpio_interface()) {
+                Ok(GpioInterface::Rp1) => PATH_DEV_GPIOMEM_RP1,
+                _ => PATH_DEV_GPIOMEM,
+            };
+
+            check_device(Path::new(path))?;
+        }
+    }
+
+    match gpiochip {
+        Some(path) => check_device(path),
+        None => check_gpiochip(),
+    }
+}
+
+// The gpiochip is detected based on its label, which is only available after the device
+// has been opened. Any device that can't be opened is checked for the reason.
+fn check_gpiochip() -> result::Result<(), PermissionProblem> {
+    match ioctl::find_gpiochip() {
+        Ok(_) => Ok(()),
+        Err(Error::PermissionDenied(path)) => {
+            check_device(Path::new(&path))?;
+
+            Err(PermissionProblem::AccessDenied(path))
+        }
+        Err(_) => Err(PermissionProblem::DeviceNotFound(
+            PATH_DEV_GPIOCHIPN.to_owned(),
+        )),
+    }
+}
+
+fn check_device(path: &Path) -> result::Result<(), PermissionProblem> {
+    let path_string = path.to_string_lossy().into_owned();
+
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(PermissionProblem::DeviceNotFound(path_string));
+        }
+        Err(_) => return Err(PermissionProblem::AccessDenied(path_string)),
+    };
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| PermissionProblem::AccessDenied(path_string.clone()))?;
+    if unsafe { libc::access(c_path.as_ptr(), libc::R_OK | libc::W_OK) } == 0 {
+        return Ok(());
+    }
+
+    let gid = metadata.gid();
+    if metadata.mode() & MODE_GROUP_RW == MODE_GROUP_RW && !in_group(gid) {
+        return Err(PermissionProblem::NotInGroup {
+            path: path_string,
+            group: group_name(gid),
+        });
+    }
+
+    Err(PermissionProblem::AccessDenied(path_string))
+}
+
+fn in_group(gid: gid_t) -> bool {
+    if unsafe { libc::getegid() } == gid {
+        return true;
+    }
+
+    let len = unsafe { libc::getgroups(0, ptr::null_mut()) };
+    if len <= 0 {
+        return false;
+    }
+
+    let mut groups: Vec<gid_t> = vec![0; len as usize];
+    let len = unsafe { libc::getgroups(len, groups.as_mut_ptr()) };
+    if len <= 0 {
+        return false;
+    }
+
+    groups[..len as usize].contains(&gid)
+}
+
+fn group_name(gid: gid_t) -> String {
+    let mut group: libc::group = unsafe { mem::zeroed() };
+    let mut buffer: Vec<c_char> = vec![0; 1024];
+    let mut result: *mut libc::group = ptr::null_mut();
+
+    let retval = unsafe {
+        libc::getgrgid_r(
+            gid,
+            &mut group,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+
+    if retval != 0 || result.is_null() || group.gr_name.is_null() {
+        return gid.to_string();
+    }
+
+    unsafe { CStr::from_ptr(group.gr_name) }
+        .to_string_lossy()
+        .into_owned()
+}
@@ -1,11 +1,18 @@
+use std::io;
+use std::mem;
 use std::os::unix::io::AsRawFd;
+use std::ptr;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use super::soft_pwm::SoftPwm;
+use super::clock::{self, ClockSource, GpClock};
+use super::soft_pwm::{self, SoftPwm};
+use super::timer_pwm::TimerPwm;
 use crate::gpio::{
-    interrupt::AsyncInterrupt, Bias, Event, GpioState, Level, Mode, Result, Trigger,
+    interrupt::{self, AsyncInterrupt},
+    ioctl, Bias, CallbackAction, Error, Event, EventClock, GpioState, Level, Mode, Result, Trigger,
 };
 
 const NANOS_PER_SEC: f64 = 1_000_000_000.0;
@@ -25,28 +32,86 @@ macro_rules! impl_pin {
 macro_rules! impl_input {
     () => {
         /// Reads the pin's logic level.
+        ///
+        /// If the pin is configured as active-low, the returned level is inverted.
         #[inline]
         pub fn read(&self) -> Level {
-            self.pin.read()
+            if self.active_low {
+                !self.pin.read()
+            } else {
+                self.pin.read()
+            }
         }
 
         /// Reads the pin's logic level, and returns `true` if it's set to [`Low`].
         ///
+        /// If the pin is configured as active-low, the returned level is inverted.
+        ///
         /// [`Low`]: enum.Level.html#variant.Low
         #[inline]
         pub fn is_low(&self) -> bool {
-            self.pin.read() == Level::Low
+            self.read() == Level::Low
         }
 
         /// Reads the pin's logic level, and returns `true` if it's set to [`High`].
         ///
+        /// If the pin is configured as active-low, the returned level is inverted.
+        ///
         /// [`High`]: enum.Level.html#variant.High
         #[inline]
         pub fn is_high(&self) -> bool {
-            self.pin.read() == Level::High
+            self.read() == Level::High
+        }
+
+        /// Returns the value of `active_low`.
+        #[inline]
+        pub fn active_low(&self) -> bool {
+            self.active_low
+        }
+
+        /// When enabled, inverts the logic level returned by [`read`], [`is_low`] and
+        /// [`is_high`], as well as the reported trigger for interrupt events.
+        ///
+        /// With `active_low` enabled, a [`RisingEdge`] trigger fires when the pin's
+        /// electrical level changes from high to low, and an [`Event`] reports the edge
+        /// as seen from the logical level. Interrupt triggers that are already configured
+        /// aren't affected until they're reconfigured. Output levels aren't affected.
+        ///
+        /// `active_low` is disabled by default.
+        ///
+        /// [`read`]: #method.read
+        /// [`is_low`]: #method.is_low
+        /// [`is_high`]: #method.is_high
+        /// [`RisingEdge`]: enum.Trigger.html#variant.RisingEdge
+        /// [`Event`]: struct.Event.html
+        #[inline]
+        pub fn set_active_low(&mut self, active_low: bool) {
+            self.active_low = active_low;
+        }
+
+        /// Returns the built-in pull-up/pull-down resistor configuration.
+        ///
+        /// The BCM283x SoCs don't allow the resistor configuration to be read back from the
+        /// GPIO registers, so this returns the value set by the `into_input_*` methods or
+        /// the most recent call to [`set_bias`], rather than the current hardware state. Use
+        /// [`Gpio::export_state`] to read back the configuration on models that support it.
+        ///
+        /// [`Gpio::export_state`]: struct.Gpio.html#method.export_state
+        ///
+        /// [`set_bias`]: #method.set_bias
+        #[inline]
+        pub fn bias(&self) -> Bias {
+            self.bias
         }
 
         /// Configures the built-in pull-up/pull-down resistors.
+        ///
+        /// The new configuration is applied immediately, and `set_bias` can be called as
+        /// often as needed without recreating the pin, for instance to alternately charge
+        /// and discharge a capacitive sensor. On the BCM2711 and the RP1, the
+        /// configuration is changed with a single register write. On older models, the
+        /// GPIO controller requires a fixed set-up and hold sequence, which makes each call
+        /// take at least 10 µs.
         #[inline]
         pub fn set_bias(&mut self, bias: Bias) {
             self.pin.set_bias(bias);
@@ -104,11 +169,25 @@ macro_rules! impl_output {
         /// hardware [`Pwm`] peripheral instead. More information can be found [here].
         ///
         /// If `set_pwm` is called when a PWM thread is already active, the existing thread
-        /// will be reconfigured at the end of the current cycle.
+        /// will be reconfigured at the end of the current cycle. On an [`OutputPin`], any
+        /// timer-based PWM signal configured through [`set_timer_pwm`] is stopped.
+        ///
+        /// Returns `Err(`[`Error::FrequencyTooHigh`]`)` if `period` is shorter than 100 µs.
         ///
         /// [`Pwm`]: ../pwm/struct.Pwm.html
         /// [here]: index.html#software-based-pwm
+        /// [`OutputPin`]: struct.OutputPin.html
+        /// [`set_timer_pwm`]: struct.OutputPin.html#method.set_timer_pwm
+        /// [`Error::FrequencyTooHigh`]: enum.Error.html#variant.FrequencyTooHigh
         pub fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<()> {
+            if period > Duration::default() && period < soft_pwm::MIN_PERIOD {
+                return Err(Error::FrequencyTooHigh(
+                    NANOS_PER_SEC / period.as_nanos() as f64,
+                ));
+            }
+
+            self.stop_timer_pwm();
+
             if let Some(ref mut soft_pwm) = self.soft_pwm {
                 soft_pwm.reconfigure(period, pulse_width);
             } else {
@@ -152,9 +231,17 @@ macro_rules! impl_output {
         /// `frequency` is specified in hertz (Hz).
         ///
         /// `duty_cycle` is specified as a floating point value between `0.0` (0%) and `1.0` (100%).
+        /// Values outside of that range are clamped.
+        ///
+        /// Returns `Err(`[`Error::FrequencyTooHigh`]`)` if `frequency` is higher than 10 kHz.
         ///
         /// [`set_pwm`]: #method.set_pwm
+        /// [`Error::FrequencyTooHigh`]: enum.Error.html#variant.FrequencyTooHigh
         pub fn set_pwm_frequency(&mut self, frequency: f64, duty_cycle: f64) -> Result<()> {
+            if frequency > soft_pwm::MAX_FREQUENCY {
+                return Err(Error::FrequencyTooHigh(frequency));
+            }
+
             let period = if frequency <= 0.0 {
                 0.0
             } else {
@@ -202,6 +289,8 @@ macro_rules! impl_reset_on_drop {
         /// [`simple_signal`]: https://crates.io/crates/simple-signal
         pub fn set_reset_on_drop(&mut self, reset_on_drop: bool) {
             self.reset_on_drop = reset_on_drop;
+            self.pin
+                .set_reset_mode(if reset_on_drop { self.prev_mode } else { None });
         }
     };
 }
@@ -228,6 +317,35 @@ macro_rules! impl_drop {
     };
 }
 
+macro_rules! impl_try_cleanup {
+    () => {
+        /// Resets the pin the same way it would be reset when it goes out of scope, and
+        /// verifies the pin's original mode was restored.
+        ///
+        /// Resetting a pin when it's dropped is best-effort, since `drop` can't report
+        /// any errors. `try_cleanup` lets you check whether the reset succeeded, for
+        /// instance before handing the pin over to another process. If `reset_on_drop`
+        /// is set to `false`, the pin is left as-is.
+        ///
+        /// Returns `Err(`[`Error::ResetFailed`]`)` if the pin's mode doesn't match its
+        /// original mode after the reset.
+        ///
+        /// [`Error::ResetFailed`]: enum.Error.html#variant.ResetFailed
+        pub fn try_cleanup(self) -> Result<()> {
+            let pin = self.pin.pin;
+            let gpio_state = self.pin.gpio_state.clone();
+            let restored_mode = self.restored_mode();
+
+            drop(self);
+
+            match restored_mode {
+                Some(mode) if gpio_state.gpio_mem.mode(pin) != mode => Err(Error::ResetFailed(pin)),
+                _ => Ok(()),
+            }
+        }
+    };
+}
+
 macro_rules! impl_eq {
     ($struct:ident) => {
         impl PartialEq for $struct {
@@ -272,12 +390,18 @@ macro_rules! impl_eq {
 pub struct Pin {
     pub(crate) pin: u8,
     gpio_state: Arc<GpioState>,
+    // Initial reset_on_drop value for derived InputPin, OutputPin and IoPin instances
+    reset_on_drop: bool,
 }
 
 impl Pin {
     #[inline]
-    pub(crate) fn new(pin: u8, gpio_state: Arc<GpioState>) -> Pin {
-        Pin { pin, gpio_state }
+    pub(crate) fn new(pin: u8, gpio_state: Arc<GpioState>, reset_on_drop: bool) -> Pin {
+        Pin {
+            pin,
+            gpio_state,
+            reset_on_drop,
+        }
     }
 
     /// Returns the GPIO pin number.
@@ -294,7 +418,76 @@ impl Pin {
         self.gpio_state.gpio_mem.mode(self.pin)
     }
 
+    /// Returns the raw function select value for the pin.
+    ///
+    /// `function_select` can be used to identify alternate functions that don't map
+    /// directly to a [`Mode`] variant. On the BCM283x and BCM2711 SoCs, the 3-bit
+    /// value is read from the pin's `GPFSELn` field.
+    ///
+    /// | Value | Function |
+    /// |-------|----------|
+    /// | `0b000` | Input |
+    /// | `0b001` | Output |
+    /// | `0b100` | Alt0 |
+    /// | `0b101` | Alt1 |
+    /// | `0b110` | Alt2 |
+    /// | `0b111` | Alt3 |
+    /// | `0b011` | Alt4 |
+    /// | `0b010` | Alt5 |
+    ///
+    /// On the Raspberry Pi 5, the 5-bit value is read from the RP1's `FUNCSEL` field,
+    /// where `0`-`8` select Alt0-Alt8, and `31` selects Null. Alt5 is used for GPIO,
+    /// with the direction stored in a separate register.
+    ///
+    /// [`Mode`]: enum.Mode.html
+    #[inline]
+    pub fn function_select(&self) -> u8 {
+        self.gpio_state.gpio_mem.function_select(self.pin)
+    }
+
+    /// Returns the pad group the pin belongs to.
+    ///
+    /// Pins within the same pad group share their drive strength, slew rate and
+    /// input hysteresis configuration, so changing those settings for one pin (for
+    /// instance through the firmware's `gpio` or `dtoverlay` options) also affects all
+    /// other pins in the group.
+    ///
+    /// On the BCM283x and BCM2711 SoCs, the pins are divided into the following groups:
+    ///
+    /// | Pad group | Pins |
+    /// |-----------|------|
+    /// | `0` | GPIO 0-27 |
+    /// | `1` | GPIO 28-45 |
+    /// | `2` | GPIO 46-53 (46-57 on the BCM2711) |
+    ///
+    /// On the Raspberry Pi 5, every pin has its own pad configuration, and all pins that
+    /// are accessible through the RP1's GPIO bank 0 (GPIO 0-27) return `0`.
+    #[inline]
+    pub fn pad_group(&self) -> u8 {
+        self.gpio_state.gpio_mem.pad_group(self.pin)
+    }
+
+    // Interrupts on pins that aren't routed to the GPIO header can never trigger
+    // from external sources on 26-pin models, so we reject them early.
+    pub(crate) fn check_header_pin(&self) -> Result<()> {
+        if self.pin >= 64 || self.gpio_state.header_pins & (1 << self.pin) == 0 {
+            return Err(Error::PinNotOnHeader(self.pin));
+        }
+
+        Ok(())
+    }
+
     /// Reads the pin's logic level.
+    ///
+    /// The level is read from the GPIO level registers (`GPLEVn` on the BCM283x and
+    /// BCM2711, `RIO_IN` on the RP1), which always reflect the pad's current state,
+    /// regardless of the pin's mode. This makes it possible to peek at pins configured for
+    /// an alternate function, such as a UART RX line, without interfering with the
+    /// peripheral. On the RP1, the level reads [`Low`] while the pin's mode is set to
+    /// [`Mode::Null`], since the pad's input buffer is disabled.
+    ///
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`Mode::Null`]: enum.Mode.html#variant.Null
     #[inline]
     pub fn read(&self) -> Level {
         self.gpio_state.gpio_mem.level(self.pin)
@@ -338,6 +531,28 @@ impl Pin {
         InputPin::new(self, Bias::PullUp)
     }
 
+    /// Consumes the `Pin` and returns an [`InputPin`]. Sets the mode to [`Input`]
+    /// and leaves the pin's built-in pull-up/pull-down resistors unchanged.
+    ///
+    /// `into_input_nopull` keeps any resistor configuration applied by the firmware, a
+    /// device tree overlay or another process, which is useful for pins with special
+    /// boot requirements. The resistors aren't changed when `InputPin` goes out of scope
+    /// either.
+    ///
+    /// Since the BCM283x and BCM2711 SoCs don't allow the resistor configuration to be
+    /// read back, [`InputPin::bias`] returns [`Bias::Off`] until [`InputPin::set_bias`]
+    /// is called.
+    ///
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`Input`]: enum.Mode.html#variant.Input
+    /// [`InputPin::bias`]: struct.InputPin.html#method.bias
+    /// [`InputPin::set_bias`]: struct.InputPin.html#method.set_bias
+    /// [`Bias::Off`]: enum.Bias.html#variant.Off
+    #[inline]
+    pub fn into_input_nopull(self) -> InputPin {
+        InputPin::with_current_bias(self)
+    }
+
     /// Consumes the `Pin` and returns an [`OutputPin`]. Sets the mode to [`Mode::Output`]
     /// and leaves the logic level unchanged.
     #[inline]
@@ -347,6 +562,16 @@ impl Pin {
 
     /// Consumes the `Pin` and returns an [`OutputPin`]. Changes the logic level to
     /// [`Level::Low`] and then sets the mode to [`Mode::Output`].
+    ///
+    /// The output level is written to the GPIO registers before the pin's function is
+    /// changed, so the pin never drives its previous output level, which prevents
+    /// glitches when switching from an input or alternate function mode. When RPPAL
+    /// accesses the GPIO registers directly through `/dev/gpiomem` or `/dev/mem`, the
+    /// output level register is written before the function select register. With the
+    /// `cdev-only` feature, or when falling back to the `gpiochip` character device, the
+    /// level is instead passed to the kernel as the initial output value of the line
+    /// request, and the kernel's GPIO driver determines the order in which the registers
+    /// are updated.
     #[inline]
     pub fn into_output_low(mut self) -> OutputPin {
         self.set_low();
@@ -356,6 +581,16 @@ impl Pin {
 
     /// Consumes the `Pin` and returns an [`OutputPin`]. Changes the logic level to
     /// [`Level::High`] and then sets the mode to [`Mode::Output`].
+    ///
+    /// The output level is written to the GPIO registers before the pin's function is
+    /// changed, so the pin never drives its previous output level, which prevents
+    /// glitches when switching from an input or alternate function mode. When RPPAL
+    /// accesses the GPIO registers directly through `/dev/gpiomem` or `/dev/mem`, the
+    /// output level register is written before the function select register. With the
+    /// `cdev-only` feature, or when falling back to the `gpiochip` character device, the
+    /// level is instead passed to the kernel as the initial output value of the line
+    /// request, and the kernel's GPIO driver determines the order in which the registers
+    /// are updated.
     #[inline]
     pub fn into_output_high(mut self) -> OutputPin {
         self.set_high();
@@ -372,6 +607,14 @@ impl Pin {
         IoPin::new(self, mode)
     }
 
+    // Stores the mode the pin should be reset to, so the cleanup handler can reset the
+    // pin without access to the owning InputPin, OutputPin or IoPin
+    #[inline]
+    pub(crate) fn set_reset_mode(&self, mode: Option<Mode>) {
+        self.gpio_state.reset_modes[self.pin as usize]
+            .store(mode.map_or(0, |mode| mode as u8 + 1), Ordering::SeqCst);
+    }
+
     #[inline]
     pub(crate) fn set_mode(&mut self, mode: Mode) {
         self.gpio_state.gpio_mem.set_mode(self.pin, mode);
@@ -404,6 +647,7 @@ impl Pin {
 impl Drop for Pin {
     fn drop(&mut self) {
         // Release taken pin
+        self.set_reset_mode(None);
         self.gpio_state.pins_taken[self.pin as usize].store(false, Ordering::SeqCst);
     }
 }
@@ -434,10 +678,24 @@ pub struct InputPin {
     async_interrupt: Option<AsyncInterrupt>,
     reset_on_drop: bool,
     bias: Bias,
+    active_low: bool,
+    auto_reset: bool,
+    interrupt_buffer_size: u32,
+    interrupt_retry_timeout: Duration,
 }
 
 impl InputPin {
-    pub(crate) fn new(mut pin: Pin, bias: Bias) -> InputPin {
+    pub(crate) fn new(pin: Pin, bias: Bias) -> InputPin {
+        let mut input_pin = InputPin::with_current_bias(pin);
+        input_pin.set_bias(bias);
+
+        input_pin
+    }
+
+    // Sets the mode to Input without changing the pull-up/pull-down resistors. The
+    // bias is stored as Off, so it's also left alone when InputPin goes out of scope.
+    pub(crate) fn with_current_bias(mut pin: Pin) -> InputPin {
+        let reset_on_drop = pin.reset_on_drop;
         let prev_mode = pin.mode();
 
         let prev_mode = if prev_mode == Mode::Input {
@@ -447,20 +705,76 @@ impl InputPin {
             Some(prev_mode)
         };
 
-        pin.set_bias(bias);
+        if reset_on_drop {
+            pin.set_reset_mode(prev_mode);
+        }
 
         InputPin {
             pin,
             prev_mode,
             async_interrupt: None,
-            reset_on_drop: true,
-            bias,
+            reset_on_drop,
+            bias: Bias::Off,
+            active_low: false,
+            auto_reset: false,
+            interrupt_buffer_size: ioctl::EVENT_BUFFER_SIZE,
+            interrupt_retry_timeout: Duration::default(),
         }
     }
 
+    // Records a bias that was applied to the pin through other means, for instance
+    // for multiple pins at once, so it's disabled when InputPin goes out of scope
+    pub(crate) fn store_bias(&mut self, bias: Bias) {
+        self.bias = bias;
+    }
+
     impl_pin!();
     impl_input!();
 
+    /// Reads the pin's logic level `samples` times, and returns the level that was read
+    /// most often.
+    ///
+    /// `read_filtered` is a simple way to reject short glitches on a polled input, without
+    /// configuring a debounce period through an interrupt trigger. The thread sleeps for
+    /// `interval` between consecutive samples, so the call takes at least
+    /// `(samples - 1) * interval`.
+    ///
+    /// If [`High`] and [`Low`] were read equally often, which is only possible for an even
+    /// number of samples, the level of the last sample is returned. Setting `samples` to
+    /// `0` or `1` performs a single read.
+    ///
+    /// If the pin is configured as active-low, the returned level is inverted.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Low`]: enum.Level.html#variant.Low
+    pub fn read_filtered(&self, samples: u8, interval: Duration) -> Level {
+        let mut high = 0u8;
+        let mut level = self.read();
+        if level == Level::High {
+            high += 1;
+        }
+
+        for _ in 1..samples {
+            if interval > Duration::ZERO {
+                thread::sleep(interval);
+            }
+
+            level = self.read();
+            if level == Level::High {
+                high += 1;
+            }
+        }
+
+        let low = samples.max(1) - high;
+        if high > low {
+            Level::High
+        } else if low > high {
+            Level::Low
+        } else {
+            level
+        }
+    }
+
     /// Configures a synchronous interrupt trigger.
     ///
     /// An optional debounce duration can be specified to filter unwanted input noise.
@@ -469,25 +783,189 @@ impl InputPin {
     /// [`Gpio::poll_interrupts`] to block while waiting for a trigger event.
     ///
     /// Any previously configured (a)synchronous interrupt triggers will be cleared.
+    /// Setting `trigger` to [`Trigger::Disabled`] has the same effect as calling
+    /// [`clear_interrupt`].
+    ///
+    /// On models with a 26-pin header, `set_interrupt` returns
+    /// `Err(`[`Error::PinNotOnHeader`]`)` if the pin isn't exposed on the GPIO header.
     ///
     /// [`poll_interrupt`]: #method.poll_interrupt
     /// [`Gpio::poll_interrupts`]: struct.Gpio.html#method.poll_interrupts
+    /// [`Trigger::Disabled`]: enum.Trigger.html#variant.Disabled
+    /// [`clear_interrupt`]: #method.clear_interrupt
+    /// [`Error::PinNotOnHeader`]: enum.Error.html#variant.PinNotOnHeader
     pub fn set_interrupt(&mut self, trigger: Trigger, debounce: Option<Duration>) -> Result<()> {
         self.clear_async_interrupt()?;
 
+        if trigger == Trigger::Disabled {
+            return self.clear_interrupt();
+        }
+
+        self.pin.check_header_pin()?;
+
         // Each pin can only be configured for a single trigger type
         (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).set_interrupt(
             self.pin(),
             trigger,
             debounce,
+            self.event_options(EventClock::Monotonic),
         )
     }
 
+    /// Configures a synchronous interrupt trigger, and returns the pin's logic level
+    /// after the trigger is armed.
+    ///
+    /// `set_interrupt_and_read` behaves the same as [`set_interrupt`], and reads the pin's
+    /// level once the kernel has started monitoring the line. Any edge that occurs after the
+    /// returned level was sampled is guaranteed to produce an interrupt trigger event, which
+    /// makes it possible to initialize an edge-driven state machine without missing the
+    /// first transition.
+    ///
+    /// An edge that occurs between arming the trigger and reading the level is both
+    /// reflected in the returned level, and reported as an event. State machines should
+    /// ignore events that don't change their current state.
+    ///
+    /// If the pin is configured as active-low, the returned level is inverted. Returns
+    /// `Err(`[`Error::Io`]`)` with [`io::ErrorKind::InvalidInput`] if `trigger` is set to
+    /// [`Trigger::Disabled`].
+    ///
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    /// [`Trigger::Disabled`]: enum.Trigger.html#variant.Disabled
+    pub fn set_interrupt_and_read(
+        &mut self,
+        trigger: Trigger,
+        debounce: Option<Duration>,
+    ) -> Result<Level> {
+        if trigger == Trigger::Disabled {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Interrupt trigger can't be disabled",
+            )));
+        }
+
+        self.set_interrupt(trigger, debounce)?;
+
+        Ok(self.read())
+    }
+
+    /// Queries the `gpiochip` character device, and returns `true` if the kernel reports
+    /// the line as active-low.
+    ///
+    /// The kernel only tracks the active-low flag while the line is requested, which is
+    /// the case when a (a)synchronous interrupt trigger is configured. `is_active_low` can
+    /// be used to verify the configuration set through [`set_active_low`] was applied.
+    ///
+    /// [`set_active_low`]: #method.set_active_low
+    pub fn is_active_low(&self) -> Result<bool> {
+        let line_info =
+            ioctl::LineInfo::new(self.pin.gpio_state.cdev.as_raw_fd(), u32::from(self.pin()))?;
+
+        Ok(line_info.flags().active_low())
+    }
+
+    /// Returns `true` if a synchronous interrupt trigger is currently configured
+    /// through [`set_interrupt`].
+    ///
+    /// [`set_interrupt`]: #method.set_interrupt
+    pub fn has_sync_interrupt(&self) -> bool {
+        (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).has_interrupt(self.pin())
+    }
+
     /// Removes a previously configured synchronous interrupt trigger.
     pub fn clear_interrupt(&mut self) -> Result<()> {
         (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).clear_interrupt(self.pin())
     }
 
+    /// Discards any cached or pending synchronous interrupt trigger events without
+    /// blocking.
+    ///
+    /// This has the same effect as the reset performed by [`poll_interrupt`] when `reset`
+    /// is set to `true`. Only trigger events that occur after `clear_pending_interrupt`
+    /// returns will be reported by subsequent calls to [`poll_interrupt`] or
+    /// [`Gpio::poll_interrupts`].
+    ///
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    /// [`Gpio::poll_interrupts`]: struct.Gpio.html#method.poll_interrupts
+    pub fn clear_pending_interrupt(&mut self) -> Result<()> {
+        (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).reset_interrupt(self.pin())
+    }
+
+    /// Returns the suggested number of trigger events the kernel buffers for this pin.
+    pub fn interrupt_buffer_size(&self) -> usize {
+        self.interrupt_buffer_size as usize
+    }
+
+    /// Sets the suggested number of trigger events the kernel buffers for this pin.
+    ///
+    /// Trigger events that occur while the buffer is full are dropped, which shows up as a
+    /// gap in [`Event::seqno`]. The kernel treats `size` as a suggestion, and may allocate a
+    /// larger buffer or cap its size. Current kernels cap the buffer at 1024 events, which
+    /// is also the default. Setting `size` to `0` selects the kernel's own default of 16
+    /// events.
+    ///
+    /// The new size is applied the next time an (a)synchronous interrupt trigger is
+    /// configured.
+    ///
+    /// [`Event::seqno`]: struct.Event.html#structfield.seqno
+    pub fn set_interrupt_buffer_size(&mut self, size: usize) {
+        self.interrupt_buffer_size = size.min(u32::MAX as usize) as u32;
+    }
+
+    /// Returns the maximum duration an interrupt trigger configuration is retried while
+    /// the pin is busy.
+    pub fn interrupt_retry_timeout(&self) -> Duration {
+        self.interrupt_retry_timeout
+    }
+
+    /// Sets the maximum duration an interrupt trigger configuration is retried while the
+    /// pin is busy.
+    ///
+    /// Configuring an interrupt trigger requests the line from the `gpiochip` character
+    /// device, which fails if the line is held by a kernel driver or another process. During
+    /// boot, a driver may hold a line briefly before releasing it. With a non-zero
+    /// `timeout`, the request is retried with an increasing delay of up to 100 ms,
+    /// until the line becomes available or `timeout` has elapsed, after which
+    /// `Err(`[`Error::Io`]`)` is returned with an `EBUSY` OS error.
+    ///
+    /// For [`set_interrupt`], the retries block the calling thread. For
+    /// [`set_async_interrupt`], the retries happen on the pin's dedicated interrupt
+    /// thread, and the callback isn't called until the line has been acquired. When
+    /// shared worker threads are configured through
+    /// [`GpioBuilder::async_interrupt_threads`], the line is requested before
+    /// [`set_async_interrupt`] returns, so the retries block the calling thread instead.
+    ///
+    /// By default, `timeout` is set to zero, and requests for a busy line fail immediately.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`set_async_interrupt`]: #method.set_async_interrupt
+    /// [`GpioBuilder::async_interrupt_threads`]: struct.GpioBuilder.html#method.async_interrupt_threads
+    pub fn set_interrupt_retry_timeout(&mut self, timeout: Duration) {
+        self.interrupt_retry_timeout = timeout;
+    }
+
+    /// Returns the value of `auto_reset`.
+    pub fn auto_reset(&self) -> bool {
+        self.auto_reset
+    }
+
+    /// When enabled, [`poll_interrupt`] and [`poll_interrupt_until`] always clear any
+    /// cached interrupt trigger events before polling for new events, as if `reset` was
+    /// set to `true`.
+    ///
+    /// This prevents stale events from being returned immediately when `reset` is
+    /// accidentally set to `false`. `auto_reset` is disabled by default, and doesn't
+    /// affect [`Gpio::poll_interrupts`].
+    ///
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    /// [`poll_interrupt_until`]: #method.poll_interrupt_until
+    /// [`Gpio::poll_interrupts`]: struct.Gpio.html#method.poll_interrupts
+    pub fn set_auto_reset(&mut self, auto_reset: bool) {
+        self.auto_reset = auto_reset;
+    }
+
     /// Blocks until an interrupt is triggered on the pin, or a timeout occurs.
     ///
     /// This only works after the pin has been configured for synchronous interrupts using
@@ -499,20 +977,25 @@ impl InputPin {
     /// using asynchronous interrupts with [`set_async_interrupt`].
     ///
     /// Setting `reset` to `false` returns any cached interrupt trigger events if available. Setting `reset` to `true`
-    /// clears all cached events before polling for new events.
+    /// clears all cached events before polling for new events. If [`auto_reset`] is enabled, cached events are
+    /// always cleared, regardless of the value of `reset`.
     ///
     /// The `timeout` duration indicates how long the call will block while waiting
     /// for interrupt trigger events, after which an `Ok(None))` is returned.
     /// `timeout` can be set to `None` to wait indefinitely.
+    /// The thread sleeps in a single system call until an event arrives or the timeout
+    /// expires. `timeout` is rounded up to the nearest millisecond.
     ///
     /// [`set_interrupt`]: #method.set_interrupt
     /// [`Gpio::poll_interrupts`]: struct.Gpio.html#method.poll_interrupts
     /// [`set_async_interrupt`]: #method.set_async_interrupt
+    /// [`auto_reset`]: #method.set_auto_reset
     pub fn poll_interrupt(
         &mut self,
         reset: bool,
         timeout: Option<Duration>,
     ) -> Result<Option<Event>> {
+        let reset = reset || self.auto_reset;
         let opt =
             (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).poll(&[self], reset, timeout)?;
 
@@ -523,6 +1006,189 @@ impl InputPin {
         }
     }
 
+    /// Returns the level the pin changed to for the oldest pending interrupt trigger
+    /// event, and removes that event, without blocking.
+    ///
+    /// `take_edge` is useful for checking whether an edge occurred since the previous
+    /// check, for instance in a state machine that runs in a loop. It only works after the
+    /// pin has been configured for synchronous interrupts using [`set_interrupt`]. Returns
+    /// `Ok(Some(`[`High`]`))` for a rising edge, `Ok(Some(`[`Low`]`))` for a falling edge, and
+    /// `Ok(None)` if no events are pending. If multiple edges occurred, each call returns the
+    /// next event until all of them have been taken.
+    ///
+    /// Unlike [`poll_interrupt`], pending events are never discarded, regardless of the
+    /// value of [`auto_reset`].
+    ///
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    /// [`auto_reset`]: #method.set_auto_reset
+    pub fn take_edge(&mut self) -> Result<Option<Level>> {
+        let opt = (*self.pin.gpio_state.sync_interrupts.lock().unwrap()).poll(
+            &[self],
+            false,
+            Some(Duration::default()),
+        )?;
+
+        Ok(opt.map(|(_, event)| match event.trigger {
+            Trigger::RisingEdge => Level::High,
+            _ => Level::Low,
+        }))
+    }
+
+    /// Captures a train of pulses, and returns each level together with the time the
+    /// pin was held at that level.
+    ///
+    /// `read_pulse_train` is intended for decoding signals such as those produced by IR
+    /// receiver modules, where a burst of edges is followed by a period of inactivity. It
+    /// only works after the pin has been configured for synchronous interrupts using
+    /// [`set_interrupt`] with [`Trigger::Both`].
+    ///
+    /// The call blocks until the first edge occurs, or until `timeout` expires, in which
+    /// case `Ok(None)` is returned. `timeout` can be set to `None` to wait indefinitely.
+    /// After the first edge, pulses are collected until no edges have occurred for the
+    /// duration of `idle_gap`. The level the pin returns to after the final edge isn't
+    /// included, since its duration is unknown.
+    ///
+    /// Durations are calculated from the kernel's event timestamps rather than the time
+    /// the events are read, so they're not affected by scheduling delays, as long as the
+    /// kernel's event buffer doesn't overflow. See [`set_interrupt_buffer_size`] for long
+    /// pulse trains.
+    ///
+    /// `read_pulse_train` doesn't decode any specific protocol. As an example, an NEC
+    /// frame starts with a 9 ms burst followed by a 4.5 ms space, after which the bits
+    /// are encoded as 562.5 µs bursts, followed by a 562.5 µs space for a `0`, or a
+    /// 1.6875 ms space for a `1`. Most IR receiver modules have an active-low output,
+    /// which means bursts show up as [`Low`], unless the pin is configured as active-low
+    /// through [`set_active_low`].
+    ///
+    /// Pending events are never discarded before the first edge is read, regardless of
+    /// the value of [`auto_reset`].
+    ///
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`Trigger::Both`]: enum.Trigger.html#variant.Both
+    /// [`set_interrupt_buffer_size`]: #method.set_interrupt_buffer_size
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`set_active_low`]: #method.set_active_low
+    /// [`auto_reset`]: #method.set_auto_reset
+    pub fn read_pulse_train(
+        &mut self,
+        idle_gap: Duration,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Vec<(Level, Duration)>>> {
+        let mut sync_interrupts = self.pin.gpio_state.sync_interrupts.lock().unwrap();
+
+        let mut prev_event = match sync_interrupts.poll(&[self], false, timeout)? {
+            Some((_, event)) => event,
+            None => return Ok(None),
+        };
+
+        let mut pulses = Vec::new();
+        while let Some((_, event)) = sync_interrupts.poll(&[self], false, Some(idle_gap))? {
+            let level = match prev_event.trigger {
+                Trigger::RisingEdge => Level::High,
+                _ => Level::Low,
+            };
+
+            pulses.push((
+                level,
+                event
+                    .timestamp
+                    .checked_sub(prev_event.timestamp)
+                    .unwrap_or_default(),
+            ));
+
+            prev_event = event;
+        }
+
+        Ok(Some(pulses))
+    }
+
+    /// Measures the duty cycle of an incoming PWM signal.
+    ///
+    /// `measure_duty_cycle` captures a single period of the signal, starting at a rising
+    /// edge, and returns the ratio between the time the signal was [`High`] and the
+    /// length of the period, as a value between `0.0` and `1.0`. It only works after the pin
+    /// has been configured for synchronous interrupts using [`set_interrupt`] with
+    /// [`Trigger::Both`]. Any cached or pending interrupt trigger events are discarded
+    /// before the measurement starts.
+    ///
+    /// The measurement is based on the kernel's event timestamps, so it isn't affected by
+    /// scheduling delays. Signals with a frequency above a few kHz may produce inaccurate
+    /// results, depending on the latency of the kernel's interrupt handling.
+    ///
+    /// If no edges are detected before `timeout` expires, the signal is assumed to be
+    /// constant, and `measure_duty_cycle` returns `Ok(Some(1.0))` if the pin reads [`High`],
+    /// or `Ok(Some(0.0))` if it reads [`Low`]. If edges were detected, but a full period
+    /// couldn't be captured before `timeout` expired, `Ok(None)` is returned.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`Trigger::Both`]: enum.Trigger.html#variant.Both
+    pub fn measure_duty_cycle(&mut self, timeout: Duration) -> Result<Option<f64>> {
+        let deadline = Instant::now() + timeout;
+        let mut sync_interrupts = self.pin.gpio_state.sync_interrupts.lock().unwrap();
+
+        let mut reset = true;
+        let mut edge_detected = false;
+        // Timestamps of the rising edge that starts the period, and the falling edge
+        let mut rising: Option<Duration> = None;
+        let mut falling: Option<Duration> = None;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let event = match sync_interrupts.poll(&[self], reset, Some(remaining))? {
+                Some((_, event)) => event,
+                None if edge_detected => return Ok(None),
+                None => return Ok(Some(if self.read() == Level::High { 1.0 } else { 0.0 })),
+            };
+
+            reset = false;
+            edge_detected = true;
+
+            match (event.trigger, rising, falling) {
+                (Trigger::RisingEdge, Some(start), Some(end)) => {
+                    let period = event.timestamp.saturating_sub(start).as_secs_f64();
+                    let high = end.saturating_sub(start).as_secs_f64();
+
+                    return Ok(Some(if period > 0.0 {
+                        (high / period).min(1.0)
+                    } else {
+                        0.0
+                    }));
+                }
+                (Trigger::RisingEdge, _, _) => {
+                    rising = Some(event.timestamp);
+                    falling = None;
+                }
+                (Trigger::FallingEdge, Some(_), None) => falling = Some(event.timestamp),
+                _ => {}
+            }
+        }
+    }
+
+    /// Blocks until an interrupt is triggered on the pin, or until `deadline` is reached.
+    ///
+    /// `poll_interrupt_until` behaves the same as [`poll_interrupt`], but takes an absolute
+    /// deadline instead of a relative timeout. The remaining time is calculated when
+    /// `poll_interrupt_until` is called. If `deadline` has already passed, any cached or
+    /// pending interrupt trigger events are returned without blocking, otherwise `Ok(None)`
+    /// is returned.
+    ///
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    pub fn poll_interrupt_until(
+        &mut self,
+        reset: bool,
+        deadline: Instant,
+    ) -> Result<Option<Event>> {
+        self.poll_interrupt(
+            reset,
+            Some(deadline.saturating_duration_since(Instant::now())),
+        )
+    }
+
     /// Configures an asynchronous interrupt trigger, which executes the callback on a
     /// separate thread when the interrupt is triggered.
     ///
@@ -531,10 +1197,30 @@ impl InputPin {
     /// The callback closure or function pointer is called with a single [`Event`] argument.
     ///
     /// Any previously configured (a)synchronous interrupt triggers for this pin are cleared
-    /// when `set_async_interrupt` is called, or when `InputPin` goes out of scope.
+    /// when `set_async_interrupt` is called, or when `InputPin` goes out of scope. Setting
+    /// `trigger` to [`Trigger::Disabled`] clears any existing triggers without spawning a
+    /// new thread, and `callback` is dropped.
+    ///
+    /// By default, every asynchronous interrupt is monitored by its own thread. When shared
+    /// worker threads are configured through [`GpioBuilder::async_interrupt_threads`], the
+    /// interrupt is assigned to one of those threads instead, and its callback is executed
+    /// one at a time with the callbacks for other pins on the same thread. Either way,
+    /// asynchronous interrupts are monitored independently of the synchronous interrupts
+    /// polled through [`poll_interrupt`] or [`Gpio::poll_interrupts`].
+    /// An asynchronous interrupt on one pin can be active while a synchronous interrupt
+    /// is polled on another pin, and events are only delivered to the interrupt that was
+    /// configured for the pin that triggered them.
+    ///
+    /// On models with a 26-pin header, `set_async_interrupt` returns
+    /// `Err(`[`Error::PinNotOnHeader`]`)` if the pin isn't exposed on the GPIO header.
     ///
     /// [`clear_async_interrupt`]: #method.clear_async_interrupt
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    /// [`Gpio::poll_interrupts`]: struct.Gpio.html#method.poll_interrupts
+    /// [`GpioBuilder::async_interrupt_threads`]: struct.GpioBuilder.html#method.async_interrupt_threads
     /// [`Event`]: struct.Event.html
+    /// [`Trigger::Disabled`]: enum.Trigger.html#variant.Disabled
+    /// [`Error::PinNotOnHeader`]: enum.Error.html#variant.PinNotOnHeader
     pub fn set_async_interrupt<C>(
         &mut self,
         trigger: Trigger,
@@ -544,35 +1230,230 @@ impl InputPin {
     where
         C: FnMut(Event) + Send + 'static,
     {
-        self.clear_interrupt()?;
-        self.clear_async_interrupt()?;
-
-        self.async_interrupt = Some(AsyncInterrupt::new(
-            self.pin.gpio_state.cdev.as_raw_fd(),
-            self.pin(),
-            trigger,
-            debounce,
-            callback,
-        )?);
-
-        Ok(())
+        self.set_async_interrupt_with_clock(trigger, debounce, EventClock::Monotonic, callback)
     }
 
-    /// Removes a previously configured asynchronous interrupt trigger.
-    pub fn clear_async_interrupt(&mut self) -> Result<()> {
-        if let Some(mut interrupt) = self.async_interrupt.take() {
-            interrupt.stop()?;
-        }
-
-        Ok(())
+    /// Configures an asynchronous interrupt trigger, and selects the clock used for
+    /// event timestamps.
+    ///
+    /// `set_async_interrupt_with_clock` behaves the same as [`set_async_interrupt`], except
+    /// that [`Event::timestamp`] is measured using the specified `clock`.
+    /// [`EventClock::Monotonic`] is the right choice for measuring intervals between
+    /// events, while [`EventClock::Realtime`] makes it easier to correlate events with
+    /// logs across multiple systems.
+    ///
+    /// [`set_async_interrupt`]: #method.set_async_interrupt
+    /// [`Event::timestamp`]: struct.Event.html#structfield.timestamp
+    /// [`EventClock::Monotonic`]: enum.EventClock.html#variant.Monotonic
+    /// [`EventClock::Realtime`]: enum.EventClock.html#variant.Realtime
+    pub fn set_async_interrupt_with_clock<C>(
+        &mut self,
+        trigger: Trigger,
+        debounce: Option<Duration>,
+        clock: EventClock,
+        mut callback: C,
+    ) -> Result<()>
+    where
+        C: FnMut(Event) + Send + 'static,
+    {
+        self.configure_async_interrupt(trigger, debounce, clock, move |event| {
+            callback(event);
+            CallbackAction::Continue
+        })
     }
 
-    impl_reset_on_drop!();
-}
-
-impl_drop!(InputPin);
-impl_eq!(InputPin);
-
+    /// Configures an asynchronous interrupt trigger, which executes the callback on a
+    /// separate thread until the callback returns [`CallbackAction::Stop`].
+    ///
+    /// `set_async_interrupt_until` behaves the same as [`set_async_interrupt`], except
+    /// that the callback decides whether the pin keeps being monitored. When the callback
+    /// returns [`CallbackAction::Stop`], the interrupt trigger is released, and the
+    /// callback isn't called again. A dedicated interrupt thread exits at that point, while
+    /// a shared worker thread keeps monitoring its other pins. This makes it possible to
+    /// implement logic such as waiting for a specific number of edges entirely within the
+    /// callback.
+    ///
+    /// [`has_async_interrupt`] keeps returning `true` after monitoring has stopped, until
+    /// [`clear_async_interrupt`] is called, or a new interrupt trigger is configured.
+    ///
+    /// [`CallbackAction::Stop`]: enum.CallbackAction.html#variant.Stop
+    /// [`set_async_interrupt`]: #method.set_async_interrupt
+    /// [`has_async_interrupt`]: #method.has_async_interrupt
+    /// [`clear_async_interrupt`]: #method.clear_async_interrupt
+    pub fn set_async_interrupt_until<C>(
+        &mut self,
+        trigger: Trigger,
+        debounce: Option<Duration>,
+        callback: C,
+    ) -> Result<()>
+    where
+        C: FnMut(Event) -> CallbackAction + Send + 'static,
+    {
+        self.configure_async_interrupt(trigger, debounce, EventClock::Monotonic, callback)
+    }
+
+    fn configure_async_interrupt<C>(
+        &mut self,
+        trigger: Trigger,
+        debounce: Option<Duration>,
+        clock: EventClock,
+        callback: C,
+    ) -> Result<()>
+    where
+        C: FnMut(Event) -> CallbackAction + Send + 'static,
+    {
+        self.clear_interrupt()?;
+        self.clear_async_interrupt()?;
+
+        if trigger == Trigger::Disabled {
+            return Ok(());
+        }
+
+        self.pin.check_header_pin()?;
+
+        self.async_interrupt = Some(AsyncInterrupt::new(
+            self.pin.gpio_state.cdev.as_raw_fd(),
+            &self.pin.gpio_state.line_registry,
+            self.pin(),
+            trigger,
+            debounce,
+            self.event_options(clock),
+            &self.pin.gpio_state.async_workers,
+            callback,
+        )?);
+
+        Ok(())
+    }
+
+    /// Counts pulses shorter than `min_width`, and calls `callback` with the total count
+    /// every time a new glitch is detected.
+    ///
+    /// `set_glitch_counter` can be used to diagnose noisy wiring or electromagnetic
+    /// interference. It configures an asynchronous interrupt trigger for both edges, and
+    /// compares the kernel's timestamps of consecutive events. Whenever two edges occur
+    /// within `min_width` of each other, the pulse between them is counted as a glitch.
+    ///
+    /// The kernel's debounce filter discards short pulses without reporting them, so no
+    /// debounce period is configured while the glitch counter is active. Pulses that are
+    /// too short for the GPIO controller or the kernel to register aren't counted, and
+    /// events dropped because the kernel's event buffer overflowed are missed, so the
+    /// count should be treated as an approximation.
+    ///
+    /// The callback is executed on a separate thread. Any previously configured
+    /// (a)synchronous interrupt triggers are cleared. The glitch counter is stopped by
+    /// calling [`clear_async_interrupt`], or when the pin goes out of scope.
+    ///
+    /// On models with a 26-pin header, `set_glitch_counter` returns
+    /// `Err(`[`Error::PinNotOnHeader`]`)` if the pin isn't exposed on the GPIO header.
+    ///
+    /// [`clear_async_interrupt`]: #method.clear_async_interrupt
+    /// [`Error::PinNotOnHeader`]: enum.Error.html#variant.PinNotOnHeader
+    pub fn set_glitch_counter<C>(&mut self, min_width: Duration, mut callback: C) -> Result<()>
+    where
+        C: FnMut(u64) + Send + 'static,
+    {
+        let mut prev_timestamp: Option<Duration> = None;
+        let mut count = 0u64;
+
+        self.set_async_interrupt(Trigger::Both, None, move |event| {
+            if let Some(prev) = prev_timestamp {
+                if event.timestamp.saturating_sub(prev) < min_width {
+                    count += 1;
+                    callback(count);
+                }
+            }
+
+            prev_timestamp = Some(event.timestamp);
+        })
+    }
+
+    fn event_options(&self, clock: EventClock) -> ioctl::EventOptions {
+        ioctl::EventOptions {
+            active_low: self.active_low,
+            buffer_size: self.interrupt_buffer_size,
+            clock,
+            retry_timeout: self.interrupt_retry_timeout,
+        }
+    }
+
+    /// Returns `true` if an asynchronous interrupt trigger is currently configured.
+    ///
+    /// The trigger remains configured until [`clear_async_interrupt`] is called, even if
+    /// monitoring stopped because of an error.
+    ///
+    /// [`clear_async_interrupt`]: #method.clear_async_interrupt
+    pub fn has_async_interrupt(&self) -> bool {
+        self.async_interrupt.is_some()
+    }
+
+    /// Returns the time that has passed since the most recent trigger event detected by
+    /// the asynchronous interrupt.
+    ///
+    /// `time_since_last_edge` can be used to detect how long the line has been stable,
+    /// for instance to notice a stalled sensor or an idle bus. The time is measured from
+    /// the moment the interrupt thread received the event, right before the callback is
+    /// called, so it includes the kernel's wake-up latency rather than the exact
+    /// [`Event::timestamp`]. Only edges that match the configured trigger and pass the
+    /// debounce filter are taken into account.
+    ///
+    /// Returns `None` if no asynchronous interrupt is configured, or if no event has been
+    /// detected since [`set_async_interrupt`] was called.
+    ///
+    /// [`Event::timestamp`]: struct.Event.html#structfield.timestamp
+    /// [`set_async_interrupt`]: #method.set_async_interrupt
+    pub fn time_since_last_edge(&self) -> Option<Duration> {
+        self.async_interrupt
+            .as_ref()?
+            .last_edge()
+            .map(|last_edge| last_edge.elapsed())
+    }
+
+    /// Removes a previously configured asynchronous interrupt trigger.
+    pub fn clear_async_interrupt(&mut self) -> Result<()> {
+        if let Some(mut interrupt) = self.async_interrupt.take() {
+            interrupt.stop()?;
+        }
+
+        Ok(())
+    }
+
+    // Stops any interrupt triggers, and moves the pin out without running InputPin's drop,
+    // which would reset its mode
+    fn into_parts(self) -> (Pin, Option<Mode>, bool, Bias, bool) {
+        let mut input_pin = mem::ManuallyDrop::new(self);
+
+        let _ = input_pin.clear_interrupt();
+        let _ = input_pin.clear_async_interrupt();
+
+        // The remaining fields don't need to be dropped, since they're either Copy, or
+        // set to None above.
+        let pin = unsafe { ptr::read(&input_pin.pin) };
+
+        (
+            pin,
+            input_pin.prev_mode,
+            input_pin.reset_on_drop,
+            input_pin.bias,
+            input_pin.active_low,
+        )
+    }
+
+    impl_reset_on_drop!();
+    impl_try_cleanup!();
+
+    // Returns the mode the pin is reset to when it goes out of scope, if any
+    fn restored_mode(&self) -> Option<Mode> {
+        if self.reset_on_drop {
+            self.prev_mode
+        } else {
+            None
+        }
+    }
+}
+
+impl_drop!(InputPin);
+impl_eq!(InputPin);
+
 /// GPIO pin configured as output.
 ///
 /// `OutputPin`s are constructed by converting a [`Pin`] using [`Pin::into_output`],
@@ -594,7 +1475,10 @@ pub struct OutputPin {
     prev_mode: Option<Mode>,
     reset_on_drop: bool,
     bias: Bias,
+    drop_level: Option<Level>,
     pub(crate) soft_pwm: Option<SoftPwm>,
+    timer_pwm: Option<TimerPwm>,
+    clock: Option<GpClock>,
     // Stores the softpwm frequency. Used for embedded_hal::PwmPin.
     #[cfg(any(
         feature = "embedded-hal-0",
@@ -613,6 +1497,7 @@ pub struct OutputPin {
 
 impl OutputPin {
     pub(crate) fn new(mut pin: Pin) -> OutputPin {
+        let reset_on_drop = pin.reset_on_drop;
         let prev_mode = pin.mode();
 
         let prev_mode = if prev_mode == Mode::Output {
@@ -622,12 +1507,19 @@ impl OutputPin {
             Some(prev_mode)
         };
 
+        if reset_on_drop {
+            pin.set_reset_mode(prev_mode);
+        }
+
         OutputPin {
             pin,
             prev_mode,
-            reset_on_drop: true,
+            reset_on_drop,
             bias: Bias::Off,
+            drop_level: None,
             soft_pwm: None,
+            timer_pwm: None,
+            clock: None,
             #[cfg(any(
                 feature = "embedded-hal-0",
                 feature = "embedded-hal",
@@ -661,13 +1553,653 @@ impl OutputPin {
         self.pin.read() == Level::High
     }
 
+    /// Reads the logic level of the pin's pad, without changing its mode.
+    ///
+    /// The level registers reflect the actual voltage on the pad, even while the pin is
+    /// driving an output. If the level read back doesn't match the output state that was
+    /// set, an external device is driving the line in the opposite direction, or the line
+    /// is shorted to ground or the supply voltage, and the pin is unable to drive it to the
+    /// intended level. Keep in mind that the pad needs some time to settle after the
+    /// output state has changed, especially with capacitive loads.
+    ///
+    /// The BCM283x and BCM2711 SoCs don't allow the output state to be read back from the
+    /// GPIO registers, so [`is_set_low`] and [`is_set_high`] read the same level register.
+    ///
+    /// [`is_set_low`]: #method.is_set_low
+    /// [`is_set_high`]: #method.is_set_high
+    #[inline]
+    pub fn read_pad(&self) -> Level {
+        self.pin.read()
+    }
+
     impl_output!();
     impl_reset_on_drop!();
+    impl_try_cleanup!();
+
+    // Returns the mode the pin is reset to when it goes out of scope, if any. When
+    // drop_level is set, the pin stays configured as an output.
+    fn restored_mode(&self) -> Option<Mode> {
+        if self.reset_on_drop && self.drop_level.is_none() {
+            self.prev_mode
+        } else {
+            None
+        }
+    }
+
+    /// Configures a software-based PWM signal driven by kernel timers.
+    ///
+    /// `period` indicates the time it takes to complete one cycle, and `pulse_width`
+    /// indicates the amount of time the PWM signal is active during a single period.
+    ///
+    /// Unlike [`set_pwm`], which uses a dedicated thread for every pin that sleeps and
+    /// busy-waits between transitions, `set_timer_pwm` arms a `timerfd` for every
+    /// transition. All timer-based PWM signals share a single thread, which blocks until
+    /// one of the timers expires, so several PWM signals can run without keeping a CPU
+    /// core busy. Deadlines are calculated from the start of each cycle, so timing errors
+    /// don't accumulate. The accuracy of each transition depends on the kernel's timer
+    /// and wake-up latency, which makes this mode best suited for lower frequencies, such
+    /// as LED dimming or servo control. If an accurate or faster PWM signal is required,
+    /// use the hardware [`Pwm`] peripheral instead.
+    ///
+    /// Any software-based PWM signal configured through [`set_pwm`] is stopped. If
+    /// `set_timer_pwm` is called when a timer-based PWM signal is already active, the new
+    /// configuration is applied at the end of the current cycle.
+    ///
+    /// Returns `Err(`[`Error::FrequencyTooHigh`]`)` if `period` is shorter than 100 µs.
+    ///
+    /// [`set_pwm`]: #method.set_pwm
+    /// [`Pwm`]: ../pwm/struct.Pwm.html
+    /// [`Error::FrequencyTooHigh`]: enum.Error.html#variant.FrequencyTooHigh
+    pub fn set_timer_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<()> {
+        if period > Duration::default() && period < soft_pwm::MIN_PERIOD {
+            return Err(Error::FrequencyTooHigh(
+                NANOS_PER_SEC / period.as_nanos() as f64,
+            ));
+        }
+
+        self.clear_pwm()?;
+
+        if let Some(ref mut timer_pwm) = self.timer_pwm {
+            timer_pwm.reconfigure(period, pulse_width);
+        } else {
+            self.timer_pwm = Some(TimerPwm::new(
+                self.pin.pin,
+                &self.pin.gpio_state,
+                period,
+                pulse_width,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    /// Stops a previously configured timer-based PWM signal.
+    ///
+    /// The output state is left at its current level. The shared timer thread is stopped
+    /// once no timer-based PWM signals remain.
+    pub fn clear_timer_pwm(&mut self) {
+        self.timer_pwm = None;
+    }
+
+    /// Returns the output state the pin is set to when it goes out of scope, if configured.
+    pub fn drop_level(&self) -> Option<Level> {
+        self.drop_level
+    }
+
+    /// Configures the output state the pin is set to when it goes out of scope.
+    ///
+    /// If `reset_on_drop` is set to `true` and `drop_level` is set to `Some(level)`, the pin
+    /// stays configured as an output, and is set to `level` when `OutputPin` goes out of
+    /// scope, instead of reverting to its original mode. This makes sure the pin ends up in
+    /// a known safe state. Any software-based PWM signal or hardware clock is stopped first.
+    /// The built-in pull-up/pull-down resistors are still disabled. Setting `drop_level`
+    /// to `None` (default) restores the original mode instead.
+    ///
+    /// The cleanup handler installed by [`Gpio::install_cleanup_handler`] doesn't take
+    /// `drop_level` into account. Use [`Gpio::set_safe_state_on_panic`] to force pins to a
+    /// safe level when a thread panics.
+    ///
+    /// [`Gpio::install_cleanup_handler`]: struct.Gpio.html#method.install_cleanup_handler
+    /// [`Gpio::set_safe_state_on_panic`]: struct.Gpio.html#method.set_safe_state_on_panic
+    pub fn set_drop_level(&mut self, drop_level: Option<Level>) {
+        self.drop_level = drop_level;
+    }
+
+    /// Outputs a square wave with a 50% duty cycle until [`clear_clock`] is called.
+    ///
+    /// `frequency` is specified in hertz (Hz). Setting `frequency` to `0.0` has the same
+    /// effect as calling [`clear_clock`].
+    ///
+    /// If the pin can be routed to one of the BCM283x/BCM2711 general-purpose clocks, the
+    /// square wave is generated in hardware, which results in a jitter-free signal up to
+    /// 125 MHz. The hardware clock uses an integer divisor, so the actual frequency is the
+    /// closest value that evenly divides one of the clock sources (19.2 MHz or 500 MHz, and
+    /// 54 MHz or 750 MHz on the BCM2711). The following pins support hardware clocking:
+    ///
+    /// | Pin | Clock | Mode |
+    /// |-----|-------|------|
+    /// | GPIO 4 | GPCLK0 | [`Mode::Alt0`] |
+    /// | GPIO 5 | GPCLK1 | [`Mode::Alt0`] |
+    /// | GPIO 6 | GPCLK2 | [`Mode::Alt0`] |
+    /// | GPIO 20 | GPCLK0 | [`Mode::Alt5`] |
+    /// | GPIO 21 | GPCLK1 | [`Mode::Alt5`] |
+    ///
+    /// Hardware clocking requires access to `/dev/mem`, which usually means root privileges.
+    /// While the hardware clock is active, the pin's mode is switched to the clock's
+    /// alternate function, and changing the output state has no effect.
+    ///
+    /// `set_clock` falls back to a software-based PWM signal (through [`set_pwm_frequency`])
+    /// if the pin doesn't support hardware clocking, the clock is already in use, `/dev/mem`
+    /// can't be accessed, `frequency` is below the hardware clock's lower limit, or the
+    /// Raspberry Pi model uses the RP1 (Raspberry Pi 5). In that case, `frequency` is
+    /// limited to 10 kHz.
+    ///
+    /// Any previously configured software-based PWM signal, including one configured
+    /// through [`set_timer_pwm`], is stopped.
+    ///
+    /// [`clear_clock`]: #method.clear_clock
+    /// [`set_timer_pwm`]: #method.set_timer_pwm
+    /// [`set_pwm_frequency`]: #method.set_pwm_frequency
+    /// [`Mode::Alt0`]: enum.Mode.html#variant.Alt0
+    /// [`Mode::Alt5`]: enum.Mode.html#variant.Alt5
+    pub fn set_clock(&mut self, frequency: f64) -> Result<()> {
+        self.clear_clock()?;
+        self.clear_timer_pwm();
+
+        if frequency <= 0.0 {
+            return Ok(());
+        }
+
+        if let Some((clock, mode)) = clock::gpclk(self.pin.pin) {
+            if let Ok(Some(gp_clock)) = GpClock::with_frequency(clock, frequency) {
+                self.pin.set_mode(mode);
+                self.clock = Some(gp_clock);
+
+                return Ok(());
+            }
+        }
+
+        self.set_pwm_frequency(frequency, 0.5)
+    }
+
+    /// Stops a square wave previously configured with [`set_clock`].
+    ///
+    /// If the square wave was generated in hardware, the pin's mode is set back to
+    /// [`Mode::Output`]. Otherwise, the software-based PWM signal is stopped.
+    ///
+    /// [`set_clock`]: #method.set_clock
+    /// [`Mode::Output`]: enum.Mode.html#variant.Output
+    pub fn clear_clock(&mut self) -> Result<()> {
+        if self.clock.take().is_some() {
+            self.pin.set_mode(Mode::Output);
+        }
+
+        self.clear_pwm()
+    }
+
+    /// Sets the pin's output state to `level`, calls `f`, and then restores the previous
+    /// output state.
+    ///
+    /// `with_level` simplifies handling chip select or enable lines around a transaction.
+    /// The previous output state is restored when `f` returns, as well as when `f` panics
+    /// and the stack is unwound. The closure receives a mutable reference to the pin, and
+    /// any changes it makes to the output state are overwritten once it returns.
+    ///
+    /// Returns the value returned by `f`.
+    pub fn with_level<F, R>(&mut self, level: Level, f: F) -> R
+    where
+        F: FnOnce(&mut OutputPin) -> R,
+    {
+        let _guard = LevelGuard {
+            pin: self.pin.pin,
+            gpio_state: self.pin.gpio_state.clone(),
+            level: self.pin.read(),
+        };
+
+        self.pin.write(level);
+
+        f(self)
+    }
+
+    /// Returns a cheap, clonable handle that can change the pin's output state from
+    /// other threads.
+    ///
+    /// [`OutputHandle`] writes directly to the GPIO registers, without borrowing the
+    /// `OutputPin`, so there's no need to wrap the pin in a `Mutex` just to change its
+    /// output state from a worker thread. The handle doesn't own the pin, and doesn't
+    /// keep it configured as an output. Once `OutputPin` goes out of scope and the pin
+    /// is reset, or it's retrieved again for a different purpose, the handle keeps
+    /// writing to the output register, which has no effect on pins that aren't
+    /// configured as outputs.
+    ///
+    /// Output changes made through the handle aren't synchronized with software-based
+    /// PWM or other output changes made through `OutputPin`.
+    ///
+    /// [`OutputHandle`]: struct.OutputHandle.html
+    pub fn sharable_handle(&self) -> OutputHandle {
+        OutputHandle {
+            pin: self.pin.pin,
+            gpio_state: self.pin.gpio_state.clone(),
+        }
+    }
+
+    /// Configures the output as open-drain, enforced by the kernel's GPIO driver.
+    ///
+    /// With open-drain enabled, setting the output state to [`High`] tristates the line
+    /// instead of driving it high, so an external or built-in pull-up resistor is needed
+    /// to pull the line high. Depending on the GPIO controller, the kernel either uses a
+    /// hardware open-drain mode, or emulates it by switching the pin to an input. Setting
+    /// `open_drain` to `false` switches back to push-pull.
+    ///
+    /// This is only supported when pins are configured through the `gpiochip` character
+    /// device, which is the case when the `cdev-only` feature is enabled, or RPPAL fell
+    /// back to it (see [`Gpio::register_backend`]), since the kernel can't enforce the
+    /// drive mode for output changes made through the memory-mapped registers. Use
+    /// [`IoPin`] to emulate an open-drain output otherwise. The drive mode is reset to
+    /// push-pull when the pin is switched to an input.
+    ///
+    /// Returns an [`Error::Io`] error if the pins are accessed through the memory-mapped
+    /// registers, or the kernel rejects the configuration.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`IoPin`]: struct.IoPin.html
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`Gpio::register_backend`]: struct.Gpio.html#method.register_backend
+    pub fn set_open_drain_cdev(&mut self, open_drain: bool) -> Result<()> {
+        self.pin.gpio_state.gpio_mem.set_drive(
+            self.pin.pin,
+            if open_drain {
+                ioctl::LineDrive::OpenDrain
+            } else {
+                ioctl::LineDrive::PushPull
+            },
+        )
+    }
+
+    /// Configures the output as open-source, enforced by the kernel's GPIO driver.
+    ///
+    /// With open-source enabled, setting the output state to [`Low`] tristates the line
+    /// instead of driving it low, so an external or built-in pull-down resistor is needed
+    /// to pull the line low. Setting `open_source` to `false` switches back to push-pull.
+    ///
+    /// Like [`set_open_drain_cdev`], this is only supported when pins are configured
+    /// through the `gpiochip` character device.
+    ///
+    /// Returns an [`Error::Io`] error if the pins are accessed through the memory-mapped
+    /// registers, or the kernel rejects the configuration.
+    ///
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`set_open_drain_cdev`]: #method.set_open_drain_cdev
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn set_open_source(&mut self, open_source: bool) -> Result<()> {
+        self.pin.gpio_state.gpio_mem.set_drive(
+            self.pin.pin,
+            if open_source {
+                ioctl::LineDrive::OpenSource
+            } else {
+                ioctl::LineDrive::PushPull
+            },
+        )
+    }
+
+    /// Releases an open-drain output by setting its output state to [`High`], and waits
+    /// until the line reads [`High`], or until `timeout` expires.
+    ///
+    /// After configuring the output as open-drain through [`set_open_drain_cdev`], another
+    /// device can keep holding the line low after it's released, for instance during I2C
+    /// clock stretching or multi-master arbitration. While waiting, the line is requested
+    /// as an input with a rising edge trigger, which is electrically identical to a
+    /// released open-drain output, and `wait_until_released` blocks until the edge is
+    /// detected, so no CPU time is spent while waiting. The open-drain output is
+    /// restored before `wait_until_released` returns. The active-low configuration isn't
+    /// taken into account.
+    ///
+    /// Returns `Ok(true)` as soon as the line reads [`High`], or `Ok(false)` if it's still
+    /// [`Low`] when `timeout` expires.
+    ///
+    /// Returns an [`Error::Io`] error if the pins are accessed through the memory-mapped
+    /// registers, or the output isn't configured as open-drain.
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`set_open_drain_cdev`]: #method.set_open_drain_cdev
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn wait_until_released(&mut self, timeout: Duration) -> Result<bool> {
+        self.pin
+            .gpio_state
+            .gpio_mem
+            .wait_for_release(self.pin.pin, timeout)
+    }
+
+    /// Returns a [`RawOutput`] that changes the pin's output state with a single
+    /// register write, for bit-banging protocols that need the lowest possible overhead.
+    ///
+    /// [`RawOutput`] looks up the location of the pin's bit in the set and clear
+    /// registers once, so [`RawOutput::set_high`] and [`RawOutput::set_low`] compile
+    /// down to a single volatile store, without any of the dispatching
+    /// [`set_high`] and [`set_low`] go through. Like [`OutputHandle`], it doesn't own the
+    /// pin, and its output changes aren't synchronized with software-based PWM or other
+    /// output changes made through `OutputPin`.
+    ///
+    /// The `gpio_raw_toggle.rs` example measures the toggle rate that can be reached on
+    /// your Raspberry Pi model. Keep in mind the peripheral bus limits how fast writes
+    /// actually reach the pin, regardless of how fast the CPU issues them.
+    ///
+    /// Returns an [`Error::Io`] error if the GPIO registers aren't memory-mapped, which
+    /// is the case when the `cdev-only` feature is enabled.
+    ///
+    /// [`RawOutput`]: struct.RawOutput.html
+    /// [`RawOutput::set_high`]: struct.RawOutput.html#method.set_high
+    /// [`RawOutput::set_low`]: struct.RawOutput.html#method.set_low
+    /// [`set_high`]: #method.set_high
+    /// [`set_low`]: #method.set_low
+    /// [`OutputHandle`]: struct.OutputHandle.html
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn raw_output(&self) -> Result<RawOutput> {
+        let (set_reg, clr_reg, mask) = self
+            .pin
+            .gpio_state
+            .gpio_mem
+            .output_registers(self.pin.pin)
+            .ok_or_else(|| {
+                Error::Io(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "GPIO registers aren't memory-mapped",
+                ))
+            })?;
+
+        Ok(RawOutput {
+            pin: self.pin.pin,
+            set_reg,
+            clr_reg,
+            mask,
+            _gpio_state: self.pin.gpio_state.clone(),
+        })
+    }
+
+    /// Plays a sequence of output states, holding each [`Level`] for the specified duration.
+    ///
+    /// `play_pattern` can be used to bit-bang timing-sensitive signals, such as IR remote
+    /// control codes (when driving a modulated transmitter), simple RF transmitter protocols,
+    /// or custom single-wire protocols. The pin is left at the last level of the pattern.
+    ///
+    /// Each step's deadline is calculated relative to the start of the pattern, so timing
+    /// errors don't accumulate over the course of the sequence. Durations of at least 250 µs
+    /// are handled by sleeping for most of the time and busy waiting for the remainder, while
+    /// shorter durations are busy-waited entirely.
+    ///
+    /// `play_pattern` blocks the calling thread until the entire pattern has been played.
+    /// Timing accuracy depends on the scheduler, and the thread can be preempted at any point,
+    /// which stretches the current step. Expect occasional deviations of tens of
+    /// microseconds, and more on a heavily loaded system. Steps shorter than a few
+    /// microseconds can't be reproduced reliably. Running the process with a real-time
+    /// scheduling policy reduces the jitter.
+    ///
+    /// Any previously configured software-based PWM signal, including one configured
+    /// through [`set_timer_pwm`], or hardware clock is stopped.
+    ///
+    /// [`Level`]: enum.Level.html
+    /// [`set_timer_pwm`]: #method.set_timer_pwm
+    pub fn play_pattern(&mut self, pattern: &[(Level, Duration)]) -> Result<()> {
+        self.clear_clock()?;
+        self.clear_timer_pwm();
+
+        let start_ns = soft_pwm::get_time_ns();
+        let mut deadline_ns = 0i64;
+        for &(level, duration) in pattern {
+            self.pin.write(level);
+
+            deadline_ns = deadline_ns.saturating_add(duration.as_nanos() as i64);
+            soft_pwm::wait_until(start_ns, deadline_ns);
+        }
+
+        Ok(())
+    }
+
+    /// Outputs a sequence of samples at a fixed sample rate, effectively turning the pin
+    /// into a 1-bit DAC.
+    ///
+    /// `sample_rate` is specified in hertz (Hz). Each sample is output at the start of its
+    /// tick, and held until the next sample is due. The pin is left at the level of the final
+    /// sample. Sample deadlines are calculated relative to the start of playback, so timing
+    /// errors don't accumulate, and the playback duration matches the number of samples
+    /// divided by `sample_rate`.
+    ///
+    /// The samples are clocked out in software, using the same timing approach as
+    /// [`play_pattern`]. Sample rates up to approximately 50 kHz are achievable on most
+    /// models, although individual samples are delayed whenever the thread is preempted.
+    /// Above approximately 100 kHz, the overhead of reading the clock and writing to the
+    /// GPIO registers becomes significant, and samples are stretched or skipped. [`DmaWave`]
+    /// generates jitter-free hardware-timed output, but only supports periodic pulses, not
+    /// arbitrary sample streams.
+    ///
+    /// `play_samples` blocks the calling thread until all samples have been output. Any
+    /// previously configured software-based PWM signal, including one configured through
+    /// [`set_timer_pwm`], or hardware clock is stopped.
+    ///
+    /// Returns an [`Error::Io`] error with [`io::ErrorKind::InvalidInput`] if `sample_rate`
+    /// isn't a positive, finite number.
+    ///
+    /// [`play_pattern`]: #method.play_pattern
+    /// [`set_timer_pwm`]: #method.set_timer_pwm
+    /// [`DmaWave`]: struct.DmaWave.html
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn play_samples(&mut self, samples: &[Level], sample_rate: f64) -> Result<()> {
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Sample rate needs to be a positive, finite number",
+            )));
+        }
+
+        self.clear_clock()?;
+        self.clear_timer_pwm();
+
+        let period_ns = NANOS_PER_SEC / sample_rate;
+        let start_ns = soft_pwm::get_time_ns();
+        for (index, &level) in samples.iter().enumerate() {
+            self.pin.write(level);
+
+            soft_pwm::wait_until(start_ns, ((index + 1) as f64 * period_ns) as i64);
+        }
+
+        Ok(())
+    }
+}
+
+impl OutputPin {
+    // Returns true if a PWM signal or clock is currently driving the pin
+    pub(crate) fn has_signal(&self) -> bool {
+        self.soft_pwm.is_some() || self.timer_pwm.is_some() || self.clock.is_some()
+    }
+
+    // Called by set_pwm(), so the timer thread and the PWM thread don't both drive the pin
+    fn stop_timer_pwm(&mut self) {
+        self.timer_pwm = None;
+    }
+
+    // Stops any PWM signals and clocks, and moves the pin out without running OutputPin's
+    // drop, which would reset its mode
+    fn into_parts(self) -> (Pin, Option<Mode>, bool, Bias) {
+        let mut output_pin = mem::ManuallyDrop::new(self);
+
+        output_pin.timer_pwm = None;
+        let _ = output_pin.clear_pwm();
+        if output_pin.clock.take().is_some() {
+            output_pin.pin.set_mode(Mode::Output);
+        }
+
+        // The remaining fields don't need to be dropped, since they're either Copy, or
+        // set to None above.
+        let pin = unsafe { ptr::read(&output_pin.pin) };
+
+        (
+            pin,
+            output_pin.prev_mode,
+            output_pin.reset_on_drop,
+            output_pin.bias,
+        )
+    }
+}
+
+impl Drop for OutputPin {
+    /// Stops any hardware clock output, resets the pin's mode (or sets the configured
+    /// drop level) and disables the built-in pull-up/pull-down resistors if
+    /// `reset_on_drop` is set to `true` (default).
+    fn drop(&mut self) {
+        // Make sure the timer thread doesn't change the output state afterwards
+        self.timer_pwm = None;
+
+        // Switch back to output mode before the original mode is restored
+        if self.clock.take().is_some() {
+            self.pin.set_mode(Mode::Output);
+        }
+
+        if !self.reset_on_drop {
+            return;
+        }
+
+        if let Some(level) = self.drop_level {
+            // Make sure the PWM thread doesn't change the output state afterwards
+            drop(self.soft_pwm.take());
+
+            self.pin.write(level);
+        } else if let Some(prev_mode) = self.prev_mode {
+            self.pin.set_mode(prev_mode);
+        }
+
+        if self.bias != Bias::Off {
+            self.pin.set_bias(Bias::Off);
+        }
+    }
 }
 
-impl_drop!(OutputPin);
 impl_eq!(OutputPin);
 
+// Restores a pin's output state when it goes out of scope, which also happens
+// while unwinding after a panic.
+struct LevelGuard {
+    pin: u8,
+    gpio_state: Arc<GpioState>,
+    level: Level,
+}
+
+impl Drop for LevelGuard {
+    fn drop(&mut self) {
+        match self.level {
+            Level::Low => self.gpio_state.gpio_mem.set_low(self.pin),
+            Level::High => self.gpio_state.gpio_mem.set_high(self.pin),
+        }
+    }
+}
+
+/// Clonable handle for changing an output pin's state from any thread.
+///
+/// `OutputHandle`s are constructed by calling [`OutputPin::sharable_handle`].
+///
+/// [`OutputPin::sharable_handle`]: struct.OutputPin.html#method.sharable_handle
+#[derive(Debug, Clone)]
+pub struct OutputHandle {
+    pin: u8,
+    gpio_state: Arc<GpioState>,
+}
+
+impl OutputHandle {
+    /// Returns the GPIO pin number.
+    ///
+    /// Pins are addressed by their BCM numbers, rather than their physical location.
+    #[inline]
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// Sets the pin's output state.
+    #[inline]
+    pub fn write(&self, level: Level) {
+        match level {
+            Level::Low => self.set_low(),
+            Level::High => self.set_high(),
+        }
+    }
+
+    /// Sets the pin's output state to [`Low`].
+    ///
+    /// [`Low`]: enum.Level.html#variant.Low
+    #[inline]
+    pub fn set_low(&self) {
+        self.gpio_state.gpio_mem.set_low(self.pin)
+    }
+
+    /// Sets the pin's output state to [`High`].
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    #[inline]
+    pub fn set_high(&self) {
+        self.gpio_state.gpio_mem.set_high(self.pin)
+    }
+}
+
+/// Single-pin output handle that writes directly to the GPIO set and clear registers.
+///
+/// `RawOutput`s are constructed by calling [`OutputPin::raw_output`].
+///
+/// [`OutputPin::raw_output`]: struct.OutputPin.html#method.raw_output
+#[derive(Debug, Clone)]
+pub struct RawOutput {
+    pin: u8,
+    set_reg: *mut u32,
+    clr_reg: *mut u32,
+    mask: u32,
+    // Keeps the registers mapped
+    _gpio_state: Arc<GpioState>,
+}
+
+impl RawOutput {
+    /// Returns the GPIO pin number.
+    ///
+    /// Pins are addressed by their BCM numbers, rather than their physical location.
+    #[inline]
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// Sets the pin's output state to [`High`].
+    ///
+    /// [`High`]: enum.Level.html#variant.High
+    #[inline(always)]
+    pub fn set_high(&self) {
+        unsafe {
+            ptr::write_volatile(self.set_reg, self.mask);
+        }
+    }
+
+    /// Sets the pin's output state to [`Low`].
+    ///
+    /// [`Low`]: enum.Level.html#variant.Low
+    #[inline(always)]
+    pub fn set_low(&self) {
+        unsafe {
+            ptr::write_volatile(self.clr_reg, self.mask);
+        }
+    }
+
+    /// Sets the pin's output state.
+    #[inline(always)]
+    pub fn write(&self, level: Level) {
+        match level {
+            Level::Low => self.set_low(),
+            Level::High => self.set_high(),
+        }
+    }
+}
+
+// The set and clear registers only affect the bits written as 1, so concurrent writes
+// from multiple threads don't interfere with each other
+unsafe impl Send for RawOutput {}
+
+unsafe impl Sync for RawOutput {}
+
 /// GPIO pin that can be (re)configured for any mode or alternate function.
 ///
 /// `IoPin`s are constructed by converting a [`Pin`] using [`Pin::into_io`].
@@ -676,7 +2208,8 @@ impl_eq!(OutputPin);
 /// An `IoPin` can be reconfigured for any available mode. Depending on the
 /// mode, some methods may not have any effect. For instance, calling a method that
 /// alters the pin's output state won't cause any changes when the pin's mode is set
-/// to [`Mode::Input`].
+/// to [`Mode::Input`]. Reading the pin's logic level works in every mode, including
+/// alternate functions, since the level registers always reflect the pad's current state.
 ///
 /// The `embedded-hal` trait implementations for `IoPin` can be enabled by specifying
 /// the optional `hal` feature in the dependency declaration for the `rppal` crate.
@@ -692,7 +2225,9 @@ pub struct IoPin {
     async_interrupt: Option<AsyncInterrupt>,
     reset_on_drop: bool,
     bias: Bias,
+    active_low: bool,
     pub(crate) soft_pwm: Option<SoftPwm>,
+    clock: Option<GpClock>,
     // Stores the softpwm frequency. Used for embedded_hal::PwmPin.
     #[cfg(any(
         feature = "embedded-hal-0",
@@ -711,6 +2246,7 @@ pub struct IoPin {
 
 impl IoPin {
     pub(crate) fn new(mut pin: Pin, mode: Mode) -> IoPin {
+        let reset_on_drop = pin.reset_on_drop;
         let prev_mode = pin.mode();
 
         let prev_mode = if prev_mode == mode {
@@ -720,14 +2256,20 @@ impl IoPin {
             Some(prev_mode)
         };
 
+        if reset_on_drop {
+            pin.set_reset_mode(prev_mode);
+        }
+
         IoPin {
             pin,
             mode,
             prev_mode,
             async_interrupt: None,
-            reset_on_drop: true,
+            reset_on_drop,
             bias: Bias::Off,
+            active_low: false,
             soft_pwm: None,
+            clock: None,
             #[cfg(any(
                 feature = "embedded-hal-0",
                 feature = "embedded-hal",
@@ -752,6 +2294,14 @@ impl IoPin {
     }
 
     /// Sets the pin's mode.
+    ///
+    /// The output level is retained while the pin is configured as an input. To prevent
+    /// glitches when switching to [`Mode::Output`], call [`set_low`] or [`set_high`]
+    /// before changing the mode.
+    ///
+    /// [`Mode::Output`]: enum.Mode.html#variant.Output
+    /// [`set_low`]: #method.set_low
+    /// [`set_high`]: #method.set_high
     #[inline]
     pub fn set_mode(&mut self, mode: Mode) {
         // If self.prev_mode is set to None, that means the
@@ -760,11 +2310,50 @@ impl IoPin {
         // it to something else now, so we can reset it on drop.
         if self.prev_mode.is_none() && mode != self.mode {
             self.prev_mode = Some(self.mode);
+
+            if self.reset_on_drop {
+                self.pin.set_reset_mode(self.prev_mode);
+            }
         }
 
         self.pin.set_mode(mode);
     }
 
+    /// Releases the line by switching the pin to [`Mode::Input`], and waits until it reads
+    /// [`High`], or until `timeout` expires.
+    ///
+    /// `wait_until_released` is intended for open-drain lines, which are driven low by
+    /// setting the pin's mode to [`Mode::Output`] with its output state set to [`Low`],
+    /// and released by switching to [`Mode::Input`], letting a pull-up resistor pull the
+    /// line high. If another device keeps holding the line low, for instance during I2C
+    /// clock stretching or multi-master arbitration, the level stays [`Low`].
+    ///
+    /// Returns `Ok(true)` as soon as the line reads [`High`], or `Ok(false)` if it's still
+    /// [`Low`] when `timeout` expires. Rather than polling the level continuously,
+    /// `wait_until_released` requests the line from the `gpiochip` character device with
+    /// a rising edge trigger, and blocks until the edge is detected, so no CPU time is
+    /// spent while waiting. The active-low configuration isn't taken into account.
+    ///
+    /// Returns an [`Error::Io`] error if the line can't be requested, for instance because
+    /// an asynchronous interrupt trigger is configured for the pin.
+    ///
+    /// [`Mode::Input`]: enum.Mode.html#variant.Input
+    /// [`Mode::Output`]: enum.Mode.html#variant.Output
+    /// [`High`]: enum.Level.html#variant.High
+    /// [`Low`]: enum.Level.html#variant.Low
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn wait_until_released(&mut self, timeout: Duration) -> Result<bool> {
+        self.set_mode(Mode::Input);
+
+        interrupt::wait_for_level(
+            self.pin.gpio_state.cdev.as_raw_fd(),
+            &self.pin.gpio_state.line_registry,
+            self.pin.pin,
+            Level::High,
+            timeout,
+        )
+    }
+
     /// Configures an asynchronous interrupt trigger, which executes the callback on a
     /// separate thread when the interrupt is triggered.
     ///
@@ -773,32 +2362,66 @@ impl IoPin {
     /// The callback closure or function pointer is called with a single [`Event`] argument.
     ///
     /// Any previously configured (a)synchronous interrupt triggers for this pin are cleared
-    /// when `set_async_interrupt` is called, or when `InputPin` goes out of scope.
+    /// when `set_async_interrupt` is called, or when `InputPin` goes out of scope. Setting
+    /// `trigger` to [`Trigger::Disabled`] clears any existing triggers without spawning a
+    /// new thread, and `callback` is dropped.
+    ///
+    /// On models with a 26-pin header, `set_async_interrupt` returns
+    /// `Err(`[`Error::PinNotOnHeader`]`)` if the pin isn't exposed on the GPIO header.
     ///
     /// [`clear_async_interrupt`]: #method.clear_async_interrupt
     /// [`Event`]: struct.Event.html
+    /// [`Trigger::Disabled`]: enum.Trigger.html#variant.Disabled
+    /// [`Error::PinNotOnHeader`]: enum.Error.html#variant.PinNotOnHeader
     pub fn set_async_interrupt<C>(
         &mut self,
         trigger: Trigger,
         debounce: Option<Duration>,
-        callback: C,
+        mut callback: C,
     ) -> Result<()>
     where
         C: FnMut(Event) + Send + 'static,
     {
         self.clear_async_interrupt()?;
 
+        if trigger == Trigger::Disabled {
+            return Ok(());
+        }
+
+        self.pin.check_header_pin()?;
+
         self.async_interrupt = Some(AsyncInterrupt::new(
             self.pin.gpio_state.cdev.as_raw_fd(),
+            &self.pin.gpio_state.line_registry,
             self.pin(),
             trigger,
             debounce,
-            callback,
+            ioctl::EventOptions {
+                active_low: self.active_low,
+                buffer_size: ioctl::EVENT_BUFFER_SIZE,
+                clock: EventClock::Monotonic,
+                retry_timeout: Duration::default(),
+            },
+            &self.pin.gpio_state.async_workers,
+            move |event| {
+                callback(event);
+                CallbackAction::Continue
+            },
         )?);
 
         Ok(())
     }
 
+    /// Returns `true` if an asynchronous interrupt trigger is currently configured.
+    ///
+    /// The trigger remains configured until [`clear_async_interrupt`] is called, even if
+    /// monitoring stopped because of an error.
+    ///
+    /// [`clear_async_interrupt`]: #method.clear_async_interrupt
+    pub fn has_async_interrupt(&self) -> bool {
+        self.async_interrupt.is_some()
+    }
+
     /// Removes a previously configured asynchronous interrupt trigger.
     pub fn clear_async_interrupt(&mut self) -> Result<()> {
         if let Some(mut interrupt) = self.async_interrupt.take() {
@@ -808,11 +2431,227 @@ impl IoPin {
         Ok(())
     }
 
+    /// Routes one of the BCM283x/BCM2711 general-purpose clocks (GPCLK) to the pin.
+    ///
+    /// The output frequency is the frequency of `source` divided by `divisor`. For
+    /// example, a `divisor` of `19.2` with [`ClockSource::Oscillator`] outputs a 1 MHz
+    /// clock on a Raspberry Pi 3. Integer divisors result in a clean signal with a 50%
+    /// duty cycle. The fractional part of `divisor` is applied through MASH noise-shaping,
+    /// which matches the requested frequency on average, but introduces some jitter.
+    ///
+    /// `divisor` needs to be between `1.0` and `4095.0`, with a minimum of `2.0` when it
+    /// contains a fractional part. The GPIO pads can't reliably drive frequencies above
+    /// 125 MHz. The pin's mode is switched to the clock's alternate function.
+    ///
+    /// | Pin | Clock | Mode |
+    /// |-----|-------|------|
+    /// | GPIO 4 | GPCLK0 | [`Mode::Alt0`] |
+    /// | GPIO 5 | GPCLK1 | [`Mode::Alt0`] |
+    /// | GPIO 6 | GPCLK2 | [`Mode::Alt0`] |
+    /// | GPIO 20 | GPCLK0 | [`Mode::Alt5`] |
+    /// | GPIO 21 | GPCLK1 | [`Mode::Alt5`] |
+    ///
+    /// Configuring the clock manager requires access to `/dev/mem`, which usually means
+    /// root privileges. The clock keeps running until [`clear_gpclk`] is called, or the
+    /// `IoPin` goes out of scope.
+    ///
+    /// Returns `Err(`[`Error::UnknownModel`]`)` on the Raspberry Pi 5, which doesn't
+    /// support the BCM283x/BCM2711 clock manager, and an `io::ErrorKind::InvalidInput`
+    /// error if the pin doesn't support a general-purpose clock or `divisor` is out of
+    /// range. If the clock is already in use by the firmware or another process, an
+    /// `io::ErrorKind::Other` error is returned.
+    ///
+    /// [`ClockSource::Oscillator`]: enum.ClockSource.html#variant.Oscillator
+    /// [`Mode::Alt0`]: enum.Mode.html#variant.Alt0
+    /// [`Mode::Alt5`]: enum.Mode.html#variant.Alt5
+    /// [`clear_gpclk`]: #method.clear_gpclk
+    /// [`Error::UnknownModel`]: enum.Error.html#variant.UnknownModel
+    pub fn set_gpclk(&mut self, source: ClockSource, divisor: f64) -> Result<()> {
+        self.clock = None;
+
+        let (clock, mode) = clock::gpclk(self.pin.pin).ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Pin doesn't support a general-purpose clock",
+            ))
+        })?;
+
+        self.clock = Some(GpClock::new(clock, source, divisor)?);
+        self.set_mode(mode);
+
+        Ok(())
+    }
+
+    /// Stops a general-purpose clock previously configured with [`set_gpclk`].
+    ///
+    /// The pin's mode isn't changed.
+    ///
+    /// [`set_gpclk`]: #method.set_gpclk
+    pub fn clear_gpclk(&mut self) {
+        self.clock = None;
+    }
+
+    /// Consumes the `IoPin` and returns an [`InputPin`]. Sets the mode to [`Input`].
+    ///
+    /// The underlying pin isn't released, so it can't be retrieved by another caller
+    /// in the meantime. The returned `InputPin` keeps the original mode, `reset_on_drop`
+    /// setting, bias and `active_low` setting, which means the pin is still reset to the
+    /// mode it had before the `IoPin` was constructed when it goes out of scope.
+    ///
+    /// Any asynchronous interrupt trigger, software-based PWM signal or general-purpose
+    /// clock is stopped.
+    ///
+    /// [`InputPin`]: struct.InputPin.html
+    /// [`Input`]: enum.Mode.html#variant.Input
+    pub fn into_input(self) -> InputPin {
+        let active_low = self.active_low;
+        let (pin, prev_mode, reset_on_drop, bias) = self.into_parts();
+
+        let mut input_pin = InputPin::new(pin, bias);
+        input_pin.active_low = active_low;
+        if let Some(prev_mode) = prev_mode {
+            input_pin.prev_mode = if prev_mode == Mode::Input {
+                None
+            } else {
+                Some(prev_mode)
+            };
+        }
+        input_pin.set_reset_on_drop(reset_on_drop);
+
+        input_pin
+    }
+
+    /// Consumes the `IoPin` and returns an [`OutputPin`]. Sets the mode to [`Output`].
+    ///
+    /// The underlying pin isn't released, so it can't be retrieved by another caller
+    /// in the meantime. The returned `OutputPin` keeps the original mode, `reset_on_drop`
+    /// setting and bias, which means the pin is still reset to the mode it had before the
+    /// `IoPin` was constructed when it goes out of scope.
+    ///
+    /// Any asynchronous interrupt trigger, software-based PWM signal or general-purpose
+    /// clock is stopped. To prevent glitches, call [`set_low`] or [`set_high`] before
+    /// converting the pin.
+    ///
+    /// [`OutputPin`]: struct.OutputPin.html
+    /// [`Output`]: enum.Mode.html#variant.Output
+    /// [`set_low`]: #method.set_low
+    /// [`set_high`]: #method.set_high
+    pub fn into_output(self) -> OutputPin {
+        let (pin, prev_mode, reset_on_drop, bias) = self.into_parts();
+
+        let mut output_pin = OutputPin::new(pin);
+        output_pin.bias = bias;
+        if let Some(prev_mode) = prev_mode {
+            output_pin.prev_mode = if prev_mode == Mode::Output {
+                None
+            } else {
+                Some(prev_mode)
+            };
+        }
+        output_pin.set_reset_on_drop(reset_on_drop);
+
+        output_pin
+    }
+
+    // Stops any background threads and clocks, and moves the pin out without running
+    // IoPin's drop, which would reset its mode
+    fn into_parts(self) -> (Pin, Option<Mode>, bool, Bias) {
+        let mut io_pin = mem::ManuallyDrop::new(self);
+
+        let _ = io_pin.clear_async_interrupt();
+        let _ = io_pin.clear_pwm();
+        io_pin.clock = None;
+
+        // The remaining fields don't need to be dropped, since they're either Copy, or
+        // set to None above.
+        let pin = unsafe { ptr::read(&io_pin.pin) };
+
+        (pin, io_pin.prev_mode, io_pin.reset_on_drop, io_pin.bias)
+    }
 
     impl_input!();
     impl_output!();
     impl_reset_on_drop!();
+    impl_try_cleanup!();
+
+    // Returns the mode the pin is reset to when it goes out of scope, if any
+    fn restored_mode(&self) -> Option<Mode> {
+        if self.reset_on_drop {
+            self.prev_mode
+        } else {
+            None
+        }
+    }
 }
 
 impl_drop!(IoPin);
+
+impl IoPin {
+    // Called by set_pwm(). IoPin doesn't support timer-based PWM, so there's nothing to stop.
+    fn stop_timer_pwm(&mut self) {}
+
+    // Wraps a pin that was owned by an InputPin or OutputPin, keeping its current mode, and
+    // the mode it's reset to when it goes out of scope
+    fn from_parts(
+        pin: Pin,
+        prev_mode: Option<Mode>,
+        reset_on_drop: bool,
+        bias: Bias,
+        active_low: bool,
+    ) -> IoPin {
+        IoPin {
+            mode: pin.mode(),
+            pin,
+            prev_mode,
+            async_interrupt: None,
+            reset_on_drop,
+            bias,
+            active_low,
+            soft_pwm: None,
+            clock: None,
+            #[cfg(any(
+                feature = "embedded-hal-0",
+                feature = "embedded-hal",
+                feature = "embedded-hal-nb"
+            ))]
+            frequency: 0.0,
+            #[cfg(any(
+                feature = "embedded-hal-0",
+                feature = "embedded-hal",
+                feature = "embedded-hal-nb"
+            ))]
+            duty_cycle: 0.0,
+        }
+    }
+}
+
+impl From<InputPin> for IoPin {
+    /// Converts an [`InputPin`] into an `IoPin`, without changing the pin's mode.
+    ///
+    /// The `IoPin` keeps the original mode, `reset_on_drop` setting, bias and `active_low`
+    /// setting. Any interrupt triggers are cleared.
+    ///
+    /// [`InputPin`]: struct.InputPin.html
+    fn from(input_pin: InputPin) -> IoPin {
+        let (pin, prev_mode, reset_on_drop, bias, active_low) = input_pin.into_parts();
+
+        IoPin::from_parts(pin, prev_mode, reset_on_drop, bias, active_low)
+    }
+}
+
+impl From<OutputPin> for IoPin {
+    /// Converts an [`OutputPin`] into an `IoPin`, without changing the pin's mode or output
+    /// state.
+    ///
+    /// The `IoPin` keeps the original mode, `reset_on_drop` setting and bias. Any
+    /// software-based PWM signal or general-purpose clock is stopped, and a drop level
+    /// configured for the [`OutputPin`] no longer applies.
+    ///
+    /// [`OutputPin`]: struct.OutputPin.html
+    fn from(output_pin: OutputPin) -> IoPin {
+        let (pin, prev_mode, reset_on_drop, bias) = output_pin.into_parts();
+
+        IoPin::from_parts(pin, prev_mode, reset_on_drop, bias, false)
+    }
+}
 impl_eq!(IoPin);
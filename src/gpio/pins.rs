@@ -0,0 +1,125 @@
+//! Named BCM GPIO numbers for common peripheral functions.
+//!
+//! The constants in this module can be passed to [`Gpio::get`] to retrieve the pin
+//! associated with a peripheral function, which saves looking up pin numbers and makes
+//! code self-documenting.
+//!
+//! ```no_run
+//! use rppal::gpio::{pins, Gpio};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut mosi = Gpio::new()?.get(pins::SPI0_MOSI)?.into_output();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The pins listed here are the defaults used on the BCM283x and BCM2711 SoCs, as well as
+//! the RP1 on the Raspberry Pi 5. Most of them can be moved to different pins through
+//! device tree overlays. On the original Raspberry Pi Model B Rev 1, the I2C pins on the
+//! GPIO header (physical pins 3 and 5) are connected to I2C0 instead of I2C1.
+//! [`header_i2c_sda`] and [`header_i2c_scl`] return the correct pin for the current model.
+//!
+//! [`Gpio::get`]: ../struct.Gpio.html#method.get
+//! [`header_i2c_sda`]: fn.header_i2c_sda.html
+//! [`header_i2c_scl`]: fn.header_i2c_scl.html
+
+use crate::system::{DeviceInfo, Model};
+
+/// I2C0 SDA (physical pin 27, or 3 on the Raspberry Pi Model B Rev 1). Used for the HAT ID EEPROM.
+pub const I2C0_SDA: u8 = 0;
+/// I2C0 SCL (physical pin 28, or 5 on the Raspberry Pi Model B Rev 1). Used for the HAT ID EEPROM.
+pub const I2C0_SCL: u8 = 1;
+/// I2C1 SDA (physical pin 3).
+pub const I2C1_SDA: u8 = 2;
+/// I2C1 SCL (physical pin 5).
+pub const I2C1_SCL: u8 = 3;
+
+/// SPI0 Slave Select 1 (physical pin 26).
+pub const SPI0_CE1: u8 = 7;
+/// SPI0 Slave Select 0 (physical pin 24).
+pub const SPI0_CE0: u8 = 8;
+/// SPI0 MISO (physical pin 21).
+pub const SPI0_MISO: u8 = 9;
+/// SPI0 MOSI (physical pin 19).
+pub const SPI0_MOSI: u8 = 10;
+/// SPI0 SCLK (physical pin 23).
+pub const SPI0_SCLK: u8 = 11;
+
+/// SPI1 Slave Select 2 (physical pin 36).
+pub const SPI1_CE2: u8 = 16;
+/// SPI1 Slave Select 1 (physical pin 11).
+pub const SPI1_CE1: u8 = 17;
+/// SPI1 Slave Select 0 (physical pin 12).
+pub const SPI1_CE0: u8 = 18;
+/// SPI1 MISO (physical pin 35).
+pub const SPI1_MISO: u8 = 19;
+/// SPI1 MOSI (physical pin 38).
+pub const SPI1_MOSI: u8 = 20;
+/// SPI1 SCLK (physical pin 40).
+pub const SPI1_SCLK: u8 = 21;
+
+/// UART TX (physical pin 8). Shared by the PL011 UART and the mini UART.
+pub const UART0_TXD: u8 = 14;
+/// UART RX (physical pin 10). Shared by the PL011 UART and the mini UART.
+pub const UART0_RXD: u8 = 15;
+
+/// PWM channel 0 (physical pin 12).
+pub const PWM0: u8 = 18;
+/// PWM channel 1 (physical pin 35).
+pub const PWM1: u8 = 19;
+/// Alternative pin for PWM channel 0 (physical pin 32).
+pub const PWM0_ALT: u8 = 12;
+/// Alternative pin for PWM channel 1 (physical pin 33).
+pub const PWM1_ALT: u8 = 13;
+
+/// General-purpose clock 0 (physical pin 7).
+pub const GPCLK0: u8 = 4;
+/// General-purpose clock 1 (physical pin 29).
+pub const GPCLK1: u8 = 5;
+/// General-purpose clock 2 (physical pin 31).
+pub const GPCLK2: u8 = 6;
+
+/// PCM/I2S clock (physical pin 12).
+pub const PCM_CLK: u8 = 18;
+/// PCM/I2S frame sync (physical pin 35).
+pub const PCM_FS: u8 = 19;
+/// PCM/I2S data in (physical pin 38).
+pub const PCM_DIN: u8 = 20;
+/// PCM/I2S data out (physical pin 40).
+pub const PCM_DOUT: u8 = 21;
+
+/// Returns the SDA pin of the I2C bus connected to the GPIO header (physical pin 3).
+///
+/// Returns [`I2C0_SDA`] on the Raspberry Pi Model B Rev 1, and [`I2C1_SDA`] on every
+/// other model, or if the model can't be identified.
+///
+/// [`I2C0_SDA`]: constant.I2C0_SDA.html
+/// [`I2C1_SDA`]: constant.I2C1_SDA.html
+pub fn header_i2c_sda() -> u8 {
+    if is_model_b_rev1() {
+        I2C0_SDA
+    } else {
+        I2C1_SDA
+    }
+}
+
+/// Returns the SCL pin of the I2C bus connected to the GPIO header (physical pin 5).
+///
+/// Returns [`I2C0_SCL`] on the Raspberry Pi Model B Rev 1, and [`I2C1_SCL`] on every
+/// other model, or if the model can't be identified.
+///
+/// [`I2C0_SCL`]: constant.I2C0_SCL.html
+/// [`I2C1_SCL`]: constant.I2C1_SCL.html
+pub fn header_i2c_scl() -> u8 {
+    if is_model_b_rev1() {
+        I2C0_SCL
+    } else {
+        I2C1_SCL
+    }
+}
+
+fn is_model_b_rev1() -> bool {
+    DeviceInfo::new()
+        .map(|device_info| device_info.model() == Model::RaspberryPiBRev1)
+        .unwrap_or(false)
+}
@@ -20,6 +20,11 @@ const BUSYWAIT_REMAINDER: i64 = 100;
 
 const NANOS_PER_SEC: i64 = 1_000_000_000;
 
+// Highest reliable frequency in hertz (Hz). Anything faster results in excessive jitter.
+pub(crate) const MAX_FREQUENCY: f64 = 10_000.0;
+// Shortest period that corresponds to MAX_FREQUENCY
+pub(crate) const MIN_PERIOD: Duration = Duration::from_micros(100);
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 enum Msg {
     Reconfigure(Duration, Duration),
@@ -42,38 +47,16 @@ impl SoftPwm {
         let (sender, receiver): (Sender<Msg>, Receiver<Msg>) = mpsc::channel();
 
         let pwm_thread = thread::spawn(move || -> Result<()> {
-            // Set the scheduling policy to real-time round robin at the highest priority. This
-            // will silently fail if we're not running as root.
-            #[cfg(target_env = "gnu")]
-            let params = sched_param {
-                sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
-            };
-
-            #[cfg(target_env = "musl")]
-            let params = sched_param {
-                sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
-                sched_ss_low_priority: 0,
-                sched_ss_repl_period: timespec {
-                    tv_sec: 0,
-                    tv_nsec: 0,
-                },
-                sched_ss_init_budget: timespec {
-                    tv_sec: 0,
-                    tv_nsec: 0,
-                },
-                sched_ss_max_repl: 0,
+            set_realtime_priority();
+
+            // Busy waiting at real-time priority would starve every other thread on a
+            // single-core model, so only sleep there, at the cost of additional jitter
+            let (sleep_threshold, busywait_max) = if is_single_core() {
+                (0, 0)
+            } else {
+                (SLEEP_THRESHOLD, BUSYWAIT_MAX)
             };
 
-            unsafe {
-                libc::sched_setscheduler(0, SCHED_RR, &params);
-            }
-
-            // Set timer slack to 1 ns (default = 50 µs). This is only relevant if we're unable
-            // to set a real-time scheduling policy.
-            unsafe {
-                libc::prctl(PR_SET_TIMERSLACK, 1);
-            }
-
             let mut period_ns = period.as_nanos() as i64;
             let mut pulse_width_ns = pulse_width.as_nanos() as i64;
 
@@ -87,8 +70,8 @@ impl SoftPwm {
 
                 // Sleep if we have enough time remaining, while reserving some time
                 // for busy waiting to compensate for sleep taking longer than needed.
-                if pulse_width_ns >= SLEEP_THRESHOLD {
-                    sleep(Duration::from_nanos((pulse_width_ns - BUSYWAIT_MAX) as u64));
+                if pulse_width_ns >= sleep_threshold {
+                    sleep(Duration::from_nanos((pulse_width_ns - busywait_max) as u64));
                 }
 
                 // Busy-wait for the remaining active time, minus BUSYWAIT_REMAINDER
@@ -124,8 +107,8 @@ impl SoftPwm {
 
                 // Sleep if we have enough time remaining, while reserving some time
                 // for busy waiting to compensate for sleep taking longer than needed.
-                if remaining_ns >= SLEEP_THRESHOLD {
-                    sleep(Duration::from_nanos((remaining_ns - BUSYWAIT_MAX) as u64));
+                if remaining_ns >= sleep_threshold {
+                    sleep(Duration::from_nanos((remaining_ns - busywait_max) as u64));
                 }
 
                 // Busy-wait for the remaining inactive time, minus BUSYWAIT_REMAINDER
@@ -178,8 +161,52 @@ impl Drop for SoftPwm {
 // safe because all usage of Sender::send() is locked behind &mut self.
 unsafe impl Sync for SoftPwm {}
 
+// Returns true if the process can only run on a single CPU core, for instance on the
+// Raspberry Pi Zero and Pi 1.
+pub(crate) fn is_single_core() -> bool {
+    thread::available_parallelism()
+        .map(|cores| cores.get() == 1)
+        .unwrap_or(false)
+}
+
+// Sets the scheduling policy of the calling thread to real-time round robin at the
+// highest priority, and reduces its timer slack.
+pub(crate) fn set_realtime_priority() {
+    // Set the scheduling policy to real-time round robin at the highest priority. This
+    // will silently fail if we're not running as root.
+    #[cfg(target_env = "gnu")]
+    let params = sched_param {
+        sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
+    };
+
+    #[cfg(target_env = "musl")]
+    let params = sched_param {
+        sched_priority: unsafe { libc::sched_get_priority_max(SCHED_RR) },
+        sched_ss_low_priority: 0,
+        sched_ss_repl_period: timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        sched_ss_init_budget: timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        sched_ss_max_repl: 0,
+    };
+
+    unsafe {
+        libc::sched_setscheduler(0, SCHED_RR, &params);
+    }
+
+    // Set timer slack to 1 ns (default = 50 µs). This is only relevant if we're unable
+    // to set a real-time scheduling policy.
+    unsafe {
+        libc::prctl(PR_SET_TIMERSLACK, 1);
+    }
+}
+
 #[inline(always)]
-fn get_time_ns() -> i64 {
+pub(crate) fn get_time_ns() -> i64 {
     let mut ts = timespec {
         tv_sec: 0,
         tv_nsec: 0,
@@ -191,3 +218,24 @@ fn get_time_ns() -> i64 {
 
     (ts.tv_sec as i64 * NANOS_PER_SEC) + ts.tv_nsec as i64
 }
+
+// Blocks until duration_ns has elapsed since start_ns. Sleeps if we have enough time
+// remaining, while reserving some time for busy waiting to compensate for sleep taking
+// longer than needed. On single-core models, only sleeps, for the same reason as the
+// software PWM threads.
+pub(crate) fn wait_until(start_ns: i64, duration_ns: i64) {
+    let remaining_ns = duration_ns - (get_time_ns() - start_ns);
+    if is_single_core() {
+        if remaining_ns > 0 {
+            sleep(Duration::from_nanos(remaining_ns as u64));
+        }
+
+        return;
+    }
+
+    if remaining_ns >= SLEEP_THRESHOLD {
+        sleep(Duration::from_nanos((remaining_ns - BUSYWAIT_MAX) as u64));
+    }
+
+    while (duration_ns - (get_time_ns() - start_ns)) > BUSYWAIT_REMAINDER {}
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use super::epoll::{epoll_event, Epoll, EventFd, TimerFd, EPOLLERR, EPOLLET, EPOLLIN};
+use super::soft_pwm::{get_time_ns, set_realtime_priority};
+use super::{GpioState, Result};
+
+// Software-based PWM channels driven by timerfd expirations. All channels share a
+// single thread, which waits for any of the timers to expire through epoll. The
+// thread is started when the first channel is created, and stopped once the last
+// channel is dropped.
+#[derive(Debug)]
+pub(crate) struct TimerPwm {
+    timer_loop: Arc<TimerLoop>,
+    id: u64,
+}
+
+impl TimerPwm {
+    pub(crate) fn new(
+        pin: u8,
+        gpio_state: &Arc<GpioState>,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> Result<TimerPwm> {
+        let timer_loop = TimerLoop::get(gpio_state)?;
+        let id = timer_loop
+            .shared
+            .add(pin, gpio_state.clone(), period, pulse_width)?;
+
+        Ok(TimerPwm { timer_loop, id })
+    }
+
+    // Applies the new period and pulse width at the start of the next cycle
+    pub(crate) fn reconfigure(&mut self, period: Duration, pulse_width: Duration) {
+        let mut channels = self.timer_loop.shared.lock_channels();
+        if let Some(channel) = channels.get_mut(&self.id) {
+            channel.configure(period, pulse_width);
+
+            // An idle channel doesn't have a timer armed that would pick up the change
+            if channel.idle {
+                let _ = channel.start_cycle(get_time_ns());
+            }
+        }
+    }
+}
+
+impl Drop for TimerPwm {
+    fn drop(&mut self) {
+        // Once the channel is removed, the thread won't change the output state anymore
+        self.timer_loop.shared.remove(self.id);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TimerLoop {
+    shared: Arc<Shared>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TimerLoop {
+    // Returns the running timer loop, or starts a new one if none exists
+    fn get(gpio_state: &GpioState) -> Result<Arc<TimerLoop>> {
+        let mut weak_loop = gpio_state.timer_pwm.lock().unwrap();
+        if let Some(timer_loop) = weak_loop.upgrade() {
+            return Ok(timer_loop);
+        }
+
+        let shared = Arc::new(Shared::new()?);
+        let thread_shared = shared.clone();
+        let thread = thread::spawn(move || {
+            set_realtime_priority();
+            thread_shared.run();
+        });
+
+        let timer_loop = Arc::new(TimerLoop {
+            shared,
+            thread: Some(thread),
+        });
+
+        *weak_loop = Arc::downgrade(&timer_loop);
+
+        Ok(timer_loop)
+    }
+}
+
+impl Drop for TimerLoop {
+    fn drop(&mut self) {
+        let _ = self.shared.tx.notify();
+
+        if let Some(thread) = self.thread.take() {
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    poll: Epoll,
+    tx: EventFd,
+    channels: Mutex<HashMap<u64, Channel>>,
+    // Id 0 is reserved for tx
+    next_id: AtomicU64,
+}
+
+impl Shared {
+    fn new() -> Result<Shared> {
+        let poll = Epoll::new()?;
+        let tx = EventFd::new()?;
+
+        poll.add(tx.fd(), 0, EPOLLERR | EPOLLET | EPOLLIN)?;
+
+        Ok(Shared {
+            poll,
+            tx,
+            channels: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    fn lock_channels(&self) -> MutexGuard<'_, HashMap<u64, Channel>> {
+        self.channels
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn add(
+        &self,
+        pin: u8,
+        gpio_state: Arc<GpioState>,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> Result<u64> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut channel = Channel {
+            pin,
+            gpio_state,
+            timer: TimerFd::new()?,
+            period_ns: 0,
+            pulse_width_ns: 0,
+            active: false,
+            idle: false,
+            cycle_start_ns: 0,
+        };
+
+        channel.configure(period, pulse_width);
+        self.poll.add(channel.timer.fd(), id, EPOLLIN)?;
+
+        let mut channels = self.lock_channels();
+        channel.start_cycle(get_time_ns())?;
+        channels.insert(id, channel);
+
+        Ok(id)
+    }
+
+    fn remove(&self, id: u64) {
+        let mut channels = self.lock_channels();
+        if let Some(channel) = channels.remove(&id) {
+            let _ = self.poll.delete(channel.timer.fd());
+        }
+    }
+
+    fn run(&self) {
+        let mut events = [epoll_event { events: 0, u64: 0 }; 16];
+        loop {
+            let num_events = match self.poll.wait(&mut events, None) {
+                Ok(num_events) => num_events,
+                Err(_) => return,
+            };
+
+            for event in &events[0..num_events] {
+                let id = event.u64;
+                if id == 0 {
+                    return; // The last channel was dropped
+                }
+
+                let mut channels = self.lock_channels();
+                if let Some(channel) = channels.get_mut(&id) {
+                    // Ignore errors, since there's no way to report them here
+                    let _ = channel.expire();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Channel {
+    pin: u8,
+    gpio_state: Arc<GpioState>,
+    timer: TimerFd,
+    period_ns: i64,
+    pulse_width_ns: i64,
+    active: bool,
+    // Set when the output is permanently inactive, and the timer is disarmed
+    idle: bool,
+    cycle_start_ns: i64,
+}
+
+impl Channel {
+    fn configure(&mut self, period: Duration, pulse_width: Duration) {
+        self.period_ns = period.as_nanos() as i64;
+        self.pulse_width_ns = (pulse_width.as_nanos() as i64).min(self.period_ns);
+    }
+
+    // Starts a new cycle at start_ns, and arms the timer for the next transition
+    fn start_cycle(&mut self, start_ns: i64) -> Result<()> {
+        self.cycle_start_ns = start_ns;
+        self.idle = false;
+
+        if self.pulse_width_ns == 0 || self.period_ns == 0 {
+            // Fully inactive, so there's nothing to schedule until reconfigured
+            self.active = false;
+            self.idle = true;
+            self.gpio_state.gpio_mem.set_low(self.pin);
+            self.timer.set_deadline(0)?;
+        } else if self.pulse_width_ns >= self.period_ns {
+            // Fully active. The timer is still armed for the end of the period, so a new
+            // configuration is picked up.
+            self.active = false;
+            self.gpio_state.gpio_mem.set_high(self.pin);
+            self.timer.set_deadline(start_ns + self.period_ns)?;
+        } else {
+            self.active = true;
+            self.gpio_state.gpio_mem.set_high(self.pin);
+            self.timer.set_deadline(start_ns + self.pulse_width_ns)?;
+        }
+
+        Ok(())
+    }
+
+    fn expire(&mut self) -> Result<()> {
+        if self.timer.expirations()? == 0 {
+            return Ok(());
+        }
+
+        if self.active {
+            // End of the pulse
+            self.active = false;
+            self.gpio_state.gpio_mem.set_low(self.pin);
+            self.timer
+                .set_deadline(self.cycle_start_ns + self.period_ns)?;
+
+            return Ok(());
+        }
+
+        // Deadlines are calculated from the start of the previous cycle, which prevents
+        // timing errors from accumulating. If we fell behind by more than a full period,
+        // start over from the current time instead of trying to catch up.
+        let now_ns = get_time_ns();
+        let mut start_ns = self.cycle_start_ns + self.period_ns;
+        if self.period_ns == 0 || now_ns - start_ns > self.period_ns {
+            start_ns = now_ns;
+        }
+
+        self.start_cycle(start_ns)
+    }
+}
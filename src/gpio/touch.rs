@@ -0,0 +1,82 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::gpio::{Bias, InputPin};
+
+// Time spent charging the line through the pull-up resistor
+const CHARGE_TIME: Duration = Duration::from_micros(100);
+// Upper limit for the discharge measurement, so a disconnected or shorted line
+// can't block indefinitely
+const MAX_TICKS: u32 = 100_000;
+
+/// Capacitive touch sensor on a single input pin.
+///
+/// `TouchSensor` charges the line through the built-in pull-up resistor, switches to the
+/// pull-down resistor, and counts how many times the pin can be read before the level
+/// drops to [`Low`]. Touching a wire or conductive pad connected to the pin increases its
+/// capacitance, and with it the time the line needs to discharge.
+///
+/// The tick count depends on the Raspberry Pi model, the CPU frequency, the length of the
+/// wiring and the size of the pad, so the threshold that separates touched from
+/// untouched readings needs to be calibrated for each setup. Readings are affected by
+/// preemption, so averaging multiple readings improves reliability. Any external resistor
+/// between the pin and ground is connected in parallel with the pull-down resistor, which
+/// speeds up the discharge and reduces the difference between readings, so the pad should
+/// be connected to the pin directly. A larger pad increases the difference between
+/// touched and untouched readings.
+///
+/// The pin's built-in resistors are restored to their previous configuration after each
+/// reading.
+///
+/// [`Low`]: enum.Level.html#variant.Low
+#[derive(Debug)]
+pub struct TouchSensor {
+    pin: InputPin,
+}
+
+impl TouchSensor {
+    /// Constructs a new `TouchSensor` on the specified pin.
+    pub fn new(pin: InputPin) -> TouchSensor {
+        TouchSensor { pin }
+    }
+
+    /// Measures the time the line needs to discharge, and returns the number of reads
+    /// it took for the pin to read [`Low`].
+    ///
+    /// Higher values indicate a higher capacitance. The count is limited to 100,000 ticks.
+    ///
+    /// [`Low`]: enum.Level.html#variant.Low
+    pub fn read_raw(&mut self) -> u32 {
+        let prev_bias = self.pin.bias();
+
+        self.pin.set_bias(Bias::PullUp);
+        thread::sleep(CHARGE_TIME);
+
+        self.pin.set_bias(Bias::PullDown);
+
+        let mut ticks = 0;
+        while ticks < MAX_TICKS && self.pin.is_high() {
+            ticks += 1;
+        }
+
+        self.pin.set_bias(prev_bias);
+
+        ticks
+    }
+
+    /// Returns `true` if a reading exceeds `threshold`.
+    ///
+    /// See [`read_raw`] for details on the raw tick count.
+    ///
+    /// [`read_raw`]: #method.read_raw
+    pub fn is_touched(&mut self, threshold: u32) -> bool {
+        self.read_raw() > threshold
+    }
+
+    /// Consumes the `TouchSensor`, and returns the underlying [`InputPin`].
+    ///
+    /// [`InputPin`]: struct.InputPin.html
+    pub fn into_inner(self) -> InputPin {
+        self.pin
+    }
+}
@@ -133,8 +133,10 @@ use crate::system::{DeviceInfo, Model};
 ))]
 mod hal;
 mod ioctl;
+mod mcp23017;
 
 pub use self::ioctl::Capabilities;
+pub use self::mcp23017::Mcp23017;
 
 /// Errors that can occur when accessing the I2C peripheral.
 #[derive(Debug)]
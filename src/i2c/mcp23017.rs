@@ -0,0 +1,242 @@
+use std::io;
+use std::time::Duration;
+
+use crate::gpio::{self, Bias, InputPin, Level, Trigger};
+use crate::i2c::{Error, I2c, Result};
+
+// Register addresses with IOCON.BANK = 0, where each port A register is directly
+// followed by its port B counterpart. Reading or writing a word accesses both ports.
+const REG_IODIR: u8 = 0x00;
+const REG_GPINTEN: u8 = 0x04;
+const REG_IOCON: u8 = 0x0a;
+const REG_GPPU: u8 = 0x0c;
+const REG_INTF: u8 = 0x0e;
+const REG_INTCAP: u8 = 0x10;
+const REG_GPIO: u8 = 0x12;
+const REG_OLAT: u8 = 0x14;
+
+// Mirror the INTA and INTB outputs, and configure them as open-drain
+const IOCON_MIRROR: u8 = 1 << 6;
+const IOCON_ODR: u8 = 1 << 2;
+
+const PINS: u8 = 16;
+
+/// Microchip MCP23017 16-bit I/O expander.
+///
+/// `Mcp23017` communicates with the expander through [`I2c`], and presents its pins
+/// through an interface similar to [`InputPin`] and [`OutputPin`]. Pins are numbered
+/// 0-15, where 0-7 refer to GPA0-GPA7, and 8-15 to GPB0-GPB7. All pins are configured as
+/// inputs after a power-on reset.
+///
+/// The expander's INTA and INTB outputs are mirrored and configured as open-drain, so
+/// either of them can be connected to a native GPIO pin, and passed to
+/// [`set_interrupt_pin`] to wait for pin changes with [`poll_interrupt`].
+///
+/// [`I2c`]: struct.I2c.html
+/// [`InputPin`]: ../gpio/struct.InputPin.html
+/// [`OutputPin`]: ../gpio/struct.OutputPin.html
+/// [`set_interrupt_pin`]: #method.set_interrupt_pin
+/// [`poll_interrupt`]: #method.poll_interrupt
+#[derive(Debug)]
+pub struct Mcp23017 {
+    i2c: I2c,
+    interrupt_pin: Option<InputPin>,
+}
+
+impl Mcp23017 {
+    /// Constructs a new `Mcp23017` for the expander at the specified 7-bit slave address.
+    ///
+    /// Depending on the A0-A2 address pins, the address is between `0x20` and `0x27`.
+    pub fn new(mut i2c: I2c, address: u16) -> Result<Mcp23017> {
+        i2c.set_slave_address(address)?;
+        i2c.smbus_write_byte(REG_IOCON, IOCON_MIRROR | IOCON_ODR)?;
+
+        Ok(Mcp23017 {
+            i2c,
+            interrupt_pin: None,
+        })
+    }
+
+    /// Configures the pin as an input.
+    pub fn set_input(&mut self, pin: u8) -> Result<()> {
+        self.update(REG_IODIR, pin, true)
+    }
+
+    /// Configures the pin as an output.
+    pub fn set_output(&mut self, pin: u8) -> Result<()> {
+        self.update(REG_IODIR, pin, false)
+    }
+
+    /// Enables or disables the pin's built-in 100 kΩ pull-up resistor.
+    ///
+    /// The pull-up resistor only has an effect when the pin is configured as an input.
+    pub fn set_pullup(&mut self, pin: u8, enabled: bool) -> Result<()> {
+        self.update(REG_GPPU, pin, enabled)
+    }
+
+    /// Reads the pin's logic level.
+    pub fn read(&self, pin: u8) -> Result<Level> {
+        let mask = pin_mask(pin)?;
+
+        Ok(if self.read_all()? & mask > 0 {
+            Level::High
+        } else {
+            Level::Low
+        })
+    }
+
+    /// Reads the logic levels of all pins, where bit 0 matches GPA0, and bit 15 matches GPB7.
+    pub fn read_all(&self) -> Result<u16> {
+        self.i2c.smbus_read_word(REG_GPIO)
+    }
+
+    /// Sets the pin's output state to [`High`].
+    ///
+    /// [`High`]: ../gpio/enum.Level.html#variant.High
+    pub fn set_high(&mut self, pin: u8) -> Result<()> {
+        self.update(REG_OLAT, pin, true)
+    }
+
+    /// Sets the pin's output state to [`Low`].
+    ///
+    /// [`Low`]: ../gpio/enum.Level.html#variant.Low
+    pub fn set_low(&mut self, pin: u8) -> Result<()> {
+        self.update(REG_OLAT, pin, false)
+    }
+
+    /// Sets the pin's output state.
+    pub fn write(&mut self, pin: u8, level: Level) -> Result<()> {
+        self.update(REG_OLAT, pin, level == Level::High)
+    }
+
+    /// Sets the output states of all pins, where bit 0 matches GPA0, and bit 15 matches GPB7.
+    pub fn write_all(&mut self, levels: u16) -> Result<()> {
+        self.i2c.smbus_write_word(REG_OLAT, levels)
+    }
+
+    /// Enables or disables the interrupt-on-change for the pin.
+    ///
+    /// When enabled, the expander pulls its INT outputs low whenever the pin's level
+    /// changes, until the interrupt is cleared by [`poll_interrupt`] or
+    /// [`interrupt_capture`].
+    ///
+    /// [`poll_interrupt`]: #method.poll_interrupt
+    /// [`interrupt_capture`]: #method.interrupt_capture
+    pub fn set_interrupt(&mut self, pin: u8, enabled: bool) -> Result<()> {
+        self.update(REG_GPINTEN, pin, enabled)
+    }
+
+    /// Returns a bit mask of the pins that caused the pending interrupt.
+    pub fn interrupt_flags(&self) -> Result<u16> {
+        self.i2c.smbus_read_word(REG_INTF)
+    }
+
+    /// Returns the logic levels of all pins at the time the pending interrupt occurred,
+    /// and clears the interrupt.
+    pub fn interrupt_capture(&self) -> Result<u16> {
+        self.i2c.smbus_read_word(REG_INTCAP)
+    }
+
+    /// Uses a native GPIO pin connected to the expander's INTA or INTB output to wait for
+    /// interrupts.
+    ///
+    /// The pin's built-in pull-up resistor is enabled, and a synchronous interrupt is
+    /// configured for the falling edge. Any pending expander interrupt is cleared. The
+    /// pin is returned by [`take_interrupt_pin`], or dropped together with `Mcp23017`.
+    ///
+    /// [`take_interrupt_pin`]: #method.take_interrupt_pin
+    pub fn set_interrupt_pin(&mut self, mut pin: InputPin) -> Result<()> {
+        pin.set_bias(Bias::PullUp);
+        pin.set_interrupt(Trigger::FallingEdge, None)
+            .map_err(gpio_error)?;
+
+        self.interrupt_capture()?;
+        self.interrupt_pin = Some(pin);
+
+        Ok(())
+    }
+
+    /// Removes and returns the native GPIO pin configured by [`set_interrupt_pin`].
+    ///
+    /// [`set_interrupt_pin`]: #method.set_interrupt_pin
+    pub fn take_interrupt_pin(&mut self) -> Option<InputPin> {
+        self.interrupt_pin.take()
+    }
+
+    /// Blocks until an interrupt is triggered on any of the expander pins configured
+    /// through [`set_interrupt`], or the timeout period elapses.
+    ///
+    /// Setting `timeout` to `None` disables the timeout period.
+    ///
+    /// Returns `Ok(Some((flags, levels)))` when an interrupt is triggered, where `flags`
+    /// contains the pins that caused the interrupt, and `levels` contains the logic
+    /// levels of all pins at the time the interrupt occurred. The interrupt is cleared
+    /// before `poll_interrupt` returns. Returns `Ok(None)` when the timeout period
+    /// elapses.
+    ///
+    /// Returns an `io::ErrorKind::NotConnected` error if no native GPIO pin was
+    /// configured through [`set_interrupt_pin`].
+    ///
+    /// [`set_interrupt`]: #method.set_interrupt
+    /// [`set_interrupt_pin`]: #method.set_interrupt_pin
+    pub fn poll_interrupt(&mut self, timeout: Option<Duration>) -> Result<Option<(u16, u16)>> {
+        let pin = self.interrupt_pin.as_mut().ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "No interrupt pin configured",
+            ))
+        })?;
+
+        // The INT output stays low until the interrupt is cleared, so a change that
+        // occurred before polling started is still pending. Stale events are discarded
+        // before checking the level, so a falling edge that occurs in between isn't lost.
+        pin.clear_pending_interrupt().map_err(gpio_error)?;
+        if pin.is_high()
+            && pin
+                .poll_interrupt(false, timeout)
+                .map_err(gpio_error)?
+                .is_none()
+        {
+            return Ok(None);
+        }
+
+        let flags = self.interrupt_flags()?;
+        let levels = self.interrupt_capture()?;
+
+        Ok(Some((flags, levels)))
+    }
+
+    fn update(&mut self, register: u8, pin: u8, value: bool) -> Result<()> {
+        let mask = pin_mask(pin)?;
+        let current = self.i2c.smbus_read_word(register)?;
+        let new = if value {
+            current | mask
+        } else {
+            current & !mask
+        };
+
+        if new != current {
+            self.i2c.smbus_write_word(register, new)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn pin_mask(pin: u8) -> Result<u16> {
+    if pin >= PINS {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "MCP23017 pin needs to be between 0 and 15",
+        )));
+    }
+
+    Ok(1 << pin)
+}
+
+fn gpio_error(err: gpio::Error) -> Error {
+    match err {
+        gpio::Error::Io(err) => Error::Io(err),
+        err => Error::Io(io::Error::new(io::ErrorKind::Other, err.to_string())),
+    }
+}
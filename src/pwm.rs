@@ -16,6 +16,16 @@
 //! The Raspberry Pi's analog audio output uses both PWM channels. Playing audio and
 //! simultaneously accessing a PWM channel may cause issues.
 //!
+//! The PWM peripheral and the analog audio output also share the same clock. The clock's
+//! source (usually PLLD or the oscillator) and frequency are managed by the kernel's clock
+//! driver, and can't be changed through the `pwm` sysfs interface. Changing the clock
+//! directly through the clock manager registers would invalidate the period and duty
+//! cycle calculations performed by the kernel's PWM driver, so RPPAL doesn't offer an
+//! option to select a different clock source. If you need PWM and audio at the same time,
+//! use HDMI or USB audio, and disable the analog audio output by adding `dtparam=audio=off`
+//! to `/boot/firmware/config.txt`. For a fixed 50% duty cycle square wave, you can use
+//! [`gpio::OutputPin::set_clock`] instead, which uses one of the general-purpose clocks.
+//!
 //! Some of the GPIO pins capable of supporting hardware PWM can also be configured for
 //! use with other peripherals. Be careful not to enable two peripherals on the same pin
 //! at the same time.
@@ -58,6 +68,7 @@
 //! to enable either of the two PWM channels are listed above.
 //!
 //! [patch]: https://github.com/raspberrypi/linux/issues/1983
+//! [`gpio::OutputPin::set_clock`]: ../gpio/struct.OutputPin.html#method.set_clock
 //! [`new`]: struct.Pwm.html#method.new
 
 use std::error;
@@ -66,6 +77,7 @@ use std::io;
 use std::result;
 use std::time::Duration;
 
+mod fan;
 #[cfg(any(
     feature = "embedded-hal-0",
     feature = "embedded-hal",
@@ -78,6 +90,8 @@ mod sysfs;
 
 use crate::system::DeviceInfo;
 
+pub use self::fan::FanController;
+
 const NANOS_PER_SEC: f64 = 1_000_000_000.0;
 
 /// Errors that can occur when accessing the PWM peripheral.
@@ -290,6 +304,11 @@ impl Pwm {
     }
 
     /// Returns the period.
+    ///
+    /// The value is read back from the `pwm` sysfs interface on every call, so it reflects
+    /// changes made by other processes. The kernel reports the value most recently applied
+    /// to the PWM channel, which doesn't include any rounding performed by the PWM driver
+    /// when it converts the value to PWM clock cycles.
     pub fn period(&self) -> Result<Duration> {
         Ok(Duration::from_nanos(sysfs::period(
             self.chip,
@@ -314,6 +333,11 @@ impl Pwm {
     }
 
     /// Returns the pulse width.
+    ///
+    /// Like [`period`], the value is read back from the `pwm` sysfs interface, and doesn't
+    /// include any rounding performed by the PWM driver.
+    ///
+    /// [`period`]: #method.period
     pub fn pulse_width(&self) -> Result<Duration> {
         Ok(Duration::from_nanos(sysfs::pulse_width(
             self.chip,
@@ -338,6 +362,50 @@ impl Pwm {
         Ok(())
     }
 
+    /// Sets the period in microseconds (µs).
+    ///
+    /// `set_period_us` is a convenience method for servos and ESCs, which are usually
+    /// specified in microseconds. A typical servo expects a period of 20000 µs (50 Hz).
+    ///
+    /// Returns an `io::ErrorKind::InvalidInput` error if `period_us` is shorter than the
+    /// current pulse width.
+    pub fn set_period_us(&self, period_us: u32) -> Result<()> {
+        let period = u64::from(period_us) * 1000;
+        if period < sysfs::pulse_width(self.chip, self.channel)? {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Period is shorter than the current pulse width",
+            )));
+        }
+
+        sysfs::set_period(self.chip, self.channel, period)?;
+
+        Ok(())
+    }
+
+    /// Sets the pulse width in microseconds (µs).
+    ///
+    /// `set_pulse_width_us` is a convenience method for servos and ESCs, which are
+    /// specified in microseconds of pulse rather than a duty cycle. The duty cycle
+    /// follows from the pulse width and the configured period. Most servos expect a pulse
+    /// width between 1000 µs and 2000 µs.
+    ///
+    /// Returns an `io::ErrorKind::InvalidInput` error if `pulse_width_us` doesn't fit within
+    /// the configured period.
+    pub fn set_pulse_width_us(&self, pulse_width_us: u32) -> Result<()> {
+        let pulse_width = u64::from(pulse_width_us) * 1000;
+        if pulse_width > sysfs::period(self.chip, self.channel)? {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Pulse width doesn't fit within the current period",
+            )));
+        }
+
+        sysfs::set_pulse_width(self.chip, self.channel, pulse_width)?;
+
+        Ok(())
+    }
+
     /// Returns the frequency.
     ///
     /// `frequency` is a convenience method that calculates the frequency in hertz (Hz)
@@ -382,7 +450,10 @@ impl Pwm {
     ///
     /// `duty_cycle` is a convenience method that calculates the duty cycle as a
     /// floating point value between `0.0` (0%) and `1.0` (100%) based on the configured
-    /// period and pulse width.
+    /// period and pulse width, as read back by [`period`] and [`pulse_width`].
+    ///
+    /// [`period`]: #method.period
+    /// [`pulse_width`]: #method.pulse_width
     pub fn duty_cycle(&self) -> Result<f64> {
         let period = sysfs::period(self.chip, self.channel)? as f64;
         let pulse_width = sysfs::pulse_width(self.chip, self.channel)? as f64;
@@ -0,0 +1,153 @@
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{Error, Pwm, Result};
+use crate::system;
+
+// Default temperature drop in degrees Celsius (°C) before the fan speed is lowered
+const DEFAULT_HYSTERESIS: f32 = 2.0;
+// Default interval between temperature readings
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Controls the speed of a PWM fan based on the SoC's temperature.
+///
+/// `FanController` reads the temperature returned by [`system::cpu_temperature`] on a
+/// separate thread, and updates the duty cycle of the [`Pwm`] channel based on a
+/// temperature-to-speed curve.
+///
+/// The curve consists of `(temperature, duty_cycle)` points, where `temperature` is
+/// specified in degrees Celsius (°C), and `duty_cycle` as a floating point value between
+/// `0.0` (0%) and `1.0` (100%). The duty cycle is set to the value of the point with the
+/// highest temperature that doesn't exceed the current temperature. Below the lowest
+/// point, the duty cycle is set to `0.0`.
+///
+/// To prevent the fan from constantly switching between speeds, a lower speed is only
+/// selected after the temperature drops below the current point's temperature by more
+/// than the configured hysteresis.
+///
+/// If the temperature can't be read, the duty cycle is set to the curve's highest value.
+///
+/// The background thread is stopped when `FanController` goes out of scope, after which
+/// the [`Pwm`] instance is dropped.
+///
+/// [`system::cpu_temperature`]: ../system/fn.cpu_temperature.html
+/// [`Pwm`]: struct.Pwm.html
+#[derive(Debug)]
+pub struct FanController {
+    thread: Option<JoinHandle<Pwm>>,
+    sender: Sender<()>,
+}
+
+impl FanController {
+    /// Starts a new `FanController` with a hysteresis of 2 °C, and reads the temperature
+    /// every second.
+    ///
+    /// `pwm` should already be configured with a period that's suitable for the fan.
+    /// `start` enables the PWM channel.
+    ///
+    /// Returns an `io::ErrorKind::InvalidInput` error if `curve` is empty.
+    pub fn start(pwm: Pwm, curve: &[(f32, f64)]) -> Result<FanController> {
+        FanController::with_hysteresis(pwm, curve, DEFAULT_HYSTERESIS, DEFAULT_INTERVAL)
+    }
+
+    /// Starts a new `FanController` with a custom hysteresis and interval.
+    ///
+    /// `hysteresis` is specified in degrees Celsius (°C). `interval` indicates how often
+    /// the temperature is read.
+    ///
+    /// `pwm` should already be configured with a period that's suitable for the fan.
+    /// `with_hysteresis` enables the PWM channel.
+    ///
+    /// Returns an `io::ErrorKind::InvalidInput` error if `curve` is empty.
+    pub fn with_hysteresis(
+        pwm: Pwm,
+        curve: &[(f32, f64)],
+        hysteresis: f32,
+        interval: Duration,
+    ) -> Result<FanController> {
+        if curve.is_empty() {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Fan curve is empty",
+            )));
+        }
+
+        let mut curve = curve.to_vec();
+        curve.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        let max_duty_cycle = curve.iter().fold(0.0f64, |max, point| max.max(point.1));
+
+        pwm.enable()?;
+
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            // Index of the currently selected point, or None when below the curve
+            let mut current: Option<usize> = None;
+
+            loop {
+                let duty_cycle = match system::cpu_temperature() {
+                    Ok(temperature) => {
+                        current = select_point(&curve, current, temperature, hysteresis);
+                        current.map_or(0.0, |index| curve[index].1)
+                    }
+                    Err(_) => max_duty_cycle,
+                };
+
+                let _ = pwm.set_duty_cycle(duty_cycle);
+
+                match receiver.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => (),
+                    _ => return pwm,
+                }
+            }
+        });
+
+        Ok(FanController {
+            thread: Some(thread),
+            sender,
+        })
+    }
+
+    /// Stops the background thread, and returns the [`Pwm`] instance.
+    ///
+    /// The duty cycle is left at its last value. Returns `None` if the background thread
+    /// panicked.
+    ///
+    /// [`Pwm`]: struct.Pwm.html
+    pub fn stop(mut self) -> Option<Pwm> {
+        self.stop_thread()
+    }
+
+    fn stop_thread(&mut self) -> Option<Pwm> {
+        let _ = self.sender.send(());
+
+        self.thread.take().and_then(|thread| thread.join().ok())
+    }
+}
+
+impl Drop for FanController {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+// Returns the index of the point that matches the current temperature
+fn select_point(
+    curve: &[(f32, f64)],
+    current: Option<usize>,
+    temperature: f32,
+    hysteresis: f32,
+) -> Option<usize> {
+    let target = curve.iter().rposition(|point| point.0 <= temperature);
+
+    match current {
+        // Stay on a higher point until the temperature drops below its threshold by more
+        // than the hysteresis
+        Some(current) if target.map_or(true, |target| target < current) => curve[..=current]
+            .iter()
+            .rposition(|point| temperature >= point.0 - hysteresis),
+        _ => target,
+    }
+}
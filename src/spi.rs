@@ -107,6 +107,21 @@
 //! Remember to reboot the Raspberry Pi afterwards. The current value of bufsiz
 //! can be checked with `cat /sys/module/spidev/parameters/bufsiz`.
 //!
+//! [`Spi::read`], [`Spi::write`] and [`Spi::transfer`] automatically split larger
+//! buffers into multiple transfers, while keeping Slave Select active in between.
+//! [`Spi::write_stream`] sends data from any [`Read`] source without loading it into
+//! memory first. [`Spi::transfer_segments`] isn't split up, so the total length of
+//! all segments needs to stay within the limit, which is returned by
+//! [`Spi::max_transfer_size`].
+//!
+//! [`Spi::read`]: struct.Spi.html#method.read
+//! [`Spi::write`]: struct.Spi.html#method.write
+//! [`Spi::transfer`]: struct.Spi.html#method.transfer
+//! [`Spi::write_stream`]: struct.Spi.html#method.write_stream
+//! [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
+//! [`Spi::max_transfer_size`]: struct.Spi.html#method.max_transfer_size
+//! [`Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
+//!
 //! ## Not supported
 //!
 //! Some features exposed by the generic `spidev` interface aren't fully
@@ -124,7 +139,8 @@
 //!
 //! `SPI_NO_CS` can be implemented by connecting the Slave Select pin on your
 //! slave device to any other available GPIO pin on the Pi, and manually
-//! changing it to high and low as needed.
+//! changing it to high and low as needed, or by passing the pin to
+//! [`Spi::with_software_cs`].
 //!
 //! [`Ss0`]: enum.SlaveSelect.html
 //! [`Ss1`]: enum.SlaveSelect.html
@@ -132,16 +148,20 @@
 //! [`Mode1`]: enum.Mode.html
 //! [`Mode3`]: enum.Mode.html
 //! [`reverse_bits`]: fn.reverse_bits.html
+//! [`Spi::with_software_cs`]: struct.Spi.html#method.with_software_cs
 
 use std::error;
 use std::fmt;
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::io;
 use std::io::{Read, Write};
 use std::marker::PhantomData;
+use std::mem;
 use std::os::unix::io::AsRawFd;
 use std::result;
 
+use crate::gpio::{Level, OutputHandle, OutputPin};
+
 #[cfg(any(
     feature = "embedded-hal-0",
     feature = "embedded-hal",
@@ -149,8 +169,12 @@ use std::result;
 ))]
 mod hal;
 mod ioctl;
+mod mcp3008;
+mod neopixel;
 mod segment;
 
+pub use self::mcp3008::Mcp3008;
+pub use self::neopixel::NeoPixelStrip;
 pub use self::segment::Segment;
 #[cfg(any(
     feature = "embedded-hal-0",
@@ -159,6 +183,10 @@ pub use self::segment::Segment;
 ))]
 pub use hal::SimpleHalSpiDevice;
 
+const PATH_BUFSIZ: &str = "/sys/module/spidev/parameters/bufsiz";
+// spidev's default buffer size limit
+const DEFAULT_BUFSIZ: usize = 4096;
+
 /// Errors that can occur when accessing the SPI peripheral.
 #[derive(Debug)]
 pub enum Error {
@@ -187,6 +215,15 @@ pub enum Error {
     ModeNotSupported(Mode),
     /// The specified Slave Select polarity is not supported.
     PolarityNotSupported(Polarity),
+    /// The total length of a transfer exceeds the maximum number of bytes `spidev`
+    /// accepts in a single transfer.
+    ///
+    /// Contains the total length of the transfer. The limit is returned by
+    /// [`Spi::max_transfer_size`]. More information can be found [here].
+    ///
+    /// [`Spi::max_transfer_size`]: struct.Spi.html#method.max_transfer_size
+    /// [here]: index.html#buffer-size-limits
+    TransferTooLarge(usize),
 }
 
 impl fmt::Display for Error {
@@ -206,6 +243,9 @@ impl fmt::Display for Error {
             Error::PolarityNotSupported(polarity) => {
                 write!(f, "Polarity value not supported: {:?}", polarity)
             }
+            Error::TransferTooLarge(len) => {
+                write!(f, "Transfer exceeds spidev's buffer size: {} bytes", len)
+            }
         }
     }
 }
@@ -416,6 +456,9 @@ impl fmt::Display for BitOrder {
 /// [here]: index.html
 pub struct Spi {
     spidev: File,
+    // Maximum number of bytes spidev accepts in a single transfer
+    bufsiz: usize,
+    software_cs: Option<SoftwareCs>,
     // Stores the last read value. Used for embedded_hal::spi::FullDuplex.
     #[cfg(any(feature = "embedded-hal-0", feature = "embedded-hal-nb"))]
     last_read: Option<u8>,
@@ -462,6 +505,8 @@ impl Spi {
 
         let spi = Spi {
             spidev,
+            bufsiz: read_bufsiz(),
+            software_cs: None,
             #[cfg(any(feature = "embedded-hal-0", feature = "embedded-hal-nb"))]
             last_read: None,
             not_sync: PhantomData,
@@ -474,6 +519,44 @@ impl Spi {
         Ok(spi)
     }
 
+    /// Uses a GPIO pin as a software-controlled Slave Select line.
+    ///
+    /// The hardware Slave Select lines only support a limited number of slave devices
+    /// per bus. With `with_software_cs`, any available GPIO pin can be used to select a
+    /// slave device instead. `cs_pin` is set to `active_level` at the start of every
+    /// call to [`read`], [`write`], [`write_stream`], [`transfer`] and
+    /// [`transfer_segments`], and set to the opposite level when the call returns,
+    /// even if the transfer fails. `cs_pin` is set to its inactive level immediately.
+    ///
+    /// The hardware Slave Select line that was selected when constructing `Spi` is still
+    /// toggled by the SPI driver, so it shouldn't be connected to any of the slave
+    /// devices addressed through software Slave Select lines. Multiple `Spi` instances,
+    /// each with their own `cs_pin`, can share the same bus and hardware Slave Select
+    /// line.
+    ///
+    /// Software Slave Select lines are slower than the hardware Slave Select lines, since
+    /// the GPIO pin is changed before and after each call, rather than by the SPI driver.
+    /// [`Segment::set_ss_change`] has no effect on `cs_pin`.
+    ///
+    /// [`read`]: #method.read
+    /// [`write`]: #method.write
+    /// [`write_stream`]: #method.write_stream
+    /// [`transfer`]: #method.transfer
+    /// [`transfer_segments`]: #method.transfer_segments
+    /// [`Segment::set_ss_change`]: struct.Segment.html#method.set_ss_change
+    pub fn with_software_cs(mut self, cs_pin: OutputPin, active_level: Level) -> Spi {
+        let handle = cs_pin.sharable_handle();
+        handle.write(!active_level);
+
+        self.software_cs = Some(SoftwareCs {
+            _pin: cs_pin,
+            handle,
+            active_level,
+        });
+
+        self
+    }
+
     /// Gets the bit order.
     pub fn bit_order(&self) -> Result<BitOrder> {
         let mut bit_order: u8 = 0;
@@ -619,6 +702,24 @@ impl Spi {
         }
     }
 
+    /// Returns the maximum number of bytes `spidev` accepts in a single transfer.
+    ///
+    /// The limit is read from `/sys/module/spidev/parameters/bufsiz` when `Spi` is
+    /// constructed, and defaults to 4096 bytes if the file isn't available.
+    /// [`read`], [`write`] and [`transfer`] automatically split larger buffers into
+    /// multiple transfers. [`transfer_segments`] returns
+    /// `Err(`[`Error::TransferTooLarge`]`)` if the combined length of its segments
+    /// exceeds the limit.
+    ///
+    /// [`read`]: #method.read
+    /// [`write`]: #method.write
+    /// [`transfer`]: #method.transfer
+    /// [`transfer_segments`]: #method.transfer_segments
+    /// [`Error::TransferTooLarge`]: enum.Error.html#variant.TransferTooLarge
+    pub fn max_transfer_size(&self) -> usize {
+        self.bufsiz
+    }
+
     /// Receives incoming data from the slave device and writes it to `buffer`.
     ///
     /// The SPI protocol doesn't indicate how much incoming data is waiting,
@@ -631,9 +732,28 @@ impl Spi {
     /// Slave Select is set to active at the start of the read, and inactive
     /// when the read completes.
     ///
+    /// If `buffer` exceeds `spidev`'s buffer size limit, the read is split into
+    /// multiple transfers, while keeping Slave Select active.
+    ///
     /// Returns how many bytes were read.
     pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
-        Ok(self.spidev.read(buffer)?)
+        let _cs = self.software_cs.as_ref().map(SoftwareCs::select);
+
+        if buffer.len() <= self.bufsiz {
+            return Ok(self.spidev.read(buffer)?);
+        }
+
+        let len = buffer.len();
+        let bufsiz = self.bufsiz;
+        for (index, chunk) in buffer.chunks_mut(bufsiz).enumerate() {
+            let mut segment = Segment::with_read(chunk);
+            // Keep Slave Select active after every transfer except the last one
+            segment.set_ss_change((index + 1) * bufsiz < len);
+
+            ioctl::transfer(self.spidev.as_raw_fd(), &[segment])?;
+        }
+
+        Ok(len)
     }
 
     /// Sends the outgoing data contained in `buffer` to the slave device.
@@ -643,9 +763,64 @@ impl Spi {
     /// Slave Select is set to active at the start of the write, and inactive
     /// when the write completes.
     ///
+    /// If `buffer` exceeds `spidev`'s buffer size limit, the write is split into
+    /// multiple transfers, while keeping Slave Select active.
+    ///
     /// Returns how many bytes were written.
     pub fn write(&mut self, buffer: &[u8]) -> Result<usize> {
-        Ok(self.spidev.write(buffer)?)
+        let _cs = self.software_cs.as_ref().map(SoftwareCs::select);
+
+        if buffer.len() <= self.bufsiz {
+            return Ok(self.spidev.write(buffer)?);
+        }
+
+        for (index, chunk) in buffer.chunks(self.bufsiz).enumerate() {
+            let mut segment = Segment::with_write(chunk);
+            // Keep Slave Select active after every transfer except the last one
+            segment.set_ss_change((index + 1) * self.bufsiz < buffer.len());
+
+            ioctl::transfer(self.spidev.as_raw_fd(), &[segment])?;
+        }
+
+        Ok(buffer.len())
+    }
+
+    /// Sends all outgoing data read from `reader` to the slave device.
+    ///
+    /// `write_stream` reads up to `spidev`'s buffer size limit from `reader` at a time,
+    /// which makes it possible to send large amounts of data, such as framebuffer contents
+    /// for a display, without loading everything into memory first. Any data received on
+    /// the MISO line from the slave is ignored.
+    ///
+    /// Slave Select is set to active at the start of the write, and stays active
+    /// until `reader` reaches the end of its data.
+    ///
+    /// If an error occurs while reading from `reader`, the error is returned, and Slave
+    /// Select may remain active until the next transfer completes.
+    ///
+    /// Returns how many bytes were written.
+    pub fn write_stream<R: Read>(&mut self, mut reader: R) -> Result<usize> {
+        let _cs = self.software_cs.as_ref().map(SoftwareCs::select);
+
+        let mut current = vec![0u8; self.bufsiz];
+        let mut next = vec![0u8; self.bufsiz];
+        let mut current_len = read_chunk(&mut reader, &mut current)?;
+        let mut total = 0;
+
+        while current_len > 0 {
+            // Read ahead, so we know whether to release Slave Select after this transfer
+            let next_len = read_chunk(&mut reader, &mut next)?;
+
+            let mut segment = Segment::with_write(&current[..current_len]);
+            segment.set_ss_change(next_len > 0);
+            ioctl::transfer(self.spidev.as_raw_fd(), &[segment])?;
+
+            total += current_len;
+            mem::swap(&mut current, &mut next);
+            current_len = next_len;
+        }
+
+        Ok(total)
     }
 
     /// Sends and receives data at the same time.
@@ -661,13 +836,35 @@ impl Spi {
     /// Slave Select is set to active at the start of the transfer, and inactive
     /// when the transfer completes.
     ///
+    /// If the buffers exceed `spidev`'s buffer size limit, the transfer is split into
+    /// multiple transfers, while keeping Slave Select active.
+    ///
     /// Returns how many bytes were transferred.
     pub fn transfer(&self, read_buffer: &mut [u8], write_buffer: &[u8]) -> Result<usize> {
-        let segment = Segment::new(read_buffer, write_buffer);
+        let _cs = self.software_cs.as_ref().map(SoftwareCs::select);
+
+        let len = read_buffer.len().min(write_buffer.len());
+        if len <= self.bufsiz {
+            let segment = Segment::new(read_buffer, write_buffer);
 
-        ioctl::transfer(self.spidev.as_raw_fd(), &[segment])?;
+            ioctl::transfer(self.spidev.as_raw_fd(), &[segment])?;
 
-        Ok(segment.len())
+            return Ok(segment.len());
+        }
+
+        for (index, (read_chunk, write_chunk)) in read_buffer[..len]
+            .chunks_mut(self.bufsiz)
+            .zip(write_buffer[..len].chunks(self.bufsiz))
+            .enumerate()
+        {
+            let mut segment = Segment::new(read_chunk, write_chunk);
+            // Keep Slave Select active after every transfer except the last one
+            segment.set_ss_change((index + 1) * self.bufsiz < len);
+
+            ioctl::transfer(self.spidev.as_raw_fd(), &[segment])?;
+        }
+
+        Ok(len)
     }
 
     /// Transfers multiple half-duplex or full-duplex segments.
@@ -680,15 +877,91 @@ impl Spi {
     /// By default, Slave Select stays active until all segments have been
     /// transferred. You can change this behavior using [`Segment::set_ss_change`].
     ///
+    /// All segments are submitted to `spidev` in a single ioctl call, so the
+    /// timing between segments is handled by the kernel driver. Use
+    /// [`Segment::set_delay`] to add a delay after a segment, before the
+    /// (optional) Slave Select change, and [`Segment::set_clock_speed`] to
+    /// override the clock speed for a specific segment.
+    ///
+    /// `transfer_segments` isn't split into multiple transfers. Returns
+    /// `Err(`[`Error::TransferTooLarge`]`)` without transferring any data if the
+    /// combined length of all segments exceeds [`max_transfer_size`].
+    ///
     /// [`Segment`]: struct.Segment.html
     /// [`Segment::set_ss_change`]: struct.Segment.html#method.set_ss_change
+    /// [`Segment::set_delay`]: struct.Segment.html#method.set_delay
+    /// [`Segment::set_clock_speed`]: struct.Segment.html#method.set_clock_speed
+    /// [`Error::TransferTooLarge`]: enum.Error.html#variant.TransferTooLarge
+    /// [`max_transfer_size`]: #method.max_transfer_size
     pub fn transfer_segments(&self, segments: &[Segment<'_, '_>]) -> Result<()> {
+        let len: usize = segments.iter().map(Segment::len).sum();
+        if len > self.bufsiz {
+            return Err(Error::TransferTooLarge(len));
+        }
+
+        let _cs = self.software_cs.as_ref().map(SoftwareCs::select);
+
         ioctl::transfer(self.spidev.as_raw_fd(), segments)?;
 
         Ok(())
     }
 }
 
+// GPIO pin used as a software-controlled Slave Select line. The OutputPin is kept
+// around so the pin stays configured as an output, while the handle is used to change
+// its level from methods that only borrow Spi.
+struct SoftwareCs {
+    _pin: OutputPin,
+    handle: OutputHandle,
+    active_level: Level,
+}
+
+impl SoftwareCs {
+    // Activates the Slave Select line until the returned guard goes out of scope
+    fn select(&self) -> SoftwareCsGuard<'_> {
+        self.handle.write(self.active_level);
+
+        SoftwareCsGuard { software_cs: self }
+    }
+}
+
+struct SoftwareCsGuard<'a> {
+    software_cs: &'a SoftwareCs,
+}
+
+impl Drop for SoftwareCsGuard<'_> {
+    fn drop(&mut self) {
+        self.software_cs
+            .handle
+            .write(!self.software_cs.active_level);
+    }
+}
+
+// Returns spidev's buffer size limit, or the default limit if it can't be determined
+fn read_bufsiz() -> usize {
+    fs::read_to_string(PATH_BUFSIZ)
+        .ok()
+        .and_then(|bufsiz| bufsiz.trim().parse::<usize>().ok())
+        .filter(|&bufsiz| bufsiz > 0)
+        .unwrap_or(DEFAULT_BUFSIZ)
+}
+
+// Fills buffer from reader, and returns the number of bytes read. Only returns less
+// than buffer.len() when the end of the data is reached.
+fn read_chunk<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(bytes_read) => len += bytes_read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(Error::Io(e)),
+        }
+    }
+
+    Ok(len)
+}
+
 // Send is safe for Spi, but we're marked !Send because of the dummy pointer that's
 // needed to force !Sync.
 unsafe impl Send for Spi {}
@@ -0,0 +1,95 @@
+use std::io;
+
+use super::{Error, Mode, Result, Spi};
+
+// The MCP3008 is specified up to 1.35 MHz at 2.7 V, and 3.6 MHz at 5 V
+const CLOCK_SPEED: u32 = 1_000_000;
+
+const CHANNELS: u8 = 8;
+
+// The start bit is followed by the single-ended/differential bit and the three channel
+// select bits. Aligning the start bit to the end of the first byte places the 10-bit
+// result at the end of the third byte.
+const START_BIT: u8 = 0x01;
+const MODE_SINGLE_ENDED: u8 = 0x80;
+
+/// Microchip MCP3008 8-channel 10-bit analog-to-digital converter.
+///
+/// `Mcp3008` communicates with the ADC through [`Spi`], and takes care of building the
+/// command bytes and decoding the conversion result. Each conversion takes a single
+/// 3-byte transfer, and returns a value between 0 and 1023, where 1023 corresponds to the
+/// voltage on the VREF pin.
+///
+/// Connect the ADC's CS/SHDN pin to the Slave Select pin of the selected SPI bus, and
+/// power it from 3.3 V, so its DOUT pin doesn't exceed the Raspberry Pi's maximum input
+/// voltage.
+///
+/// [`Spi`]: struct.Spi.html
+#[derive(Debug)]
+pub struct Mcp3008 {
+    spi: Spi,
+}
+
+impl Mcp3008 {
+    /// Constructs a new `Mcp3008`.
+    ///
+    /// `new` reconfigures `spi` to use [`Mode0`] and a clock speed of 1 MHz, which stays
+    /// within the MCP3008's limits at any supported supply voltage.
+    ///
+    /// [`Mode0`]: enum.Mode.html#variant.Mode0
+    pub fn new(spi: Spi) -> Result<Mcp3008> {
+        spi.set_mode(Mode::Mode0)?;
+        spi.set_clock_speed(CLOCK_SPEED)?;
+
+        Ok(Mcp3008 { spi })
+    }
+
+    /// Performs a single-ended conversion on the specified channel (0-7), and returns the
+    /// 10-bit result.
+    pub fn read_channel(&self, channel: u8) -> Result<u16> {
+        check_channel(channel)?;
+
+        self.convert(MODE_SINGLE_ENDED | (channel << 4))
+    }
+
+    /// Performs a differential conversion on a pair of channels, and returns the 10-bit
+    /// result.
+    ///
+    /// `pair` selects the channels and their polarity, as listed in the MCP3008 datasheet.
+    /// An even `pair` measures channel `pair` as IN+ and channel `pair + 1` as IN-. An odd
+    /// `pair` swaps the polarity, so `1` measures channel 0 as IN- and channel 1 as IN+.
+    /// The result is 0 whenever IN+ is below IN-.
+    pub fn read_differential(&self, pair: u8) -> Result<u16> {
+        check_channel(pair)?;
+
+        self.convert(pair << 4)
+    }
+
+    /// Consumes the `Mcp3008`, and returns the underlying [`Spi`].
+    ///
+    /// [`Spi`]: struct.Spi.html
+    pub fn into_inner(self) -> Spi {
+        self.spi
+    }
+
+    fn convert(&self, command: u8) -> Result<u16> {
+        let write_buffer = [START_BIT, command, 0];
+        let mut read_buffer = [0u8; 3];
+
+        self.spi.transfer(&mut read_buffer, &write_buffer)?;
+
+        // The ADC outputs a null bit, followed by the result's 10 bits, MSB first
+        Ok((u16::from(read_buffer[1] & 0x03) << 8) | u16::from(read_buffer[2]))
+    }
+}
+
+fn check_channel(channel: u8) -> Result<()> {
+    if channel >= CHANNELS {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "MCP3008 channel needs to be between 0 and 7",
+        )));
+    }
+
+    Ok(())
+}
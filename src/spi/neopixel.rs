@@ -0,0 +1,180 @@
+use std::io;
+
+use super::{Error, Mode, Result, Spi};
+
+// WS2812 bit timing is emulated by sending 3 SPI bits for every data bit. At
+// 2.4 MHz, each SPI bit takes ~417 ns, which results in a ~417 ns high / ~833 ns
+// low signal for a 0, and a ~833 ns high / ~417 ns low signal for a 1.
+const CLOCK_SPEED: u32 = 2_400_000;
+// The bit timing stays within the WS2812 tolerances as long as the actual clock speed is
+// within 10% of CLOCK_SPEED
+const CLOCK_SPEED_TOLERANCE: u32 = CLOCK_SPEED / 10;
+// Encoded 0 and 1 bits
+const BIT_0: u8 = 0b100;
+const BIT_1: u8 = 0b110;
+// Each color channel is encoded as 8 * 3 = 24 SPI bits
+const BYTES_PER_CHANNEL: usize = 3;
+const BYTES_PER_PIXEL: usize = BYTES_PER_CHANNEL * 3;
+// The latch signal requires the data line to be held low for at least 50 µs on
+// older WS2812 LEDs, and 280 µs on newer WS2812B revisions. 90 bytes at 2.4 MHz
+// equals 300 µs.
+const RESET_BYTES: usize = 90;
+
+/// Drives a strip of WS2812 (NeoPixel) addressable LEDs through the SPI MOSI line.
+///
+/// WS2812 LEDs use a single-wire protocol with strict timing requirements. `NeoPixelStrip`
+/// emulates the required signal by encoding each data bit as 3 SPI bits at a clock
+/// speed of 2.4 MHz, which avoids the timing issues associated with bit-banging the
+/// protocol on a GPIO pin.
+///
+/// Connect the data input of the first LED to the MOSI pin of the selected SPI bus.
+/// The Slave Select and SCLK pins aren't used. Because WS2812 LEDs expect a 5 V signal,
+/// you may need a level shifter between the Raspberry Pi and the LED strip.
+///
+/// Each LED requires 9 bytes of SPI data, followed by 90 bytes for the latch signal at
+/// the end of every update. The entire update needs to fit in a single transfer, since
+/// the gap between two consecutive transfers can exceed the latch time, which makes the
+/// LEDs latch a partial update. With the default `spidev` buffer size of 4096 bytes, a
+/// single strip is therefore limited to 445 LEDs. [`show`] returns an error for longer
+/// strips, unless the buffer size is increased as described [here].
+///
+/// [`show`]: #method.show
+/// [here]: index.html#buffer-size-limits
+#[derive(Debug)]
+pub struct NeoPixelStrip {
+    spi: Spi,
+    buffer: Vec<u8>,
+    len: usize,
+}
+
+impl NeoPixelStrip {
+    /// Constructs a new `NeoPixelStrip` for `len` LEDs.
+    ///
+    /// `new` reconfigures `spi` to use [`Mode0`] and a clock speed of 2.4 MHz.
+    /// All LEDs are initially set to off, but the strip isn't updated until
+    /// [`show`] is called.
+    ///
+    /// The clock speed is read back after it's changed, since the SPI driver may not be
+    /// able to generate 2.4 MHz exactly. Returns an [`Error::Io`] error if the clock speed
+    /// reported by the driver differs from 2.4 MHz by more than 10%, which would violate
+    /// the WS2812 bit timing.
+    ///
+    /// [`Mode0`]: enum.Mode.html#variant.Mode0
+    /// [`show`]: #method.show
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn new(spi: Spi, len: usize) -> Result<NeoPixelStrip> {
+        spi.set_mode(Mode::Mode0)?;
+        spi.set_clock_speed(CLOCK_SPEED)?;
+
+        let clock_speed = spi.clock_speed()?;
+        if !(CLOCK_SPEED - CLOCK_SPEED_TOLERANCE..=CLOCK_SPEED + CLOCK_SPEED_TOLERANCE)
+            .contains(&clock_speed)
+        {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "SPI clock speed of {} Hz is too far off the 2.4 MHz required for WS2812 LEDs",
+                    clock_speed
+                ),
+            )));
+        }
+
+        let mut strip = NeoPixelStrip {
+            spi,
+            buffer: vec![0u8; (len * BYTES_PER_PIXEL) + RESET_BYTES],
+            len,
+        };
+
+        strip.clear();
+
+        Ok(strip)
+    }
+
+    /// Returns the number of LEDs.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the strip doesn't contain any LEDs.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sets the color of the LED at position `index`.
+    ///
+    /// The change isn't visible until [`show`] is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// [`show`]: #method.show
+    pub fn set_pixel(&mut self, index: usize, red: u8, green: u8, blue: u8) {
+        assert!(
+            index < self.len,
+            "index out of bounds: the len is {} but the index is {}",
+            self.len,
+            index
+        );
+
+        // WS2812 LEDs expect the color channels in GRB order
+        let offset = index * BYTES_PER_PIXEL;
+        for (channel, value) in [green, red, blue].iter().enumerate() {
+            let start = offset + channel * BYTES_PER_CHANNEL;
+            encode_channel(*value, &mut self.buffer[start..start + BYTES_PER_CHANNEL]);
+        }
+    }
+
+    /// Sets all LEDs to off.
+    ///
+    /// The change isn't visible until [`show`] is called.
+    ///
+    /// [`show`]: #method.show
+    pub fn clear(&mut self) {
+        for index in 0..self.len {
+            self.set_pixel(index, 0, 0, 0);
+        }
+    }
+
+    /// Sends the current colors to the LED strip.
+    ///
+    /// Returns `Err(`[`Error::TransferTooLarge`]`)` without sending any data if the
+    /// encoded colors and latch signal exceed [`Spi::max_transfer_size`].
+    ///
+    /// [`Error::TransferTooLarge`]: enum.Error.html#variant.TransferTooLarge
+    /// [`Spi::max_transfer_size`]: struct.Spi.html#method.max_transfer_size
+    pub fn show(&mut self) -> Result<()> {
+        if self.buffer.len() > self.spi.max_transfer_size() {
+            return Err(Error::TransferTooLarge(self.buffer.len()));
+        }
+
+        self.spi.write(&self.buffer)?;
+
+        Ok(())
+    }
+
+    /// Consumes the `NeoPixelStrip` and returns the underlying [`Spi`] instance.
+    ///
+    /// [`Spi`]: struct.Spi.html
+    pub fn into_inner(self) -> Spi {
+        self.spi
+    }
+}
+
+// Encodes a single color channel value as 24 SPI bits, MSB first.
+fn encode_channel(value: u8, buffer: &mut [u8]) {
+    let mut bits: u32 = 0;
+
+    for bit in (0..8).rev() {
+        bits = (bits << 3)
+            | u32::from(if (value >> bit) & 0x01 > 0 {
+                BIT_1
+            } else {
+                BIT_0
+            });
+    }
+
+    buffer[0] = (bits >> 16) as u8;
+    buffer[1] = (bits >> 8) as u8;
+    buffer[2] = bits as u8;
+}
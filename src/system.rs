@@ -2,13 +2,28 @@
 //!
 //! Use [`DeviceInfo`] to identify the Raspberry Pi's model and SoC.
 //!
+//! [`cpu_temperature`] and [`throttled_state`] can be used to monitor the SoC's
+//! temperature and throttling state, for instance to control a cooling fan.
+//!
+//! [`hat_info`] identifies the HAT attached to the GPIO header, if it has an ID EEPROM.
+//!
+//! [`board_serial`], [`revision_code`] and [`board_info`] return the board's unique serial
+//! number and details decoded from its revision code.
+//!
 //! [`DeviceInfo`]: struct.DeviceInfo.html
+//! [`cpu_temperature`]: fn.cpu_temperature.html
+//! [`throttled_state`]: fn.throttled_state.html
+//! [`hat_info`]: fn.hat_info.html
+//! [`board_serial`]: fn.board_serial.html
+//! [`revision_code`]: fn.revision_code.html
+//! [`board_info`]: fn.board_info.html
 
 use std::error;
 use std::fmt;
 use std::fs;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
 use std::result;
 
 // Peripheral base address
@@ -21,6 +36,66 @@ const PERIPHERAL_BASE_RP1: u32 = 0x4000_0000;
 const GPIO_OFFSET: u32 = 0x20_0000;
 const GPIO_OFFSET_RP1: u32 = 0x0d_0000;
 
+// BCM GPIO pins exposed on the 26-pin P1 header (and P5 header, if available) on the
+// original Raspberry Pi models
+const HEADER_PINS_RPI_B_REV1: u64 = (1 << 0)
+    | (1 << 1)
+    | (1 << 4)
+    | (1 << 7)
+    | (1 << 8)
+    | (1 << 9)
+    | (1 << 10)
+    | (1 << 11)
+    | (1 << 14)
+    | (1 << 15)
+    | (1 << 17)
+    | (1 << 18)
+    | (1 << 21)
+    | (1 << 22)
+    | (1 << 23)
+    | (1 << 24)
+    | (1 << 25);
+const HEADER_PINS_RPI_B_REV2: u64 = (1 << 2)
+    | (1 << 3)
+    | (1 << 4)
+    | (1 << 7)
+    | (1 << 8)
+    | (1 << 9)
+    | (1 << 10)
+    | (1 << 11)
+    | (1 << 14)
+    | (1 << 15)
+    | (1 << 17)
+    | (1 << 18)
+    | (1 << 22)
+    | (1 << 23)
+    | (1 << 24)
+    | (1 << 25)
+    | (1 << 27)
+    | (1 << 28)
+    | (1 << 29)
+    | (1 << 30)
+    | (1 << 31);
+// Models with a 40-pin header, and compute modules, don't restrict pin access
+const HEADER_PINS_ALL: u64 = u64::MAX;
+
+// Thermal zone for the SoC's temperature sensor
+const PATH_THERMAL_ZONE0: &str = "/sys/class/thermal/thermal_zone0/temp";
+// The firmware driver exposes the same value that's returned by vcgencmd get_throttled
+const PATH_GET_THROTTLED: &str = "/sys/devices/platform/soc/soc:firmware/get_throttled";
+const PATH_PROC_CPUINFO: &str = "/proc/cpuinfo";
+const PATH_DEVICE_TREE_SERIAL: &str = "/sys/firmware/devicetree/base/serial-number";
+// Populated by the firmware with the contents of the HAT ID EEPROM
+const PATH_DEVICE_TREE_HAT: &str = "/proc/device-tree/hat";
+
+// get_throttled bits
+const THROTTLED_UNDER_VOLTAGE: u32 = 1 << 0;
+const THROTTLED_ARM_FREQUENCY_CAPPED: u32 = 1 << 1;
+const THROTTLED_THROTTLED: u32 = 1 << 2;
+const THROTTLED_SOFT_TEMPERATURE_LIMIT: u32 = 1 << 3;
+// Sticky bits, set when the matching condition has occurred since boot
+const THROTTLED_HISTORY_SHIFT: u32 = 16;
+
 // Number of GPIO lines
 const GPIO_LINES_BCM283X: u8 = 54;
 const GPIO_LINES_BCM2711: u8 = 58;
@@ -45,18 +120,29 @@ pub enum Error {
     /// doesn't provide any of the common user-accessible system files
     /// that are used to identify the model and SoC.
     UnknownModel,
+    /// I/O error.
+    ///
+    /// A system file couldn't be read, or its contents couldn't be parsed.
+    Io(io::Error),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::UnknownModel => write!(f, "Unknown Raspberry Pi model"),
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
         }
     }
 }
 
 impl error::Error for Error {}
 
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
 /// Result type returned from methods that can have `system::Error`s.
 pub type Result<T> = result::Result<T, Error>;
 
@@ -188,27 +274,9 @@ fn parse_proc_cpuinfo() -> Result<Model> {
             Err(_) => return Err(Error::UnknownModel),
         };
 
-        match revision_type {
-            0x00 => Model::RaspberryPiA,
-            0x01 => Model::RaspberryPiBRev2,
-            0x02 => Model::RaspberryPiAPlus,
-            0x03 => Model::RaspberryPiBPlus,
-            0x04 => Model::RaspberryPi2B,
-            0x06 => Model::RaspberryPiComputeModule,
-            0x08 => Model::RaspberryPi3B,
-            0x09 => Model::RaspberryPiZero,
-            0x0a => Model::RaspberryPiComputeModule3,
-            0x0c => Model::RaspberryPiZeroW,
-            0x0d => Model::RaspberryPi3BPlus,
-            0x0e => Model::RaspberryPi3APlus,
-            0x10 => Model::RaspberryPiComputeModule3Plus,
-            0x11 => Model::RaspberryPi4B,
-            0x12 => Model::RaspberryPiZero2W,
-            0x13 => Model::RaspberryPi400,
-            0x14 => Model::RaspberryPiComputeModule4,
-            0x15 => Model::RaspberryPiComputeModule4S,
-            0x17 => Model::RaspberryPi5,
-            _ => return Err(Error::UnknownModel),
+        match model_from_type(revision_type as u32) {
+            Some(model) => model,
+            None => return Err(Error::UnknownModel),
         }
     } else {
         return Err(Error::UnknownModel);
@@ -217,6 +285,32 @@ fn parse_proc_cpuinfo() -> Result<Model> {
     Ok(model)
 }
 
+// Returns the model for the type field (bits 4-11) of a new-style revision code
+fn model_from_type(revision_type: u32) -> Option<Model> {
+    Some(match revision_type {
+        0x00 => Model::RaspberryPiA,
+        0x01 => Model::RaspberryPiBRev2,
+        0x02 => Model::RaspberryPiAPlus,
+        0x03 => Model::RaspberryPiBPlus,
+        0x04 => Model::RaspberryPi2B,
+        0x06 => Model::RaspberryPiComputeModule,
+        0x08 => Model::RaspberryPi3B,
+        0x09 => Model::RaspberryPiZero,
+        0x0a => Model::RaspberryPiComputeModule3,
+        0x0c => Model::RaspberryPiZeroW,
+        0x0d => Model::RaspberryPi3BPlus,
+        0x0e => Model::RaspberryPi3APlus,
+        0x10 => Model::RaspberryPiComputeModule3Plus,
+        0x11 => Model::RaspberryPi4B,
+        0x12 => Model::RaspberryPiZero2W,
+        0x13 => Model::RaspberryPi400,
+        0x14 => Model::RaspberryPiComputeModule4,
+        0x15 => Model::RaspberryPiComputeModule4S,
+        0x17 => Model::RaspberryPi5,
+        _ => return None,
+    })
+}
+
 // Identify Pi model based on /sys/firmware/devicetree/base/compatible
 fn parse_base_compatible() -> Result<Model> {
     let base_compatible = match fs::read_to_string("/sys/firmware/devicetree/base/compatible") {
@@ -318,6 +412,443 @@ fn parse_base_model() -> Result<Model> {
     Ok(model)
 }
 
+/// Reads the SoC's current temperature in degrees Celsius (°C).
+///
+/// The temperature is read from `/sys/class/thermal/thermal_zone0/temp`.
+pub fn cpu_temperature() -> Result<f32> {
+    let millidegrees: i32 = fs::read_to_string(PATH_THERMAL_ZONE0)?
+        .trim()
+        .parse()
+        .map_err(|_| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid temperature value",
+            ))
+        })?;
+
+    Ok(millidegrees as f32 / 1000.0)
+}
+
+/// Reads the firmware's current throttling state.
+///
+/// The state is read from `/sys/devices/platform/soc/soc:firmware/get_throttled`, which
+/// contains the same value that's returned by `vcgencmd get_throttled`. This file is
+/// provided by the firmware driver on recent Raspberry Pi OS kernels.
+pub fn throttled_state() -> Result<ThrottledState> {
+    let value = fs::read_to_string(PATH_GET_THROTTLED)?;
+    let value = value.trim();
+    let value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+
+    let bits = u32::from_str_radix(value, 16).map_err(|_| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid throttled state value",
+        ))
+    })?;
+
+    Ok(ThrottledState { bits })
+}
+
+/// Returns `true` if the firmware currently detects an under-voltage condition.
+///
+/// Under-voltage is reported when the supply voltage drops below approximately 4.63 V.
+/// Marginal power supplies or long, thin USB cables often cause brief voltage drops under
+/// load, which can lead to erratic behavior of peripherals connected to the GPIO header.
+/// Use [`throttle_history`] to check whether under-voltage has occurred at any point
+/// since boot.
+///
+/// [`throttle_history`]: fn.throttle_history.html
+pub fn undervoltage_detected() -> Result<bool> {
+    Ok(throttled_state()?.under_voltage())
+}
+
+/// Reads which throttling conditions have occurred since boot.
+///
+/// This is equivalent to calling [`ThrottledState::history`] on the value returned by
+/// [`throttled_state`].
+///
+/// [`ThrottledState::history`]: struct.ThrottledState.html#method.history
+/// [`throttled_state`]: fn.throttled_state.html
+pub fn throttle_history() -> Result<ThrottleHistory> {
+    Ok(throttled_state()?.history())
+}
+
+/// Firmware throttling state, as returned by [`throttled_state`].
+///
+/// [`throttled_state`]: fn.throttled_state.html
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ThrottledState {
+    bits: u32,
+}
+
+impl ThrottledState {
+    /// Returns the raw bit field, as returned by `vcgencmd get_throttled`.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Returns `true` if an under-voltage condition is currently detected.
+    pub fn under_voltage(&self) -> bool {
+        self.bits & THROTTLED_UNDER_VOLTAGE > 0
+    }
+
+    /// Returns `true` if the ARM frequency is currently capped.
+    pub fn arm_frequency_capped(&self) -> bool {
+        self.bits & THROTTLED_ARM_FREQUENCY_CAPPED > 0
+    }
+
+    /// Returns `true` if the SoC is currently throttled.
+    pub fn throttled(&self) -> bool {
+        self.bits & THROTTLED_THROTTLED > 0
+    }
+
+    /// Returns `true` if the soft temperature limit is currently active.
+    pub fn soft_temperature_limit(&self) -> bool {
+        self.bits & THROTTLED_SOFT_TEMPERATURE_LIMIT > 0
+    }
+
+    /// Returns the throttling conditions that have occurred since boot.
+    pub fn history(&self) -> ThrottleHistory {
+        ThrottleHistory {
+            bits: (self.bits >> THROTTLED_HISTORY_SHIFT) & 0x0f,
+        }
+    }
+}
+
+/// Throttling conditions that have occurred since boot, as returned by
+/// [`throttle_history`] or [`ThrottledState::history`].
+///
+/// The firmware sets these flags the first time a condition is detected, and doesn't
+/// clear them until the next reboot.
+///
+/// [`throttle_history`]: fn.throttle_history.html
+/// [`ThrottledState::history`]: struct.ThrottledState.html#method.history
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ThrottleHistory {
+    bits: u32,
+}
+
+impl ThrottleHistory {
+    /// Returns `true` if an under-voltage condition has occurred.
+    pub fn under_voltage_occurred(&self) -> bool {
+        self.bits & THROTTLED_UNDER_VOLTAGE > 0
+    }
+
+    /// Returns `true` if the ARM frequency has been capped.
+    pub fn arm_frequency_capped_occurred(&self) -> bool {
+        self.bits & THROTTLED_ARM_FREQUENCY_CAPPED > 0
+    }
+
+    /// Returns `true` if the SoC has been throttled.
+    pub fn throttled_occurred(&self) -> bool {
+        self.bits & THROTTLED_THROTTLED > 0
+    }
+
+    /// Returns `true` if the soft temperature limit has been active.
+    pub fn soft_temperature_limit_occurred(&self) -> bool {
+        self.bits & THROTTLED_SOFT_TEMPERATURE_LIMIT > 0
+    }
+}
+
+/// Returns information about the attached HAT, as stored on its ID EEPROM.
+///
+/// HATs carry an I2C EEPROM connected to the ID_SD and ID_SC pins (BCM GPIO 0 and 1,
+/// physical pins 27 and 28). The firmware reads the EEPROM during boot, and makes
+/// its contents available through the device tree at `/proc/device-tree/hat`.
+/// `hat_info` reads the device tree rather than the EEPROM itself, because the I2C0 bus
+/// used for the ID EEPROM usually isn't accessible from Linux once the system has booted.
+///
+/// Returns `None` if no HAT is attached, the attached board doesn't have an ID EEPROM,
+/// or the EEPROM doesn't contain a valid vendor info atom.
+pub fn hat_info() -> Option<HatInfo> {
+    let path = Path::new(PATH_DEVICE_TREE_HAT);
+
+    let read_string = |name: &str| -> Option<String> {
+        let value = fs::read(path.join(name)).ok()?;
+        let value = String::from_utf8_lossy(&value);
+
+        Some(value.trim_end_matches('\0').trim().to_owned())
+    };
+
+    let read_hex = |name: &str| -> Option<u16> {
+        let value = read_string(name)?;
+        let value = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(&value);
+
+        u16::from_str_radix(value, 16).ok()
+    };
+
+    Some(HatInfo {
+        vendor: read_string("vendor")?,
+        product: read_string("product")?,
+        product_id: read_hex("product_id")?,
+        product_version: read_hex("product_ver")?,
+        uuid: read_string("uuid")?,
+    })
+}
+
+/// HAT vendor and product information, as returned by [`hat_info`].
+///
+/// [`hat_info`]: fn.hat_info.html
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HatInfo {
+    vendor: String,
+    product: String,
+    product_id: u16,
+    product_version: u16,
+    uuid: String,
+}
+
+impl HatInfo {
+    /// Returns the vendor name.
+    pub fn vendor(&self) -> &str {
+        &self.vendor
+    }
+
+    /// Returns the product name.
+    pub fn product(&self) -> &str {
+        &self.product
+    }
+
+    /// Returns the vendor-assigned product ID.
+    pub fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    /// Returns the vendor-assigned product version.
+    pub fn product_version(&self) -> u16 {
+        self.product_version
+    }
+
+    /// Returns the UUID that uniquely identifies this particular board, formatted as
+    /// a hyphenated string.
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+}
+
+// Revision code bit fields (new-style revision codes)
+const REVISION_NEW_STYLE: u32 = 1 << 23;
+const REVISION_MEMORY_SHIFT: u32 = 20;
+const REVISION_MANUFACTURER_SHIFT: u32 = 16;
+const REVISION_TYPE_SHIFT: u32 = 4;
+
+// Returns the value of the first /proc/cpuinfo line that starts with key
+fn read_cpuinfo_value(key: &str) -> Result<Option<String>> {
+    let proc_cpuinfo = BufReader::new(File::open(PATH_PROC_CPUINFO)?);
+
+    for line in proc_cpuinfo.lines() {
+        let line = line?;
+        if let Some((line_key, line_value)) = line.split_once(':') {
+            if line_key.trim() == key {
+                return Ok(Some(line_value.trim().to_owned()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn parse_hex(value: &str) -> Result<u64> {
+    let value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+
+    u64::from_str_radix(value, 16).map_err(|_| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid hexadecimal value",
+        ))
+    })
+}
+
+/// Reads the board's unique serial number.
+///
+/// The serial number is read from the `Serial` field in `/proc/cpuinfo`, or from
+/// `/sys/firmware/devicetree/base/serial-number` if `/proc/cpuinfo` doesn't contain a
+/// serial number. It matches the serial number reported by the firmware, and is commonly
+/// used to identify individual devices.
+pub fn board_serial() -> Result<u64> {
+    let serial = match read_cpuinfo_value("Serial")? {
+        Some(serial) => serial,
+        None => {
+            let serial = fs::read(PATH_DEVICE_TREE_SERIAL)?;
+            String::from_utf8_lossy(&serial)
+                .trim_end_matches('\0')
+                .trim()
+                .to_owned()
+        }
+    };
+
+    parse_hex(&serial)
+}
+
+/// Reads the board's revision code.
+///
+/// The revision code is read from the `Revision` field in `/proc/cpuinfo`. Use
+/// [`board_info`] to decode the revision code.
+///
+/// [`board_info`]: fn.board_info.html
+pub fn revision_code() -> Result<u32> {
+    match read_cpuinfo_value("Revision")? {
+        Some(revision) => Ok(parse_hex(&revision)? as u32),
+        None => Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Revision code not found",
+        ))),
+    }
+}
+
+/// Reads and decodes the board's revision code.
+///
+/// Both new-style revision codes, and the old-style revision codes used by the original
+/// Raspberry Pi A, A+, B, B+ and Compute Module, are supported. The warranty bits that
+/// are set on over-volted boards are ignored.
+///
+/// Returns `Err(`[`Error::UnknownModel`]`)` if the revision code doesn't match any known
+/// model, memory size or manufacturer.
+///
+/// [`Error::UnknownModel`]: enum.Error.html#variant.UnknownModel
+pub fn board_info() -> Result<BoardInfo> {
+    BoardInfo::from_revision_code(revision_code()?).ok_or(Error::UnknownModel)
+}
+
+/// Board manufacturers, as encoded in the revision code.
+///
+/// `Manufacturer` might be extended with additional variants in a minor or
+/// patch revision, and must not be exhaustively matched against.
+/// Instead, add a `_` catch-all arm to match future variants.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+pub enum Manufacturer {
+    SonyUk,
+    Egoman,
+    Embest,
+    SonyJapan,
+    Stadium,
+    Qisda,
+}
+
+impl fmt::Display for Manufacturer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Manufacturer::SonyUk => write!(f, "Sony UK"),
+            Manufacturer::Egoman => write!(f, "Egoman"),
+            Manufacturer::Embest => write!(f, "Embest"),
+            Manufacturer::SonyJapan => write!(f, "Sony Japan"),
+            Manufacturer::Stadium => write!(f, "Stadium"),
+            Manufacturer::Qisda => write!(f, "Qisda"),
+        }
+    }
+}
+
+/// Board details decoded from the revision code, as returned by [`board_info`].
+///
+/// [`board_info`]: fn.board_info.html
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct BoardInfo {
+    model: Model,
+    revision: (u8, u8),
+    memory: u32,
+    manufacturer: Manufacturer,
+}
+
+impl BoardInfo {
+    /// Decodes the specified revision code.
+    ///
+    /// Returns `None` if the revision code doesn't match any known model, memory size
+    /// or manufacturer.
+    pub fn from_revision_code(code: u32) -> Option<BoardInfo> {
+        if code & REVISION_NEW_STYLE == 0 {
+            return BoardInfo::from_old_style(code & 0xffff);
+        }
+
+        let model = model_from_type((code >> REVISION_TYPE_SHIFT) & 0xff)?;
+        let memory = match (code >> REVISION_MEMORY_SHIFT) & 0b111 {
+            memory @ 0..=6 => 256 << memory,
+            _ => return None,
+        };
+        let manufacturer = match (code >> REVISION_MANUFACTURER_SHIFT) & 0b1111 {
+            0 => Manufacturer::SonyUk,
+            1 => Manufacturer::Egoman,
+            2 | 4 => Manufacturer::Embest,
+            3 => Manufacturer::SonyJapan,
+            5 => Manufacturer::Stadium,
+            _ => return None,
+        };
+
+        Some(BoardInfo {
+            model,
+            revision: (1, (code & 0b1111) as u8),
+            memory,
+            manufacturer,
+        })
+    }
+
+    fn from_old_style(code: u32) -> Option<BoardInfo> {
+        use self::Manufacturer::*;
+        use self::Model::*;
+
+        let (model, revision, memory, manufacturer) = match code {
+            0x0002 | 0x0003 => (RaspberryPiBRev1, (1, 0), 256, Egoman),
+            0x0004 => (RaspberryPiBRev2, (2, 0), 256, SonyUk),
+            0x0005 => (RaspberryPiBRev2, (2, 0), 256, Qisda),
+            0x0006 => (RaspberryPiBRev2, (2, 0), 256, Egoman),
+            0x0007 => (RaspberryPiA, (2, 0), 256, Egoman),
+            0x0008 => (RaspberryPiA, (2, 0), 256, SonyUk),
+            0x0009 => (RaspberryPiA, (2, 0), 256, Qisda),
+            0x000d => (RaspberryPiBRev2, (2, 0), 512, Egoman),
+            0x000e => (RaspberryPiBRev2, (2, 0), 512, SonyUk),
+            0x000f => (RaspberryPiBRev2, (2, 0), 512, Egoman),
+            0x0010 => (RaspberryPiBPlus, (1, 2), 512, SonyUk),
+            0x0011 => (RaspberryPiComputeModule, (1, 0), 512, SonyUk),
+            0x0012 => (RaspberryPiAPlus, (1, 1), 256, SonyUk),
+            0x0013 => (RaspberryPiBPlus, (1, 2), 512, Embest),
+            0x0014 => (RaspberryPiComputeModule, (1, 0), 512, Embest),
+            0x0015 => (RaspberryPiAPlus, (1, 1), 256, Embest),
+            _ => return None,
+        };
+
+        Some(BoardInfo {
+            model,
+            revision,
+            memory,
+            manufacturer,
+        })
+    }
+
+    /// Returns the Raspberry Pi model.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Returns the PCB revision as a `(major, minor)` pair, for instance `(1, 2)` for
+    /// revision 1.2.
+    pub fn revision(&self) -> (u8, u8) {
+        self.revision
+    }
+
+    /// Returns the amount of memory in megabytes (MB).
+    ///
+    /// Some of the Raspberry Pi A+ boards manufactured by Embest (revision code `0015`)
+    /// shipped with 512 MB instead of 256 MB, which can't be determined from the
+    /// revision code.
+    pub fn memory(&self) -> u32 {
+        self.memory
+    }
+
+    /// Returns the board's manufacturer.
+    pub fn manufacturer(&self) -> Manufacturer {
+        self.manufacturer
+    }
+}
+
 /// Retrieves Raspberry Pi device information.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct DeviceInfo {
@@ -443,6 +974,7 @@ impl DeviceInfo {
     }
 
     /// Returns the offset from the peripheral base memory address for the GPIO section.
+    #[cfg_attr(feature = "cdev-only", allow(dead_code))]
     pub(crate) fn gpio_offset(&self) -> u32 {
         self.gpio_offset
     }
@@ -457,6 +989,18 @@ impl DeviceInfo {
         self.gpio_interface
     }
 
+    /// Returns a bit mask of the BCM GPIO pins exposed on the GPIO header(s).
+    ///
+    /// Only the original Raspberry Pi A and B models with a 26-pin header limit the
+    /// available pins. All other models return a mask with all bits set.
+    pub(crate) fn header_pins(&self) -> u64 {
+        match self.model {
+            Model::RaspberryPiBRev1 => HEADER_PINS_RPI_B_REV1,
+            Model::RaspberryPiA | Model::RaspberryPiBRev2 => HEADER_PINS_RPI_B_REV2,
+            _ => HEADER_PINS_ALL,
+        }
+    }
+
     /// Returns the PWM chip # used for hardware PWM.
     pub(crate) fn pwm_chip(&self) -> u8 {
         self.pwm_chip